@@ -0,0 +1,35 @@
+use crate::{AutoLaunch, Change, Result};
+
+/// Async variants, the work is run on the tokio blocking thread pool
+///
+/// The registry I/O, file writes and the `osascript` subprocess are blocking,
+/// these variants avoid stalling the async executor.
+///
+/// The `osascript` subprocess is also waited for on the blocking thread pool,
+/// instead of via `tokio::process`, since it is run through the same `CommandRunner`
+/// and login item logic as the sync API. Each call holds a blocking thread until it exits.
+impl AutoLaunch {
+    /// Enable the AutoLaunch setting, see `enable`
+    pub async fn enable_async(&self) -> Result<Change> {
+        let auto = self.clone();
+        tokio::task::spawn_blocking(move || auto.enable())
+            .await
+            .map_err(std::io::Error::from)?
+    }
+
+    /// Disable the AutoLaunch setting, see `disable`
+    pub async fn disable_async(&self) -> Result<Change> {
+        let auto = self.clone();
+        tokio::task::spawn_blocking(move || auto.disable())
+            .await
+            .map_err(std::io::Error::from)?
+    }
+
+    /// Check whether the AutoLaunch setting is enabled, see `is_enabled`
+    pub async fn is_enabled_async(&self) -> Result<bool> {
+        let auto = self.clone();
+        tokio::task::spawn_blocking(move || auto.is_enabled())
+            .await
+            .map_err(std::io::Error::from)?
+    }
+}
@@ -47,7 +47,7 @@
 //!     let app_name = "the-app";
 //!     let app_path = "/path/to/the-app.app";
 //!     let args = &["--minimized"];
-//!     let auto = AutoLaunch::new(app_name, app_path, false, args);
+//!     let auto = AutoLaunch::new(app_name, app_path, false, args, &[] as &[&str], "");
 //!
 //!     // enable the auto launch
 //!     auto.enable().is_ok();
@@ -63,17 +63,18 @@
 //! ### Windows
 //!
 //! On Windows, it will add a registry entry under `\HKEY_CURRENT_USER\SOFTWARE\Microsoft\Windows\CurrentVersion\Run`.
+//! The `enable_mode` decides whether to use `HKEY_LOCAL_MACHINE` or `HKEY_CURRENT_USER`, see `WindowsEnableMode`.
 //!
 //! ```rust
 //! # #[cfg(target_os = "windows")]
 //! # mod win {
-//! use auto_launch::AutoLaunch;
+//! use auto_launch::{AutoLaunch, WindowsEnableMode};
 //!
 //! fn main() {
 //!     let app_name = "the-app";
 //!     let app_path = "C:\\path\\to\\the-app.exe";
 //!     let args = &["--minimized"];
-//!     let auto = AutoLaunch::new(app_name, app_path, args);
+//!     let auto = AutoLaunch::new(app_name, app_path, WindowsEnableMode::Dynamic, args);
 //!
 //!     // enable the auto launch
 //!     auto.enable().is_ok();
@@ -111,6 +112,12 @@
 //! }
 //! ```
 //!
+//! ### Disabling in CI
+//!
+//! With the environment variable `AUTO_LAUNCH_DISABLE=1`, `enable` and `enable_once`
+//! return without touching the system and `is_enabled` returns false,
+//! e.g. so that the build machine of the packagers is not registered.
+//!
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -120,25 +127,516 @@ pub enum Error {
     AppPathNotSpecified,
     #[error("app path doesn't exist: {0}")]
     AppPathDoesntExist(std::path::PathBuf),
+    #[deprecated(note = "the relative app_path is resolved, never returned")]
     #[error("app path is not absolute: {0}")]
     AppPathIsNotAbsolute(std::path::PathBuf),
     #[error("Failed to execute apple script with status: {0}")]
     AppleScriptFailed(i32),
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
     #[error("Unsupported target os")]
     UnsupportedOS,
+    #[error("Launch Agent is unsupported in the App Sandbox")]
+    SandboxUnsupported,
+    #[error("the home dir of the current user is not found")]
+    HomeDirNotFound,
+    #[error("Backend unavailable: {0}")]
+    Backend(String),
+    #[error("the entry `{0}` was removed right after enabled")]
+    EntryRemoved(String),
+    #[error("the file belongs to another entry: {0}")]
+    Conflict(std::path::PathBuf),
+    #[error("failed to remove {} entries: {}", .0.len(), join_errors(.0))]
+    PurgeFailed(Vec<Error>),
     #[error(transparent)]
     Io(#[from] std::io::Error),
 }
 
+fn join_errors(errors: &[Error]) -> String {
+    let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+    errors.join("; ")
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The arg appended to the entry with `AutoLaunchBuilder::set_startup_sentinel`,
+/// see `AutoLaunch::launched_at_startup`
+pub const LAUNCHED_AT_STARTUP_ARG: &str = "--launched-at-startup";
+
+/// The action to plan with `AutoLaunch::plan`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Plan for `AutoLaunch::enable`
+    Enable,
+    /// Plan for `AutoLaunch::disable`
+    Disable,
+    /// Plan for `AutoLaunch::enable_once`
+    EnableOnce,
+}
+
+/// Whether `enable` or `disable` actually changed the system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The entry is written or removed
+    Changed,
+    /// The entry is already in the desired state, nothing is touched
+    Unchanged,
+}
+
+/// A change that `enable` or `disable` would make to the system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedChange {
+    /// Write the `content` to the file at `path`
+    WriteFile {
+        path: std::path::PathBuf,
+        content: String,
+    },
+    /// Remove the file at `path`
+    RemoveFile { path: std::path::PathBuf },
+    /// Create the symlink at `path` pointing to `target`
+    CreateSymlink {
+        path: std::path::PathBuf,
+        target: std::path::PathBuf,
+    },
+    /// Execute the AppleScript command via `osascript`
+    RunAppleScript { script: String },
+    /// Execute the external command
+    RunCommand { program: String, args: Vec<String> },
+    /// Set a string value under the registry key
+    SetRegistryString {
+        hive: String,
+        key: String,
+        name: String,
+        data: String,
+    },
+    /// Set an expandable string value (`REG_EXPAND_SZ`) under the registry key
+    SetRegistryExpandString {
+        hive: String,
+        key: String,
+        name: String,
+        data: String,
+    },
+    /// Set a binary value under the registry key
+    SetRegistryBytes {
+        hive: String,
+        key: String,
+        name: String,
+        data: Vec<u8>,
+    },
+    /// Remove the value from the registry key
+    RemoveRegistryValue {
+        hive: String,
+        key: String,
+        name: String,
+    },
+}
+
+/// The difference between the configured entry and the one on the system,
+/// see `AutoLaunch::diff`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateDiff {
+    /// The entry on the system matches the configured one
+    Matches,
+    /// The entry is not found on the system
+    Missing,
+    /// The entry on the system launches a different command
+    Differs { actual: String },
+}
+
+/// Why the entry is enabled or not, see `AutoLaunch::enabled_status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnabledStatus {
+    /// The entry is registered and launches the configured command
+    Enabled,
+    /// The entry is not found on the system
+    NotRegistered,
+    /// The entry is registered, but disabled by the OS or the user,
+    /// e.g. in Task Manager on Windows
+    RegisteredButDisabledByOs,
+    /// The entry is registered, but launches a different command, see `AutoLaunch::diff`
+    RegisteredButPathMismatch,
+}
+
+/// The `StartCalendarInterval` of the macOS Launch Agent,
+/// the unset fields match every value like `*` in crontab
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CalendarInterval {
+    /// The minute, 0-59
+    pub minute: Option<u8>,
+    /// The hour, 0-23
+    pub hour: Option<u8>,
+    /// The day of month, 1-31
+    pub day: Option<u8>,
+    /// The day of week, 0-7, both 0 and 7 are Sunday
+    pub weekday: Option<u8>,
+    /// The month, 1-12
+    pub month: Option<u8>,
+}
+
+/// An autostart entry registered on the system, see `AutoLaunch::list_entries`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    /// The entry name, which is the `app_name` when registered by this crate
+    pub name: String,
+    /// The command launched at startup
+    pub command: String,
+}
+
+/// The mechanism used to launch the app at startup, see `AutoLaunch::active_backend`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// The `Run` registry value on Windows
+    RegistryRun,
+    /// The login item via AppleScript on macOS
+    AppleScript,
+    /// The Launch Agent plist on macOS
+    LaunchAgent,
+    /// The XDG autostart desktop entry on Linux
+    DesktopEntry,
+    /// The systemd user unit on Linux, along with the desktop entry
+    SystemdUnit,
+}
+
+/// The target platform to render the entry for, see `render_entry`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// The XDG autostart desktop entry
+    Linux,
+    /// The Launch Agent plist
+    MacOS,
+    /// The command line of the `Run` registry value
+    Windows,
+}
+
+/// The registry scope to write the entry on Windows
+///
+/// It is accepted on every platform by `AutoLaunchBuilder`, but only works on Windows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WindowsEnableMode {
+    /// Use `HKEY_LOCAL_MACHINE` if it is writable, otherwise `HKEY_CURRENT_USER`
+    #[default]
+    Dynamic,
+    /// Use `HKEY_CURRENT_USER` only
+    CurrentUser,
+    /// Use `HKEY_LOCAL_MACHINE` only, which requires elevation
+    System,
+}
+
+/// The registry view of `HKEY_LOCAL_MACHINE` to write the entry on Windows
+///
+/// The 32-bit view is the `WOW6432Node` keys, read by the 32-bit apps and the
+/// `Run` scan alike, while the 64-bit view is the native keys.
+/// It is accepted on every platform by `AutoLaunchBuilder`, but only works on Windows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RegistryView {
+    /// The same as `Force32`, as the former versions did
+    #[default]
+    Default,
+    /// Use the 32-bit view, i.e. `SOFTWARE\WOW6432Node`
+    Force32,
+    /// Use the 64-bit view, which is only reachable from a 64-bit process
+    Force64,
+}
+
+/// Execute the external commands, e.g. `osascript` and `launchctl` on macOS
+///
+/// The default is `SystemCommandRunner`, it can be replaced by
+/// `AutoLaunchBuilder::set_command_runner`, e.g. with a fake one in tests.
+pub trait CommandRunner: std::fmt::Debug + Send + Sync {
+    /// Run the `program` with `args`, and wait for its output
+    fn run(&self, program: &str, args: &[&str]) -> std::io::Result<std::process::Output>;
+}
+
+/// The `CommandRunner` via `std::process::Command`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SystemCommandRunner;
+
+impl CommandRunner for SystemCommandRunner {
+    fn run(&self, program: &str, args: &[&str]) -> std::io::Result<std::process::Output> {
+        std::process::Command::new(program).args(args).output()
+    }
+}
+
+/// The shared `CommandRunner` held by `AutoLaunch`, compared by identity
+///
+/// `None` stands for `SystemCommandRunner`.
+#[cfg(target_os = "macos")]
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SharedCommandRunner(pub(crate) Option<std::sync::Arc<dyn CommandRunner>>);
+
+#[cfg(target_os = "macos")]
+impl PartialEq for SharedCommandRunner {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => std::sync::Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Eq for SharedCommandRunner {}
+
+/// Customize the generated entry content before it is written, e.g. to add a key
+///
+/// It receives the desktop entry on Linux and the Launch Agent plist on macOS,
+/// and returns the modified one. It is set by `AutoLaunchBuilder::set_entry_customizer`.
+pub trait EntryCustomizer: std::fmt::Debug + Send + Sync {
+    /// Modify the `content` of the entry, the result must still parse,
+    /// otherwise `enable` returns `Error::InvalidInput`
+    fn customize(&self, content: String) -> String;
+}
+
+/// The shared `EntryCustomizer` held by `AutoLaunch`, compared by identity
+///
+/// `None` keeps the content as is.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SharedEntryCustomizer(pub(crate) Option<std::sync::Arc<dyn EntryCustomizer>>);
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl SharedEntryCustomizer {
+    /// Customize the `content`, or keep it as is
+    pub(crate) fn apply(&self, content: String) -> String {
+        match &self.0 {
+            Some(customizer) => customizer.customize(content),
+            None => content,
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl PartialEq for SharedEntryCustomizer {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => std::sync::Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl Eq for SharedEntryCustomizer {}
+
+/// Canonicalize the `app_path` for writing and comparing the entries
+///
+/// - the leading `~` is expanded to the home dir
+/// - the relative path is resolved against the current dir
+/// - the symlinks are followed if the path exists
+///
+/// The empty path is kept as is.
+pub(crate) fn canonicalize_app_path(app_path: &str) -> String {
+    if app_path.is_empty() {
+        return app_path.into();
+    }
+
+    let mut path = std::path::PathBuf::from(app_path);
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Some(home) = get_home_dir() {
+            path = home.join(rest);
+        }
+    }
+    if let Ok(absolute) = std::path::absolute(&path) {
+        path = absolute;
+    }
+    if let Ok(canonical) = path.canonicalize() {
+        path = canonical;
+    }
+
+    let path = path.to_string_lossy();
+    // remove the verbatim prefix added by `canonicalize` on Windows
+    #[cfg(target_os = "windows")]
+    if let Some(rest) = path.strip_prefix(r"\\?\") {
+        return match rest.strip_prefix(r"UNC\") {
+            Some(unc) => format!(r"\\{}", unc),
+            None => rest.into(),
+        };
+    }
+    path.into_owned()
+}
+
+/// Get the home dir of the current user
+pub(crate) fn get_home_dir() -> Option<std::path::PathBuf> {
+    #[cfg(not(target_os = "windows"))]
+    return dirs::home_dir();
+    #[cfg(target_os = "windows")]
+    return std::env::var_os("USERPROFILE").map(Into::into);
+}
+
+/// Get the home dir of the current user with the symlinks resolved,
+/// or `Error::HomeDirNotFound` if there is none
+///
+/// The entries are written and read under it on Linux and macOS, so the paths are the same
+/// whether `$HOME` is a symlink or the resolved dir.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(crate) fn get_canonical_home_dir() -> Result<std::path::PathBuf> {
+    let home = dirs::home_dir().ok_or(Error::HomeDirNotFound)?;
+    Ok(home.canonicalize().unwrap_or(home))
+}
+
+/// Retry the file operation with backoff on the transient errors, up to `retries` times
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(crate) fn retry_transient_io<T>(
+    retries: u32,
+    op: impl Fn() -> std::io::Result<T>,
+) -> std::io::Result<T> {
+    use std::io::ErrorKind;
+    let mut delay = std::time::Duration::from_millis(50);
+    let mut retried = 0;
+    loop {
+        match op() {
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::PermissionDenied | ErrorKind::ResourceBusy | ErrorKind::Interrupted
+                ) && retried < retries =>
+            {
+                std::thread::sleep(delay);
+                delay *= 2;
+                retried += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+/// Normalize the `app_name` into a filesystem-safe identifier, used as the file stem
+/// of the desktop entry on Linux and the plist on macOS
+///
+/// Characters other than alphanumerics, `-`, `_` and `.` are replaced with `_`,
+/// and the leading `.` is replaced too so that the file is not hidden.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(crate) fn normalize_identifier(name: &str) -> String {
+    let identifier = name
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            '.' if i == 0 => '_',
+            c if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    match identifier.is_empty() {
+        true => "_".into(),
+        false => identifier,
+    }
+}
+
+/// Quote the word with double quotes for the shell, if necessary
+///
+/// The quoting rules of the `Exec` key of desktop entry are the same as the shell double quotes.
+pub(crate) fn shell_quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_=./:,@%+".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        return word.into();
+    }
+    let mut quoted = String::from("\"");
+    for c in word.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Collect the failures of the best-effort removals into `Error::PurgeFailed`
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+pub(crate) fn collect_failures(results: impl IntoIterator<Item = Result<()>>) -> Result<()> {
+    let errors = results
+        .into_iter()
+        .filter_map(|x| x.err())
+        .collect::<Vec<_>>();
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(Error::PurgeFailed(errors)),
+    }
+}
+
+/// Check whether `AUTO_LAUNCH_DISABLE` is set to anything other than empty or `0`,
+/// which makes `enable` a no-op and `is_enabled` false
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+pub(crate) fn is_disabled_by_env() -> bool {
+    let disabled =
+        std::env::var_os("AUTO_LAUNCH_DISABLE").is_some_and(|x| !x.is_empty() && x != "0");
+    #[cfg(feature = "log")]
+    if disabled {
+        log::warn!("auto-launch: skipped by the environment variable `AUTO_LAUNCH_DISABLE`");
+    }
+    disabled
+}
+
+/// Replace the `${NAME}` placeholders in the arg with the value of `lookup(NAME)`
+///
+/// The `NAME` consists of ASCII alphanumerics and `_`, and doesn't start with a digit.
+/// The placeholder is kept as is if `lookup` returns `None`, e.g. the variable is unset,
+/// so are the other `$` and the malformed placeholders.
+pub(crate) fn expand_placeholders(arg: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let is_name = |name: &str| {
+        !name.starts_with(|c: char| c.is_ascii_digit())
+            && !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let mut expanded = String::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let name = after
+            .find('}')
+            .map(|end| &after[..end])
+            .filter(|x| is_name(x));
+        match name.and_then(|name| Some((name, lookup(name)?))) {
+            Some((name, value)) => {
+                expanded.push_str(&value);
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                expanded.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Write the file atomically, so that a crash or power loss never leaves a truncated file
+///
+/// The data is written and synced to a temp file in the same dir first,
+/// then the temp file is renamed over the `path`.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(crate) fn write_atomic(path: &std::path::Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    let result = std::fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(data)?;
+        file.sync_all()?;
+        std::fs::rename(&temp, path)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+#[cfg(feature = "tokio")]
+mod async_impl;
 #[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "macos")]
 mod macos;
+mod render;
 #[cfg(target_os = "windows")]
 mod windows;
 
+pub use render::render_entry;
+
 /// The parameters of `AutoLaunch::new` are different on each platform.
 ///
 /// ### Linux
@@ -164,7 +662,16 @@ mod windows;
 /// # let app_path = "/path/to/the-app";
 /// # let use_launch_agent = false;
 /// # let args = &["--minimized"];
-/// AutoLaunch::new(app_name, app_path, use_launch_agent, args);
+/// # let bundle_identifiers = &["com.github.the-app"];
+/// # let agent_extra_config = "";
+/// AutoLaunch::new(
+///     app_name,
+///     app_path,
+///     use_launch_agent,
+///     args,
+///     bundle_identifiers,
+///     agent_extra_config,
+/// );
 /// # }
 /// ```
 ///
@@ -176,10 +683,19 @@ mod windows;
 /// # use auto_launch::AutoLaunch;
 /// # let app_name = "the-app";
 /// # let app_path = "/path/to/the-app";
+/// # use auto_launch::WindowsEnableMode;
 /// # let args = &["--minimized"];
-/// AutoLaunch::new(app_name, app_path, args);
+/// # let enable_mode = WindowsEnableMode::Dynamic;
+/// AutoLaunch::new(app_name, app_path, enable_mode, args);
 /// # }
 /// ```
+///
+/// ## Thread Safety
+///
+/// `AutoLaunch` holds only owned data and a `Send + Sync` `CommandRunner`,
+/// so it is `Send + Sync` and can be shared
+/// across threads, e.g. in an `Arc`. Note that the system entry itself is not locked,
+/// concurrent `enable` and `disable` of the same `app_name` race as usual.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AutoLaunch {
     /// The application name
@@ -188,12 +704,158 @@ pub struct AutoLaunch {
     /// The application executable path (absolute path will be better)
     pub(crate) app_path: String,
 
+    /// The canonical form of `app_path`, used for writing and comparing the entries
+    pub(crate) canonical_app_path: String,
+
     #[cfg(target_os = "macos")]
     /// Whether use Launch Agent for implement or use AppleScript
     pub(crate) use_launch_agent: bool,
 
-    /// Args passed to the binary on startup
+    /// Args passed to the binary on startup, see `expand_placeholders` for `${NAME}`
     pub(crate) args: Vec<String>,
+
+    /// The raw command line args used verbatim instead of `args`
+    pub(crate) raw_args: Option<String>,
+
+    #[cfg(target_os = "linux")]
+    /// Whether to launch via the login shell to inherit the user environment
+    pub(crate) wrap_in_login_shell: bool,
+
+    #[cfg(target_os = "linux")]
+    /// The `Comment` of the desktop entry
+    pub(crate) description: Option<String>,
+
+    #[cfg(target_os = "linux")]
+    /// Whether the `app_path` is a bare command name looked up via `PATH`
+    pub(crate) use_path_lookup: bool,
+
+    #[cfg(target_os = "linux")]
+    /// Whether to write a systemd user unit alongside the desktop entry
+    pub(crate) systemd_unit: bool,
+
+    #[cfg(target_os = "linux")]
+    /// The `StartupWMClass` of the desktop entry
+    pub(crate) startup_wm_class: Option<String>,
+
+    #[cfg(target_os = "linux")]
+    /// The `GenericName` of the desktop entry
+    pub(crate) generic_name: Option<String>,
+
+    #[cfg(target_os = "linux")]
+    /// The `Keywords` of the desktop entry
+    pub(crate) keywords: Vec<String>,
+
+    #[cfg(target_os = "linux")]
+    /// The working dir to launch the app in
+    pub(crate) working_dir: Option<String>,
+
+    #[cfg(target_os = "linux")]
+    /// Whether to change to the `working_dir` via `sh -c` instead of the `Path` key
+    pub(crate) working_dir_via_shell: bool,
+
+    #[cfg(target_os = "linux")]
+    /// Whether `enable` checks the `app_path` exists before writing
+    pub(crate) require_app_path_exists: bool,
+
+    #[cfg(target_os = "linux")]
+    /// Whether to write `DBusActivatable=true`, to launch the app via its D-Bus service
+    pub(crate) dbus_activatable: bool,
+
+    #[cfg(target_os = "linux")]
+    /// The D-Bus well-known name of the app, naming the desktop entry when `dbus_activatable`
+    pub(crate) dbus_name: Option<String>,
+
+    #[cfg(target_os = "linux")]
+    /// The `Name` of the desktop entry, falls back to the `app_name`
+    pub(crate) display_name: Option<String>,
+
+    #[cfg(target_os = "windows")]
+    /// The registry scope to write the entry
+    pub(crate) enable_mode: WindowsEnableMode,
+
+    #[cfg(target_os = "windows")]
+    /// Whether to write and read the Task Manager override (`StartupApproved\Run`)
+    pub(crate) manage_task_manager_override: bool,
+
+    #[cfg(target_os = "windows")]
+    /// How many times to retry writing the `Run` value on the transient errors
+    pub(crate) registry_retry_count: u32,
+
+    #[cfg(target_os = "windows")]
+    /// The prefix of the registry value name, to avoid collisions with other apps
+    pub(crate) value_namespace: Option<String>,
+
+    #[cfg(target_os = "windows")]
+    /// The registry view of `HKEY_LOCAL_MACHINE`
+    pub(crate) registry_view: RegistryView,
+
+    #[cfg(target_os = "macos")]
+    /// Bundle identifiers used to locate the installed app, in lookup order
+    pub(crate) bundle_identifiers: Vec<String>,
+
+    #[cfg(target_os = "macos")]
+    /// Extra `<key>/<value>` pairs merged into the Launch Agent plist
+    pub(crate) agent_extra_config: String,
+
+    #[cfg(target_os = "macos")]
+    /// The Launch Agent label, defaults to the `app_name`
+    pub(crate) label: Option<String>,
+
+    #[cfg(target_os = "macos")]
+    /// The Launch Agent plist file name without `.plist`, defaults to the label
+    pub(crate) plist_filename: Option<String>,
+
+    #[cfg(target_os = "macos")]
+    /// Whether the Launch Agent runs at load, the `RunAtLoad` key
+    pub(crate) run_at_load: bool,
+
+    #[cfg(target_os = "macos")]
+    /// Run the Launch Agent every n seconds, the `StartInterval` key
+    pub(crate) start_interval: Option<u32>,
+
+    #[cfg(target_os = "macos")]
+    /// Run the Launch Agent on the calendar, the `StartCalendarInterval` key
+    pub(crate) start_calendar_interval: Option<CalendarInterval>,
+
+    #[cfg(target_os = "macos")]
+    /// Run the Launch Agent when the paths are modified, the `WatchPaths` key
+    pub(crate) watch_paths: Vec<std::path::PathBuf>,
+
+    #[cfg(target_os = "macos")]
+    /// Load the Launch Agent only in the session type, the `LimitLoadToSessionType` key
+    pub(crate) session_type: Option<String>,
+
+    #[cfg(target_os = "macos")]
+    /// Whether to unload the Launch Agent via `launchctl` on `disable`
+    pub(crate) unload_on_disable: bool,
+
+    #[cfg(target_os = "macos")]
+    /// Whether to reload the loaded Launch Agent via `launchctl` when `enable` changes the plist
+    pub(crate) reload_on_change: bool,
+
+    #[cfg(target_os = "macos")]
+    /// Whether `is_enabled` confirms the Launch Agent is loaded via `launchctl list`
+    pub(crate) verify_loaded: bool,
+
+    #[cfg(target_os = "macos")]
+    /// The runner to execute `osascript`, `launchctl`, `plutil` and `mdfind`
+    pub(crate) command_runner: SharedCommandRunner,
+
+    #[cfg(target_os = "macos")]
+    /// Whether to write the Launch Agent plist in the binary format
+    pub(crate) binary_plist: bool,
+
+    #[cfg(target_os = "macos")]
+    /// Whether to overwrite the existing plist with another `Label`
+    pub(crate) force_overwrite: bool,
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    /// The hook to customize the desktop entry or plist before it is written
+    pub(crate) entry_customizer: SharedEntryCustomizer,
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    /// How many times to retry writing and removing the entry file on the transient errors
+    pub(crate) file_retry_count: u32,
 }
 
 impl AutoLaunch {
@@ -214,6 +876,26 @@ impl AutoLaunch {
         ))
     }
 
+    /// check whether the current process was started by the autostart entry
+    ///
+    /// It looks for `LAUNCHED_AT_STARTUP_ARG` in the process args, so it only works
+    /// for the entry created by this crate with `AutoLaunchBuilder::set_startup_sentinel`.
+    ///
+    /// ## Usage
+    ///
+    /// ```rust
+    /// use auto_launch::AutoLaunch;
+    ///
+    /// if AutoLaunch::launched_at_startup() {
+    ///     // e.g. start minimized to the tray
+    /// }
+    /// ```
+    pub fn launched_at_startup() -> bool {
+        std::env::args_os()
+            .skip(1)
+            .any(|arg| arg == LAUNCHED_AT_STARTUP_ARG)
+    }
+
     /// get the application name
     pub fn get_app_name(&self) -> &str {
         &self.app_name
@@ -224,10 +906,148 @@ impl AutoLaunch {
         &self.app_path
     }
 
+    /// get the canonical application path, which is written into the entry
+    ///
+    /// The leading `~` is expanded to the home dir, the relative path is resolved
+    /// against the current dir, and the symlinks are followed if the path exists.
+    pub fn get_canonical_app_path(&self) -> &str {
+        &self.canonical_app_path
+    }
+
     /// get the args
     pub fn get_args(&self) -> &[String] {
         &self.args
     }
+
+    /// get the raw args, see `AutoLaunchBuilder::set_raw_args`
+    pub fn get_raw_args(&self) -> Option<&str> {
+        self.raw_args.as_deref()
+    }
+
+    /// Get the args with the `${NAME}` placeholders resolved from the current environment
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub(crate) fn get_resolved_args(&self) -> Vec<String> {
+        let lookup = |name: &str| std::env::var(name).ok();
+        self.args
+            .iter()
+            .map(|x| expand_placeholders(x, lookup))
+            .collect()
+    }
+
+    /// Get the mechanism used to launch the app at startup, based on the options
+    ///
+    /// It is `RegistryRun` on Windows, `LaunchAgent` or `AppleScript` by `use_launch_agent`
+    /// on macOS, and `SystemdUnit` or `DesktopEntry` by `systemd_unit` on Linux.
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    pub fn active_backend(&self) -> Backend {
+        #[cfg(target_os = "windows")]
+        return Backend::RegistryRun;
+        #[cfg(target_os = "macos")]
+        return match self.use_launch_agent {
+            true => Backend::LaunchAgent,
+            false => Backend::AppleScript,
+        };
+        #[cfg(target_os = "linux")]
+        return match self.systemd_unit {
+            true => Backend::SystemdUnit,
+            false => Backend::DesktopEntry,
+        };
+    }
+
+    /// Remove the entry under the `old_name`, then enable the current one
+    ///
+    /// It migrates the entry when the app is renamed, so that the app is not launched
+    /// twice, or under the former name. On macOS, the entry under the `old_name`
+    /// is looked up without the `label` and `plist_filename`.
+    ///
+    /// ## Errors
+    ///
+    /// The same as `disable` and `enable`.
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    pub fn rename_from(&self, old_name: &str) -> Result<()> {
+        let mut old = self.clone();
+        old.app_name = old_name.into();
+        #[cfg(target_os = "macos")]
+        {
+            old.label = None;
+            old.plist_filename = None;
+        }
+        old.disable()?;
+        self.enable()?;
+        Ok(())
+    }
+
+    /// Enable the AutoLaunch setting only if needed, and return whether it was written
+    ///
+    /// It is for calling on every launch, the entry already enabled with the same content
+    /// is not rewritten, so the file watchers and antivirus are not triggered.
+    /// The stale entry, e.g. with another path, is rewritten.
+    ///
+    /// ## Errors
+    ///
+    /// The same as `enable`.
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    pub fn ensure_enabled(&self) -> Result<bool> {
+        Ok(self.enable()? == Change::Changed)
+    }
+
+    /// Disable the AutoLaunch setting only if needed, and return whether it was removed
+    ///
+    /// ## Errors
+    ///
+    /// The same as `disable`.
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    pub fn ensure_disabled(&self) -> Result<bool> {
+        Ok(self.disable()? == Change::Changed)
+    }
+}
+
+/// Several `AutoLaunch` managed together, e.g. the helpers installed by an app suite
+///
+/// The operations go on with the rest members after a failure,
+/// and return the result of each member in order.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AutoLaunchGroup {
+    members: Vec<AutoLaunch>,
+}
+
+impl AutoLaunchGroup {
+    /// Create a group of the `members`
+    pub fn new(members: Vec<AutoLaunch>) -> AutoLaunchGroup {
+        AutoLaunchGroup { members }
+    }
+
+    /// Add a member to the group
+    pub fn add(&mut self, member: AutoLaunch) -> &mut Self {
+        self.members.push(member);
+        self
+    }
+
+    /// get the members
+    pub fn members(&self) -> &[AutoLaunch] {
+        &self.members
+    }
+
+    /// Enable every member, see `AutoLaunch::enable`
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    pub fn enable_all(&self) -> Vec<Result<Change>> {
+        self.members.iter().map(AutoLaunch::enable).collect()
+    }
+
+    /// Disable every member, see `AutoLaunch::disable`
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    pub fn disable_all(&self) -> Vec<Result<Change>> {
+        self.members.iter().map(AutoLaunch::disable).collect()
+    }
+
+    /// Get the status of every member, see `AutoLaunch::enabled_status`
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    pub fn statuses(&self) -> Vec<Result<EnabledStatus>> {
+        self.members
+            .iter()
+            .map(AutoLaunch::enabled_status)
+            .collect()
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -264,9 +1084,89 @@ pub struct AutoLaunchBuilder {
 
     pub app_path: Option<String>,
 
+    pub app_path_error: Option<String>,
+
     pub use_launch_agent: bool,
 
     pub args: Option<Vec<String>>,
+
+    pub raw_args: Option<String>,
+
+    pub startup_sentinel: bool,
+
+    pub bundle_identifiers: Option<Vec<String>>,
+
+    pub agent_extra_config: Option<String>,
+
+    pub manage_task_manager_override: Option<bool>,
+
+    pub label: Option<String>,
+
+    pub plist_filename: Option<String>,
+
+    pub wrap_in_login_shell: bool,
+
+    pub use_path_lookup: bool,
+
+    pub systemd_unit: bool,
+
+    pub startup_wm_class: Option<String>,
+
+    pub generic_name: Option<String>,
+
+    pub keywords: Vec<String>,
+
+    pub working_dir: Option<String>,
+
+    pub working_dir_via_shell: bool,
+
+    pub require_app_path_exists: bool,
+
+    pub dbus_activatable: bool,
+
+    pub dbus_name: Option<String>,
+
+    pub display_name: Option<String>,
+
+    pub windows_enable_mode: WindowsEnableMode,
+
+    pub allow_name_correction: Option<bool>,
+
+    pub run_at_load: Option<bool>,
+
+    pub start_interval: Option<u32>,
+
+    pub start_calendar_interval: Option<CalendarInterval>,
+
+    pub watch_paths: Option<Vec<std::path::PathBuf>>,
+
+    pub session_type: Option<String>,
+
+    pub unload_on_disable: Option<bool>,
+
+    pub reload_on_change: Option<bool>,
+
+    pub verify_loaded: Option<bool>,
+
+    pub command_runner: Option<std::sync::Arc<dyn CommandRunner>>,
+
+    pub entry_customizer: Option<std::sync::Arc<dyn EntryCustomizer>>,
+
+    pub file_retry_count: Option<u32>,
+
+    pub binary_plist: Option<bool>,
+
+    pub force_overwrite: Option<bool>,
+
+    pub strict: bool,
+
+    pub description: Option<String>,
+
+    pub registry_retry_count: Option<u32>,
+
+    pub value_namespace: Option<String>,
+
+    pub registry_view: RegistryView,
 }
 
 impl AutoLaunchBuilder {
@@ -274,6 +1174,12 @@ impl AutoLaunchBuilder {
         AutoLaunchBuilder::default()
     }
 
+    /// Reset all the options to the default, the same as `AutoLaunchBuilder::new`
+    pub fn reset(&mut self) -> &mut Self {
+        *self = AutoLaunchBuilder::default();
+        self
+    }
+
     /// Set the `app_name`
     pub fn set_app_name(&mut self, name: &str) -> &mut Self {
         self.app_name = Some(name.into());
@@ -283,6 +1189,25 @@ impl AutoLaunchBuilder {
     /// Set the `app_path`
     pub fn set_app_path(&mut self, path: &str) -> &mut Self {
         self.app_path = Some(path.into());
+        self.app_path_error = None;
+        self
+    }
+
+    /// Set the `app_path` to `std::env::current_exe`, the running executable
+    ///
+    /// On macOS, the `.app` bundle of the executable is walked up to for the login item
+    /// as `AutoLaunch::new` does. The failure to resolve it is returned by `build`.
+    pub fn set_app_path_from_current_exe(&mut self) -> &mut Self {
+        match std::env::current_exe() {
+            Ok(path) => {
+                self.app_path = Some(path.to_string_lossy().into_owned());
+                self.app_path_error = None;
+            }
+            Err(e) => {
+                self.app_path = None;
+                self.app_path_error = Some(e.to_string());
+            }
+        }
         self
     }
 
@@ -293,38 +1218,665 @@ impl AutoLaunchBuilder {
         self
     }
 
+    /// Set the `allow_name_correction`, default is true
+    /// This setting only works on macOS
+    ///
+    /// In case using AppleScript, the `app_name` is corrected to the basename of `app_path`.
+    /// When false, `build` returns `Error::InvalidInput` if they don't match instead.
+    pub fn set_allow_name_correction(&mut self, allow: bool) -> &mut Self {
+        self.allow_name_correction = Some(allow);
+        self
+    }
+
     /// Set the args
+    ///
+    /// The `${NAME}` placeholders are replaced by the environment variable `NAME`,
+    /// resolved on `enable` on Linux and macOS, or on login on Windows.
+    /// On Windows, `${APP_DIR}` is replaced by the dir of `app_path` on `enable`.
     pub fn set_args(&mut self, args: &[impl AsRef<str>]) -> &mut Self {
         self.args = Some(args.iter().map(|s| s.as_ref().to_string()).collect());
         self
     }
 
+    /// Clear the args and raw args set before, e.g. when the builder is reused
+    pub fn clear_args(&mut self) -> &mut Self {
+        self.args = None;
+        self.raw_args = None;
+        self
+    }
+
+    /// Set the raw args, a pre-built command line string like `--a --b="x y"`
+    ///
+    /// It is appended verbatim to the quoted app path by each backend,
+    /// so the caller is responsible for quoting it for the platform.
+    /// On macOS, the Launch Agent runs it via `/bin/sh -c`, as the plist has no command line.
+    /// It can't be used together with `set_args`, `build` returns `Error::InvalidInput` then.
+    pub fn set_raw_args(&mut self, raw_args: &str) -> &mut Self {
+        self.raw_args = Some(raw_args.into());
+        self
+    }
+
+    /// Set whether to append `LAUNCHED_AT_STARTUP_ARG` to the args written into the entry
+    ///
+    /// It's appended to the raw args instead, if `set_raw_args` is used.
+    /// So the app can tell it was started by the autostart entry via `AutoLaunch::launched_at_startup`.
+    /// The app must accept the extra arg. The AppleScript login items can't carry args,
+    /// so it has no effect on macOS unless using the Launch Agent.
+    pub fn set_startup_sentinel(&mut self, startup_sentinel: bool) -> &mut Self {
+        self.startup_sentinel = startup_sentinel;
+        self
+    }
+
+    /// Set the `bundle_identifiers`
+    /// This setting only works on macOS
+    pub fn set_bundle_identifiers(&mut self, bundle_identifiers: &[impl AsRef<str>]) -> &mut Self {
+        self.bundle_identifiers = Some(
+            bundle_identifiers
+                .iter()
+                .map(|s| s.as_ref().to_string())
+                .collect(),
+        );
+        self
+    }
+
+    /// Clear the `bundle_identifiers` set before
+    pub fn clear_bundle_identifiers(&mut self) -> &mut Self {
+        self.bundle_identifiers = None;
+        self
+    }
+
+    /// Set the `agent_extra_config`
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// The config is a block of `<key>/<value>` pairs, which will be merged into
+    /// the top-level `<dict>` of the generated plist, e.g.
+    /// `<key>LimitLoadToSessionType</key><string>Aqua</string>`.
+    /// It should be well-formed and must not duplicate the generated keys,
+    /// otherwise `enable` will fail.
+    pub fn set_agent_extra_config(&mut self, config: &str) -> &mut Self {
+        self.agent_extra_config = Some(config.into());
+        self
+    }
+
+    /// Set the `label`, e.g. `com.example.app`
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// The label is used as the `Label` key and the file name of the plist,
+    /// it falls back to the `app_name` when not set.
+    pub fn set_label(&mut self, label: &str) -> &mut Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Set the `plist_filename`, e.g. `com.example.app` for `com.example.app.plist`
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// It names the plist file instead of the label, while the `Label` key is kept,
+    /// and the trailing `.plist` is optional.
+    pub fn set_plist_filename(&mut self, filename: &str) -> &mut Self {
+        let filename = filename.strip_suffix(".plist").unwrap_or(filename);
+        self.plist_filename = Some(filename.into());
+        self
+    }
+
+    /// Set the `run_at_load`, default is true
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// It controls the `RunAtLoad` key of the plist. When false, the agent
+    /// is not started at login but only by the other triggers in the plist.
+    pub fn set_run_at_load(&mut self, run_at_load: bool) -> &mut Self {
+        self.run_at_load = Some(run_at_load);
+        self
+    }
+
+    /// Set the `start_interval` in seconds
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// It is the `StartInterval` key of the plist, which runs the agent periodically.
+    /// It can't be set together with `start_calendar_interval`, otherwise `enable` will fail.
+    pub fn set_start_interval(&mut self, seconds: u32) -> &mut Self {
+        self.start_interval = Some(seconds);
+        self
+    }
+
+    /// Set the `start_calendar_interval`
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// It is the `StartCalendarInterval` key of the plist, which runs the agent on the calendar.
+    /// It can't be set together with `start_interval`, and the fields out of range
+    /// will make `enable` fail.
+    pub fn set_start_calendar_interval(&mut self, interval: CalendarInterval) -> &mut Self {
+        self.start_calendar_interval = Some(interval);
+        self
+    }
+
+    /// Set the `watch_paths`
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// It is the `WatchPaths` key of the plist, which runs the agent
+    /// when any of the paths is modified. It is omitted when empty.
+    pub fn set_watch_paths(&mut self, paths: Vec<std::path::PathBuf>) -> &mut Self {
+        self.watch_paths = Some(paths);
+        self
+    }
+
+    /// Set the `session_type`
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// It is the `LimitLoadToSessionType` key of the plist, e.g. `"Aqua"` to load
+    /// the agent only in the GUI login session, not in SSH or background sessions.
+    /// It must be one of `Aqua`, `Background`, `LoginWindow`, `StandardIO` and `System`,
+    /// otherwise `enable` fails. It is omitted when unset.
+    pub fn set_session_type(&mut self, session_type: &str) -> &mut Self {
+        self.session_type = Some(session_type.into());
+        self
+    }
+
+    /// Set the `unload_on_disable`, default is false
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// When true, `disable` runs `launchctl unload` on the plist before removing it,
+    /// so that the agent loaded in the current session is stopped immediately.
+    pub fn set_unload_on_disable(&mut self, unload: bool) -> &mut Self {
+        self.unload_on_disable = Some(unload);
+        self
+    }
+
+    /// Set the `reload_on_change`, default is false
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// When true, `enable` runs `launchctl unload` and `load` around rewriting the plist
+    /// which differs from the existing one, if the agent is loaded in the current session,
+    /// so that the updated args and path take effect without logging out.
+    pub fn set_reload_on_change(&mut self, reload: bool) -> &mut Self {
+        self.reload_on_change = Some(reload);
+        self
+    }
+
+    /// Set the `verify_loaded`, default is false
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// When true, `is_enabled` also runs `launchctl list {label}` to confirm the agent
+    /// is loaded, not only the plist exists, e.g. launchd failed to load a bad plist.
+    /// Note that the agent written by `enable` is loaded only at the next login.
+    pub fn set_verify_loaded(&mut self, verify: bool) -> &mut Self {
+        self.verify_loaded = Some(verify);
+        self
+    }
+
+    /// Set the `command_runner` to execute the external commands, default is `SystemCommandRunner`
+    /// This setting only works on macOS
+    pub fn set_command_runner(&mut self, runner: std::sync::Arc<dyn CommandRunner>) -> &mut Self {
+        self.command_runner = Some(runner);
+        self
+    }
+
+    /// Set the `binary_plist`, default is false
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// When true, the plist is converted to the binary format via `plutil -convert binary1`
+    /// after written, for the old macOS versions or validators which require it.
+    pub fn set_binary_plist(&mut self, binary: bool) -> &mut Self {
+        self.binary_plist = Some(binary);
+        self
+    }
+
+    /// Set the `force_overwrite`, default is false
+    /// This setting only works on macOS with Launch Agent
+    ///
+    /// By default, `enable` returns `Error::Conflict` instead of overwriting an existing plist
+    /// at the same path whose `Label` is not ours, e.g. a third-party agent.
+    pub fn set_force_overwrite(&mut self, force: bool) -> &mut Self {
+        self.force_overwrite = Some(force);
+        self
+    }
+
+    /// Set the `wrap_in_login_shell`, default is false
+    /// This setting only works on Linux
+    ///
+    /// When true, the app is launched via `sh -lc 'exec "{app_path}" {args}'`,
+    /// so that the login profile is sourced and the user environment is inherited.
+    pub fn set_wrap_in_login_shell(&mut self, wrap: bool) -> &mut Self {
+        self.wrap_in_login_shell = wrap;
+        self
+    }
+
+    /// Set the `use_path_lookup`, default is false
+    /// This setting only works on Linux
+    ///
+    /// When true, the `app_path` is a bare command name, e.g. `myapp`,
+    /// written as is to the `Exec` key and looked up via `PATH` on login.
+    /// `build` returns `Error::InvalidInput` if it contains a path separator.
+    pub fn set_use_path_lookup(&mut self, lookup: bool) -> &mut Self {
+        self.use_path_lookup = lookup;
+        self
+    }
+
+    /// Set the `systemd_unit`, default is false
+    /// This setting only works on Linux
+    ///
+    /// When true, a systemd user unit `~/.config/systemd/user/{identifier}.service`
+    /// wanted by `default.target` is written alongside the desktop entry,
+    /// so the app also starts without a graphical session.
+    /// The app should guard against running twice, as both of them launch it in a graphical session.
+    pub fn set_systemd_unit(&mut self, systemd_unit: bool) -> &mut Self {
+        self.systemd_unit = systemd_unit;
+        self
+    }
+
+    /// Set the `startup_wm_class`, the `StartupWMClass` of the desktop entry
+    /// This setting only works on Linux
+    ///
+    /// It groups the app window with its launcher in the taskbar, omitted when unset.
+    pub fn set_startup_wm_class(&mut self, wm_class: &str) -> &mut Self {
+        self.startup_wm_class = Some(wm_class.into());
+        self
+    }
+
+    /// Set the `generic_name`, the `GenericName` of the desktop entry, e.g. "Web Browser"
+    /// This setting only works on Linux
+    ///
+    /// It is shown and searched in the startup settings, omitted when unset.
+    pub fn set_generic_name(&mut self, generic_name: &str) -> &mut Self {
+        self.generic_name = Some(generic_name.into());
+        self
+    }
+
+    /// Set the `keywords`, the `Keywords` of the desktop entry
+    /// This setting only works on Linux
+    ///
+    /// They are written semicolon-terminated, omitted when empty.
+    pub fn set_keywords(&mut self, keywords: &[impl AsRef<str>]) -> &mut Self {
+        self.keywords = keywords.iter().map(|s| s.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Set the `working_dir` to launch the app in
+    /// This setting only works on Linux
+    ///
+    /// It is written as the `Path` key of the desktop entry by default,
+    /// and the `WorkingDirectory` of the systemd unit.
+    /// The Windows `Run` value has no working dir, so it is ignored there like on macOS.
+    pub fn set_working_dir(&mut self, dir: &str) -> &mut Self {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    /// Set the `working_dir_via_shell`, default is false
+    /// This setting only works on Linux
+    ///
+    /// When true, the app is launched via `sh -c 'cd "{working_dir}" && exec "{app_path}" {args}'`
+    /// instead of the `Path` key, which is not honored by some desktop environments.
+    pub fn set_working_dir_via_shell(&mut self, via_shell: bool) -> &mut Self {
+        self.working_dir_via_shell = via_shell;
+        self
+    }
+
+    /// Set the `require_app_path_exists`, default is false
+    /// This setting only works on Linux
+    ///
+    /// When true, `enable` returns `Error::AppPathDoesntExist` instead of writing
+    /// the desktop entry which would fail at login, as macOS always does.
+    /// The bare command name with `use_path_lookup` is looked up in `PATH`.
+    pub fn set_require_app_path_exists(&mut self, require: bool) -> &mut Self {
+        self.require_app_path_exists = require;
+        self
+    }
+
+    /// Set the `dbus_activatable`, default is false
+    /// This setting only works on Linux
+    ///
+    /// When true, `DBusActivatable=true` is written, so that the app is launched via
+    /// its D-Bus service file, with `Exec` kept for the launchers not supporting it.
+    /// It requires the `dbus_name`, otherwise `build` returns `Error::InvalidInput`.
+    pub fn set_dbus_activatable(&mut self, activatable: bool) -> &mut Self {
+        self.dbus_activatable = activatable;
+        self
+    }
+
+    /// Set the `dbus_name`, the D-Bus well-known name of the app, e.g. `org.example.App`
+    /// This setting only works on Linux
+    ///
+    /// The desktop entry is named `{dbus_name}.desktop` when `dbus_activatable` is true,
+    /// as the D-Bus activation looks up the service by the file name.
+    pub fn set_dbus_name(&mut self, name: &str) -> &mut Self {
+        self.dbus_name = Some(name.into());
+        self
+    }
+
+    /// Set the `display_name`, the `Name` shown by the desktop, default is the `app_name`
+    /// This setting only works on Linux
+    ///
+    /// The desktop entry is still named by the normalized `app_name`,
+    /// so the display name can change without orphaning the file.
+    pub fn set_display_name(&mut self, name: &str) -> &mut Self {
+        self.display_name = Some(name.into());
+        self
+    }
+
+    /// Set the `description`, default is `{app_name} startup script`
+    /// This setting only works on Linux
+    ///
+    /// It is written as the `Comment` of the desktop entry.
+    pub fn set_description(&mut self, description: &str) -> &mut Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set the `manage_task_manager_override`, default is true
+    /// This setting only works on Windows
+    ///
+    /// When true, `enable` writes the enabled Task Manager override (`StartupApproved\Run`),
+    /// creating the key if it's absent.
+    /// When false, `enable` will not write the Task Manager override
+    /// and `is_enabled` will ignore it, only the plain `Run` value is used.
+    pub fn set_manage_task_manager_override(&mut self, manage: bool) -> &mut Self {
+        self.manage_task_manager_override = Some(manage);
+        self
+    }
+
+    /// Set the `windows_enable_mode`, default is `WindowsEnableMode::Dynamic`
+    /// This setting only works on Windows
+    pub fn set_windows_enable_mode(&mut self, mode: WindowsEnableMode) -> &mut Self {
+        self.windows_enable_mode = mode;
+        self
+    }
+
+    /// Set the `registry_retry_count`, default is 3
+    /// This setting only works on Windows
+    ///
+    /// `enable` retries writing the `Run` value with backoff when the registry key
+    /// is held by another process, e.g. other startup managers during login.
+    pub fn set_registry_retry_count(&mut self, count: u32) -> &mut Self {
+        self.registry_retry_count = Some(count);
+        self
+    }
+
+    /// Set the `value_namespace`, e.g. the vendor name
+    /// This setting only works on Windows
+    ///
+    /// The registry value is named `{value_namespace}.{app_name}` instead of `app_name`,
+    /// so that the apps with the same `app_name` don't overwrite each other.
+    pub fn set_value_namespace(&mut self, namespace: &str) -> &mut Self {
+        self.value_namespace = Some(namespace.into());
+        self
+    }
+
+    /// Set the `registry_view`, default is `RegistryView::Default`
+    /// This setting only works on Windows
+    ///
+    /// It decides whether the `HKEY_LOCAL_MACHINE` entry is under `WOW6432Node` or not,
+    /// `build` returns `Error::InvalidInput` for `RegistryView::Force64` in a 32-bit process.
+    pub fn set_registry_view(&mut self, view: RegistryView) -> &mut Self {
+        self.registry_view = view;
+        self
+    }
+
+    /// Set the `file_retry_count`, default is 2
+    /// This setting only works on Linux and macOS
+    ///
+    /// `enable` and `disable` retry writing and removing the entry file with backoff
+    /// when it is locked briefly, e.g. by the antivirus or indexing, which shows up
+    /// as `PermissionDenied` or `ResourceBusy`.
+    pub fn set_file_retry_count(&mut self, count: u32) -> &mut Self {
+        self.file_retry_count = Some(count);
+        self
+    }
+
+    /// Set the `entry_customizer` to modify the generated entry content
+    /// This setting only works on Linux and macOS
+    ///
+    /// It is applied to the desktop entry on Linux and the Launch Agent plist on macOS,
+    /// e.g. to add the organization-specific keys without a setter for each.
+    pub fn set_entry_customizer(
+        &mut self,
+        customizer: std::sync::Arc<dyn EntryCustomizer>,
+    ) -> &mut Self {
+        self.entry_customizer = Some(customizer);
+        self
+    }
+
+    /// Set the `strict`, default is false
+    ///
+    /// The options which only work on the other platforms are ignored by `build`,
+    /// with a warning logged when the `log` feature is enabled.
+    /// When true, `build` returns `Error::InvalidInput` for them instead.
+    pub fn set_strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Get the names of the options set but not working on the current platform
+    fn get_ignored_options(&self) -> Vec<&'static str> {
+        let macos = [
+            ("use_launch_agent", self.use_launch_agent),
+            (
+                "allow_name_correction",
+                self.allow_name_correction.is_some(),
+            ),
+            ("bundle_identifiers", self.bundle_identifiers.is_some()),
+            ("agent_extra_config", self.agent_extra_config.is_some()),
+            ("label", self.label.is_some()),
+            ("plist_filename", self.plist_filename.is_some()),
+            ("run_at_load", self.run_at_load.is_some()),
+            ("start_interval", self.start_interval.is_some()),
+            (
+                "start_calendar_interval",
+                self.start_calendar_interval.is_some(),
+            ),
+            ("watch_paths", self.watch_paths.is_some()),
+            ("session_type", self.session_type.is_some()),
+            ("unload_on_disable", self.unload_on_disable.is_some()),
+            ("reload_on_change", self.reload_on_change.is_some()),
+            ("verify_loaded", self.verify_loaded.is_some()),
+            ("command_runner", self.command_runner.is_some()),
+            ("binary_plist", self.binary_plist.is_some()),
+            ("force_overwrite", self.force_overwrite.is_some()),
+        ];
+        let windows = [
+            (
+                "manage_task_manager_override",
+                self.manage_task_manager_override.is_some(),
+            ),
+            (
+                "windows_enable_mode",
+                self.windows_enable_mode != WindowsEnableMode::Dynamic,
+            ),
+            ("registry_retry_count", self.registry_retry_count.is_some()),
+            ("value_namespace", self.value_namespace.is_some()),
+            ("registry_view", self.registry_view != RegistryView::Default),
+        ];
+        let linux = [
+            ("wrap_in_login_shell", self.wrap_in_login_shell),
+            ("use_path_lookup", self.use_path_lookup),
+            ("systemd_unit", self.systemd_unit),
+            ("startup_wm_class", self.startup_wm_class.is_some()),
+            ("generic_name", self.generic_name.is_some()),
+            ("keywords", !self.keywords.is_empty()),
+            ("working_dir", self.working_dir.is_some()),
+            ("working_dir_via_shell", self.working_dir_via_shell),
+            ("require_app_path_exists", self.require_app_path_exists),
+            ("dbus_activatable", self.dbus_activatable),
+            ("dbus_name", self.dbus_name.is_some()),
+            ("display_name", self.display_name.is_some()),
+            ("description", self.description.is_some()),
+        ];
+        let linux_and_macos = [
+            ("entry_customizer", self.entry_customizer.is_some()),
+            ("file_retry_count", self.file_retry_count.is_some()),
+        ];
+
+        let mut options = vec![];
+        if !cfg!(target_os = "macos") {
+            options.extend(macos);
+        }
+        if !cfg!(target_os = "windows") {
+            options.extend(windows);
+        }
+        if !cfg!(target_os = "linux") {
+            options.extend(linux);
+        }
+        if !cfg!(any(target_os = "linux", target_os = "macos")) {
+            options.extend(linux_and_macos);
+        }
+        options
+            .into_iter()
+            .filter(|(_, set)| *set)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Reject or warn the options set but not working on the current platform
+    fn check_ignored_options(&self) -> Result<()> {
+        let ignored = self.get_ignored_options();
+        if self.strict && !ignored.is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "options not supported on this platform: {}",
+                ignored.join(", ")
+            )));
+        }
+        #[cfg(feature = "log")]
+        for option in ignored {
+            log::warn!("auto-launch: `{}` is ignored on this platform", option);
+        }
+        Ok(())
+    }
+
+    /// Get the args and raw args, with `LAUNCHED_AT_STARTUP_ARG` appended to the last of them
+    pub(crate) fn args_with_sentinel(&self) -> (Vec<String>, Option<String>) {
+        let mut args = self.args.clone().unwrap_or_default();
+        let mut raw_args = self.raw_args.clone();
+        if self.startup_sentinel {
+            match &mut raw_args {
+                Some(raw_args) => {
+                    raw_args.push(' ');
+                    raw_args.push_str(LAUNCHED_AT_STARTUP_ARG);
+                }
+                None => args.push(LAUNCHED_AT_STARTUP_ARG.into()),
+            }
+        }
+        (args, raw_args)
+    }
+
     /// Construct a AutoLaunch instance
     ///
     /// ## Errors
     ///
     /// - `app_name` is none
     /// - `app_path` is none
-    ///
-    /// ## Panics
-    ///
-    /// - Unsupported target OS
+    /// - `app_name` doesn't match the basename of `app_path` when using AppleScript,
+    ///   and `allow_name_correction` is false
+    /// - the options only working on the other platforms are set, and `strict` is true
+    /// - unsupported target OS, returns `Error::UnsupportedOS`
     pub fn build(&self) -> Result<AutoLaunch> {
         let app_name = self.app_name.as_ref().ok_or(Error::AppNameNotSpecified)?;
+        if let Some(e) = &self.app_path_error {
+            return Err(std::io::Error::other(format!(
+                "failed to resolve the current exe for app_path: {}",
+                e
+            ))
+            .into());
+        }
         let app_path = self.app_path.as_ref().ok_or(Error::AppPathNotSpecified)?;
-        let args = self.args.clone().unwrap_or_default();
+        let (args, raw_args) = self.args_with_sentinel();
+        self.check_ignored_options()?;
+        if self.args.is_some() && self.raw_args.is_some() {
+            return Err(Error::InvalidInput(
+                "args and raw_args can't be set together".into(),
+            ));
+        }
 
         #[cfg(target_os = "linux")]
-        return Ok(AutoLaunch::new(app_name, app_path, &args));
+        return {
+            if self.use_path_lookup && (app_path.is_empty() || app_path.contains('/')) {
+                return Err(Error::InvalidInput(format!(
+                    "app_path `{}` is not a bare command name to look up via PATH",
+                    app_path
+                )));
+            }
+            if self.dbus_activatable {
+                let name = self.dbus_name.as_deref().ok_or_else(|| {
+                    Error::InvalidInput("dbus_activatable requires the dbus_name".into())
+                })?;
+                if !linux::is_dbus_name(name) {
+                    return Err(Error::InvalidInput(format!(
+                        "dbus_name `{}` is not a D-Bus well-known name",
+                        name
+                    )));
+                }
+            }
+            Ok(AutoLaunch {
+                wrap_in_login_shell: self.wrap_in_login_shell,
+                raw_args: raw_args.clone(),
+                description: self.description.clone(),
+                use_path_lookup: self.use_path_lookup,
+                systemd_unit: self.systemd_unit,
+                startup_wm_class: self.startup_wm_class.clone(),
+                generic_name: self.generic_name.clone(),
+                keywords: self.keywords.clone(),
+                working_dir: self.working_dir.clone(),
+                working_dir_via_shell: self.working_dir_via_shell,
+                require_app_path_exists: self.require_app_path_exists,
+                dbus_activatable: self.dbus_activatable,
+                dbus_name: self.dbus_name.clone(),
+                display_name: self.display_name.clone(),
+                entry_customizer: SharedEntryCustomizer(self.entry_customizer.clone()),
+                file_retry_count: self.file_retry_count.unwrap_or(2),
+                ..AutoLaunch::new(app_name, app_path, &args)
+            })
+        };
         #[cfg(target_os = "macos")]
-        return Ok(AutoLaunch::new(
-            app_name,
-            app_path,
-            self.use_launch_agent,
-            &args,
-        ));
+        return {
+            let auto = AutoLaunch {
+                label: self.label.clone(),
+                plist_filename: self.plist_filename.clone(),
+                raw_args: raw_args.clone(),
+                run_at_load: self.run_at_load.unwrap_or(true),
+                start_interval: self.start_interval,
+                start_calendar_interval: self.start_calendar_interval.clone(),
+                watch_paths: self.watch_paths.clone().unwrap_or_default(),
+                session_type: self.session_type.clone(),
+                unload_on_disable: self.unload_on_disable.unwrap_or(false),
+                reload_on_change: self.reload_on_change.unwrap_or(false),
+                verify_loaded: self.verify_loaded.unwrap_or(false),
+                command_runner: SharedCommandRunner(self.command_runner.clone()),
+                entry_customizer: SharedEntryCustomizer(self.entry_customizer.clone()),
+                file_retry_count: self.file_retry_count.unwrap_or(2),
+                binary_plist: self.binary_plist.unwrap_or(false),
+                force_overwrite: self.force_overwrite.unwrap_or(false),
+                ..AutoLaunch::new(
+                    app_name,
+                    app_path,
+                    self.use_launch_agent,
+                    &args,
+                    &self.bundle_identifiers.clone().unwrap_or_default(),
+                    self.agent_extra_config.as_deref().unwrap_or_default(),
+                )
+            };
+            if !self.allow_name_correction.unwrap_or(true) && auto.app_name != *app_name {
+                Err(Error::InvalidInput(format!(
+                    "app_name `{}` doesn't match the basename `{}` of app_path",
+                    app_name, auto.app_name
+                )))
+            } else {
+                Ok(auto)
+            }
+        };
+        #[cfg(target_os = "windows")]
+        if self.registry_view == RegistryView::Force64 && cfg!(target_pointer_width = "32") {
+            return Err(Error::InvalidInput(
+                "the 64-bit registry view is not reachable from a 32-bit process".into(),
+            ));
+        }
         #[cfg(target_os = "windows")]
-        return Ok(AutoLaunch::new(app_name, app_path, &args));
+        return Ok(AutoLaunch {
+            manage_task_manager_override: self.manage_task_manager_override.unwrap_or(true),
+            raw_args: raw_args.clone(),
+            registry_retry_count: self.registry_retry_count.unwrap_or(3),
+            value_namespace: self.value_namespace.clone(),
+            registry_view: self.registry_view,
+            ..AutoLaunch::new(app_name, app_path, self.windows_enable_mode, &args)
+        });
 
         #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
         return Err(Error::UnsupportedOS);
@@ -1,5 +1,16 @@
-use crate::{AutoLaunch, Result};
-use std::{fs, io::Write, path::PathBuf};
+use crate::{
+    canonicalize_app_path, collect_failures, get_canonical_home_dir, is_disabled_by_env,
+    normalize_identifier,
+    render::{escape_exec, format_desktop_entry},
+    retry_transient_io, shell_quote, write_atomic, Action, AutoLaunch, Change, EnabledStatus,
+    EntryInfo, Error, PlannedChange, Result, SharedEntryCustomizer, StateDiff,
+    LAUNCHED_AT_STARTUP_ARG,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 /// Linux implement
 impl AutoLaunch {
@@ -15,34 +26,114 @@ impl AutoLaunch {
         AutoLaunch {
             app_name: app_name.into(),
             app_path: app_path.into(),
+            canonical_app_path: canonicalize_app_path(app_path),
             args: args.iter().map(|s| s.as_ref().to_string()).collect(),
+            raw_args: None,
+            wrap_in_login_shell: false,
+            description: None,
+            use_path_lookup: false,
+            systemd_unit: false,
+            startup_wm_class: None,
+            generic_name: None,
+            keywords: vec![],
+            working_dir: None,
+            working_dir_via_shell: false,
+            require_app_path_exists: false,
+            dbus_activatable: false,
+            dbus_name: None,
+            display_name: None,
+            entry_customizer: SharedEntryCustomizer::default(),
+            file_retry_count: 2,
         }
     }
 
     /// Enable the AutoLaunch setting
     ///
+    /// Returns `Change::Unchanged` if the same desktop entry (and systemd unit) is already written,
+    /// or `AUTO_LAUNCH_DISABLE` is set.
+    ///
+    /// The desktop entry is written to a temp file and renamed into place,
+    /// so that it is never left truncated.
+    /// With `systemd_unit`, the desktop entry is removed again if failed to write the unit.
+    ///
     /// ## Errors
     ///
+    /// - the `app_path` doesn't exist, when `require_app_path_exists` is true
     /// - failed to create dir `~/.config/autostart`
-    /// - failed to create file `~/.config/autostart/{app_name}.desktop`
-    /// - failed to write bytes to the file
-    pub fn enable(&self) -> Result<()> {
-        let data = format!(
-            "[Desktop Entry]\n\
-            Type=Application\n\
-            Version=1.0\n\
-            Name={}\n\
-            Comment={}startup script\n\
-            Exec={} {}\n\
-            StartupNotify=false\n\
-            Terminal=false",
-            self.app_name,
-            self.app_name,
-            self.app_path,
-            self.args.join(" ")
-        );
+    /// - failed to create file `~/.config/autostart/{identifier}.desktop`,
+    ///   where the `identifier` is the `app_name` normalized to be filesystem-safe
+    /// - failed to write bytes to the temp file in the same dir, or rename it to the file
+    /// - failed to write the systemd unit `~/.config/systemd/user/{identifier}.service`,
+    ///   or link it into `default.target.wants`, when `systemd_unit` is true
+    pub fn enable(&self) -> Result<Change> {
+        if is_disabled_by_env() {
+            return Ok(Change::Unchanged);
+        }
+        self.check_app_path()?;
+        let file = self.get_file()?;
+        let legacy = self.get_legacy_file()?.is_some_and(|x| x.exists());
+        let unit_written = !self.systemd_unit || self.is_systemd_unit_written()?;
+        if unit_written
+            && !legacy
+            && file.exists()
+            && fs::read_to_string(&file)? == self.get_desktop_entry(false)?
+        {
+            return Ok(Change::Unchanged);
+        }
+        let previous = fs::read(&file).ok();
+        self.write_desktop_entry(false)?;
+        if self.systemd_unit {
+            // don't leave the desktop entry without the unit, nor remove the existing one
+            if let Err(e) = self.write_systemd_unit() {
+                let _ = match previous {
+                    Some(data) => write_atomic(&file, &data),
+                    None => fs::remove_file(&file),
+                };
+                return Err(e);
+            }
+        }
+        Ok(Change::Changed)
+    }
+
+    /// Enable the AutoLaunch setting for the next login only
+    ///
+    /// There is no native equivalent on Linux, so the desktop entry launches
+    /// the app via `sh -c`, which removes the desktop entry itself first.
+    ///
+    /// ## Errors
+    ///
+    /// The same as `enable`.
+    pub fn enable_once(&self) -> Result<()> {
+        if is_disabled_by_env() {
+            return Ok(());
+        }
+        self.check_app_path()?;
+        self.write_desktop_entry(true)
+    }
 
-        let dir = get_dir();
+    /// Check the `app_path` exists if `require_app_path_exists`,
+    /// looked up in `PATH` with `use_path_lookup`
+    fn check_app_path(&self) -> Result<()> {
+        if !self.require_app_path_exists {
+            return Ok(());
+        }
+        let exists = match self.use_path_lookup {
+            true => std::env::var_os("PATH").is_some_and(|paths| {
+                std::env::split_paths(&paths).any(|dir| dir.join(&self.app_path).is_file())
+            }),
+            false => Path::new(&self.canonical_app_path).exists(),
+        };
+        match exists {
+            true => Ok(()),
+            false => Err(Error::AppPathDoesntExist(self.app_path.clone().into())),
+        }
+    }
+
+    fn write_desktop_entry(&self, once: bool) -> Result<()> {
+        let data = self.get_desktop_entry(once)?;
+        validate_desktop_entry(&data)?;
+
+        let dir = get_dir()?;
         if !dir.exists() {
             fs::create_dir_all(&dir).or_else(|e| {
                 if e.kind() == std::io::ErrorKind::AlreadyExists {
@@ -52,40 +143,722 @@ impl AutoLaunch {
                 }
             })?;
         }
-        let mut file = fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(self.get_file())?;
-        file.write_all(data.as_bytes())?;
+        let file = self.get_file()?;
+        retry_transient_io(self.file_retry_count, || {
+            write_atomic(&file, data.as_bytes())
+        })?;
+
+        // avoid launching twice with the entry written by the former versions
+        if let Some(legacy) = self.get_legacy_file()?.filter(|x| x.exists()) {
+            retry_transient_io(self.file_retry_count, || fs::remove_file(&legacy))?;
+        }
         Ok(())
     }
 
     /// Disable the AutoLaunch setting
     ///
+    /// Returns `Change::Unchanged` if there is no desktop entry (or systemd unit) to remove,
+    /// including when the home dir or the autostart dir doesn't exist.
+    ///
     /// ## Errors
     ///
-    /// - failed to remove file `~/.config/autostart/{app_name}.desktop`
-    pub fn disable(&self) -> Result<()> {
-        let file = self.get_file();
-        if file.exists() {
-            fs::remove_file(file)?;
+    /// - failed to remove file `~/.config/autostart/{identifier}.desktop`
+    /// - the file is not removable by the current process after `file_retry_count` retries,
+    ///   e.g. in a read-only dir or owned by another user, returns `Error::PermissionDenied`
+    /// - failed to remove the systemd unit or its link, when `systemd_unit` is true,
+    ///   both the desktop entry and the unit are tried and the failures
+    ///   are returned as `Error::PurgeFailed`
+    pub fn disable(&self) -> Result<Change> {
+        // nothing was ever written without the dirs, e.g. on a fresh or headless account
+        let dir_exists = |dir: Result<PathBuf>| dir.is_ok_and(|x| fs::symlink_metadata(x).is_ok());
+        if !(dir_exists(get_dir()) || self.systemd_unit && dir_exists(get_systemd_dir())) {
+            return Ok(Change::Unchanged);
+        }
+
+        let mut change = Change::Unchanged;
+        let mut remove = |files: Vec<PathBuf>| -> Result<()> {
+            for file in files {
+                if fs::symlink_metadata(&file).is_ok() {
+                    let removed =
+                        retry_transient_io(self.file_retry_count, || fs::remove_file(&file));
+                    removed.map_err(|e| match e.kind() {
+                        std::io::ErrorKind::PermissionDenied => Error::PermissionDenied(format!(
+                            "the entry `{}` is not removable by the current user, \
+                            elevation may be required: {}",
+                            file.display(),
+                            e
+                        )),
+                        _ => e.into(),
+                    })?;
+                    change = Change::Changed;
+                }
+            }
+            Ok(())
+        };
+        let entries = self
+            .get_legacy_file()?
+            .into_iter()
+            .chain([self.get_file()?]);
+        if self.systemd_unit {
+            let entries = remove(entries.collect());
+            collect_failures([entries, remove(self.get_systemd_unit_files()?)])?;
+        } else {
+            remove(entries.collect())?;
+        }
+        Ok(change)
+    }
+
+    /// Remove the entries written by any version of this crate, for uninstalling
+    ///
+    /// It is best-effort, the missing entries are skipped, and all the entries
+    /// are tried even if some of them fail.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to remove some of the entries, returns `Error::PurgeFailed`
+    pub fn purge_all(&self) -> Result<()> {
+        let files = self
+            .get_legacy_file()?
+            .into_iter()
+            .chain([self.get_file()?]);
+        let files = files.chain(self.get_systemd_unit_files()?);
+        collect_failures(
+            files
+                .filter(|x| fs::symlink_metadata(x).is_ok())
+                .map(|file| {
+                    fs::remove_file(file)?;
+                    Ok(())
+                }),
+        )
+    }
+
+    /// Replace the args in the `Exec` value of the existing desktop entry in place
+    ///
+    /// The `Exec` value is rebuilt the same way as `enable` with the new args, keeping
+    /// the `raw_args`, the startup sentinel, and the shell wrapping of `wrap_in_login_shell`,
+    /// `working_dir_via_shell` or `enable_once`. All the other keys are kept as is,
+    /// so that the manual edits to the desktop entry survive.
+    /// The systemd unit is rewritten too if it is written, when `systemd_unit` is true.
+    ///
+    /// ## Errors
+    ///
+    /// - the desktop entry doesn't exist, returns `std::io::ErrorKind::NotFound`
+    /// - failed to read the desktop entry, or write it back
+    /// - failed to write the systemd unit
+    pub fn set_exec_args(&self, args: &[impl AsRef<str>]) -> Result<()> {
+        let file = [Some(self.get_file()?), self.get_legacy_file()?]
+            .into_iter()
+            .flatten()
+            .find(|x| x.exists())
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("the desktop entry of `{}` is not found", self.app_name),
+                )
+            })?;
+
+        let mut auto = self.clone();
+        auto.args = args.iter().map(|x| x.as_ref().to_string()).collect();
+        // the sentinel appended to the args by the builder
+        if self.args.iter().any(|x| x == LAUNCHED_AT_STARTUP_ARG)
+            && !auto.args.iter().any(|x| x == LAUNCHED_AT_STARTUP_ARG)
+        {
+            auto.args.push(LAUNCHED_AT_STARTUP_ARG.into());
+        }
+        // the entry written by `enable_once` removes itself before launching
+        let once_script = format!("rm -f {}", shell_quote(&self.get_file()?.to_string_lossy()));
+        let once_script = escape_exec(&once_script.replace('\'', r"'\''"));
+
+        let content = fs::read_to_string(&file)?;
+        let mut group = "";
+        let mut lines = vec![];
+        for line in content.split_inclusive('\n') {
+            let text = line.trim_end_matches(['\r', '\n']);
+            if text.starts_with('[') {
+                group = text;
+            }
+            match text.strip_prefix("Exec=") {
+                // the actions have the `Exec` keys of their own
+                Some(exec) if group == "[Desktop Entry]" => {
+                    let exec = auto.get_exec(exec.contains(&once_script))?;
+                    lines.push(format!("Exec={}{}", exec, &line[text.len()..]));
+                }
+                _ => lines.push(line.to_string()),
+            }
+        }
+        let content = lines.concat();
+        retry_transient_io(self.file_retry_count, || {
+            write_atomic(&file, content.as_bytes())
+        })?;
+
+        if self.systemd_unit && self.get_systemd_unit_file()?.exists() {
+            let (unit, data) = (auto.get_systemd_unit_file()?, auto.get_systemd_unit()?);
+            retry_transient_io(self.file_retry_count, || {
+                write_atomic(&unit, data.as_bytes())
+            })?;
         }
         Ok(())
     }
 
     /// Check whether the AutoLaunch setting is enabled
+    ///
+    /// With `systemd_unit`, it is true if either the desktop entry or the unit is enabled.
     pub fn is_enabled(&self) -> Result<bool> {
-        Ok(self.get_file().exists())
+        if is_disabled_by_env() {
+            return Ok(false);
+        }
+        let legacy = self.get_legacy_file()?.is_some_and(|x| x.exists());
+        let unit = self.systemd_unit && self.get_systemd_unit_link()?.exists();
+        Ok(self.get_file()?.exists() || legacy || unit)
+    }
+
+    /// Check whether the AutoLaunch setting is enabled, and why not if it's not
+    ///
+    /// The desktop entry is disabled by the desktop environment with `Hidden=true`
+    /// or `X-GNOME-Autostart-enabled=false`, e.g. in GNOME Tweaks.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to read the desktop entry
+    pub fn enabled_status(&self) -> Result<EnabledStatus> {
+        if !self.is_enabled()? {
+            return Ok(EnabledStatus::NotRegistered);
+        }
+        let file = [Some(self.get_file()?), self.get_legacy_file()?]
+            .into_iter()
+            .flatten()
+            .find(|x| x.exists());
+        if let Some(file) = file {
+            let content = fs::read_to_string(file)?;
+            let disabled = content.lines().any(|line| {
+                matches!(
+                    line.trim(),
+                    "Hidden=true" | "X-GNOME-Autostart-enabled=false"
+                )
+            });
+            if disabled {
+                return Ok(EnabledStatus::RegisteredButDisabledByOs);
+            }
+        }
+        match self.diff()? {
+            StateDiff::Differs { .. } => Ok(EnabledStatus::RegisteredButPathMismatch),
+            // only the systemd unit is present
+            _ => Ok(EnabledStatus::Enabled),
+        }
+    }
+
+    /// Get the last modified time of the desktop entry, or `None` if it doesn't exist
+    ///
+    /// ## Errors
+    ///
+    /// - failed to read the metadata of the desktop entry
+    pub fn entry_modified_time(&self) -> Result<Option<SystemTime>> {
+        let file = [Some(self.get_file()?), self.get_legacy_file()?]
+            .into_iter()
+            .flatten()
+            .find(|x| x.exists());
+        match file {
+            Some(file) => Ok(Some(fs::metadata(file)?.modified()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Compare the configured entry against the desktop entry on the system
+    ///
+    /// The `Exec` values are compared, and `actual` is the `Exec` value on the system.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to read the desktop entry
+    pub fn diff(&self) -> Result<StateDiff> {
+        let file = [Some(self.get_file()?), self.get_legacy_file()?]
+            .into_iter()
+            .flatten()
+            .find(|x| x.exists());
+        let Some(file) = file else {
+            return Ok(StateDiff::Missing);
+        };
+
+        let content = fs::read_to_string(file)?;
+        let actual = content
+            .lines()
+            .find_map(|line| line.strip_prefix("Exec="))
+            .unwrap_or_default();
+        if actual == self.get_exec(false)? {
+            Ok(StateDiff::Matches)
+        } else {
+            Ok(StateDiff::Differs {
+                actual: actual.into(),
+            })
+        }
+    }
+
+    /// Export the desktop entry on the system as a string, e.g. for the bug reports
+    ///
+    /// It is the path of the desktop entry in a `#` comment line, followed by its content.
+    ///
+    /// ## Errors
+    ///
+    /// - the desktop entry is not found
+    /// - failed to read the desktop entry
+    pub fn export_entry(&self) -> Result<String> {
+        let file = [Some(self.get_file()?), self.get_legacy_file()?]
+            .into_iter()
+            .flatten()
+            .find(|x| x.exists());
+        let Some(file) = file else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("the desktop entry `{}` is not found", self.app_name),
+            )
+            .into());
+        };
+        let content = fs::read_to_string(&file)?;
+        Ok(format!("# {}\n{}", file.display(), content))
     }
 
-    /// Get the desktop entry file path
-    fn get_file(&self) -> PathBuf {
-        get_dir().join(format!("{}.desktop", self.app_name))
+    /// List all the desktop entries in the autostart dir `~/.config/autostart`
+    ///
+    /// The `name` is the file stem of the desktop entry,
+    /// and the `command` is the value of its `Exec` key.
+    /// The entries which can't be read, e.g. not UTF-8, are skipped.
+    pub fn list_entries() -> Result<Vec<EntryInfo>> {
+        let dir = get_dir()?;
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut entries = vec![];
+        for file in fs::read_dir(dir)?.flatten() {
+            let path = file.path();
+            if path.extension().is_none_or(|ext| ext != "desktop") {
+                continue;
+            }
+            let name = match path.file_stem() {
+                Some(stem) => stem.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(_e) => {
+                    #[cfg(feature = "log")]
+                    log::warn!("auto-launch: skipped `{}`: {}", path.display(), _e);
+                    continue;
+                }
+            };
+            let command = content
+                .lines()
+                .find_map(|line| line.strip_prefix("Exec="))
+                .unwrap_or_default()
+                .to_string();
+            entries.push(EntryInfo { name, command });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
     }
+
+    /// Count the desktop entries in `~/.config/autostart` for the app,
+    /// named by the `app_name` or launching the `app_path`
+    ///
+    /// A count greater than 1 means duplicates, e.g. left by an older version with another name.
+    ///
+    /// The entries which can't be read, e.g. not UTF-8, are skipped.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to read the autostart dir
+    pub fn entry_count(&self) -> Result<usize> {
+        let dir = get_dir()?;
+        if !dir.exists() {
+            return Ok(0);
+        }
+
+        let files = [Some(self.get_file()?), self.get_legacy_file()?];
+        // the program as written in `Exec`, also after `exec` of the `sh -c` wrapper
+        let program = self.get_program().replace('\\', r"\\").replace('%', "%%");
+        let launches_app = |exec: &str| {
+            split_exec_program(exec).0 == program
+                || exec.match_indices("exec ").any(|(i, token)| {
+                    // the `exec` command in the script, not a part of another word
+                    if !exec[..i].ends_with([' ', '\'']) {
+                        return false;
+                    }
+                    // the script ends with the closing `'` of `sh -c`
+                    let script = exec[i + token.len()..].trim_end_matches('\'');
+                    split_exec_program(script).0 == program
+                })
+        };
+
+        let mut count = 0;
+        for file in fs::read_dir(dir)?.flatten() {
+            let path = file.path();
+            if path.extension().is_none_or(|ext| ext != "desktop") {
+                continue;
+            }
+            if files.contains(&Some(path.clone())) {
+                count += 1;
+                continue;
+            }
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(_e) => {
+                    #[cfg(feature = "log")]
+                    log::warn!("auto-launch: skipped `{}`: {}", path.display(), _e);
+                    continue;
+                }
+            };
+            let exec = content.lines().find_map(|line| line.strip_prefix("Exec="));
+            if exec.is_some_and(launches_app) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Check whether the current session honors the desktop entries in `~/.config/autostart`
+    ///
+    /// It is false in headless sessions without `DISPLAY` or `WAYLAND_DISPLAY`,
+    /// or without a freedesktop desktop environment in `XDG_CURRENT_DESKTOP`,
+    /// e.g. a bare window manager, where the entries are never read.
+    /// Apps can warn the user or choose another mechanism in such case.
+    pub fn session_supports_autostart() -> bool {
+        let var = |key: &str| std::env::var(key).ok().filter(|x| !x.is_empty());
+        if var("DISPLAY").is_none() && var("WAYLAND_DISPLAY").is_none() {
+            return false;
+        }
+        // the window managers which don't read the autostart dir by themselves
+        const UNSUPPORTED: [&str; 5] = ["i3", "sway", "Hyprland", "bspwm", "dwm"];
+        var("XDG_CURRENT_DESKTOP").is_some_and(|desktops| {
+            desktops
+                .split(':')
+                .any(|x| !x.is_empty() && !UNSUPPORTED.iter().any(|u| u.eq_ignore_ascii_case(x)))
+        })
+    }
+
+    /// Describe the changes that `enable`, `enable_once` or `disable` would make,
+    /// without touching the system
+    ///
+    /// ## Errors
+    ///
+    /// - the home dir is not found, returns `Error::HomeDirNotFound`
+    pub fn plan(&self, action: Action) -> Result<Vec<PlannedChange>> {
+        let mut changes = vec![];
+        if action != Action::Disable {
+            changes.push(PlannedChange::WriteFile {
+                path: self.get_file()?,
+                content: self.get_desktop_entry(action == Action::EnableOnce)?,
+            });
+        } else if self.get_file()?.exists() {
+            changes.push(PlannedChange::RemoveFile {
+                path: self.get_file()?,
+            });
+        }
+        if let Some(path) = self.get_legacy_file()?.filter(|x| x.exists()) {
+            changes.push(PlannedChange::RemoveFile { path });
+        }
+        if self.systemd_unit && action == Action::Enable {
+            changes.push(PlannedChange::WriteFile {
+                path: self.get_systemd_unit_file()?,
+                content: self.get_systemd_unit()?,
+            });
+            changes.push(PlannedChange::CreateSymlink {
+                path: self.get_systemd_unit_link()?,
+                target: self.get_systemd_unit_file()?,
+            });
+        } else if self.systemd_unit && action == Action::Disable {
+            let files = self.get_systemd_unit_files()?.into_iter();
+            for path in files.filter(|x| fs::symlink_metadata(x).is_ok()) {
+                changes.push(PlannedChange::RemoveFile { path });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Get the description written as the `Comment` of the desktop entry,
+    /// default is `{app_name} startup script`
+    pub fn get_description(&self) -> String {
+        match &self.description {
+            Some(description) => description.clone(),
+            None => format!("{} startup script", self.app_name),
+        }
+    }
+
+    /// Get the path of the desktop entry managed by this instance,
+    /// `~/.config/autostart/{identifier}.desktop`
+    ///
+    /// The home dir is resolved if it is a symlink, the same for writing and reading the entry.
+    ///
+    /// ## Errors
+    ///
+    /// - the home dir is not found, returns `Error::HomeDirNotFound`
+    pub fn get_desktop_entry_path(&self) -> Result<PathBuf> {
+        self.get_file()
+    }
+
+    /// Get the systemd user unit content, started with `default.target`
+    fn get_systemd_unit(&self) -> Result<String> {
+        let working_dir = match self.get_path_key() {
+            Some(dir) => format!("WorkingDirectory={}\n", dir),
+            None => String::new(),
+        };
+        Ok(format!(
+            "[Unit]\n\
+            Description={}\n\
+            \n\
+            [Service]\n\
+            ExecStart={}\n\
+            {}\
+            \n\
+            [Install]\n\
+            WantedBy=default.target\n",
+            self.get_description(),
+            self.get_command(false)?.replace('%', "%%"),
+            working_dir
+        ))
+    }
+
+    /// Write the systemd user unit, and link it into `default.target.wants`
+    /// the same way as `systemctl --user enable`
+    fn write_systemd_unit(&self) -> Result<()> {
+        let link = self.get_systemd_unit_link()?;
+        if let Some(dir) = link.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let (file, data) = (self.get_systemd_unit_file()?, self.get_systemd_unit()?);
+        retry_transient_io(self.file_retry_count, || {
+            write_atomic(&file, data.as_bytes())
+        })?;
+        if fs::symlink_metadata(&link).is_err() {
+            std::os::unix::fs::symlink(self.get_systemd_unit_file()?, link)?;
+        }
+        Ok(())
+    }
+
+    /// Check whether the same systemd unit is written and linked
+    fn is_systemd_unit_written(&self) -> Result<bool> {
+        let file = self.get_systemd_unit_file()?;
+        Ok(file.exists()
+            && self.get_systemd_unit_link()?.exists()
+            && fs::read_to_string(file)? == self.get_systemd_unit()?)
+    }
+
+    /// Get the desktop entry content, customized by the `entry_customizer`
+    fn get_desktop_entry(&self, once: bool) -> Result<String> {
+        let mut extra = String::new();
+        if let Some(generic_name) = &self.generic_name {
+            extra += &format!("GenericName={}\n", generic_name);
+        }
+        if !self.keywords.is_empty() {
+            // the `;` inside a keyword is escaped, as it separates the list
+            let keywords: String = self
+                .keywords
+                .iter()
+                .map(|x| x.replace('\\', r"\\").replace(';', r"\;") + ";")
+                .collect();
+            extra += &format!("Keywords={}\n", keywords);
+        }
+        if let Some(wm_class) = &self.startup_wm_class {
+            extra += &format!("StartupWMClass={}\n", wm_class);
+        }
+        if let Some(dir) = self.get_path_key() {
+            extra += &format!("Path={}\n", dir);
+        }
+        if self.dbus_activatable {
+            extra += "DBusActivatable=true\n";
+        }
+        let content = format_desktop_entry(
+            self.display_name.as_ref().unwrap_or(&self.app_name),
+            &self.get_description(),
+            &self.get_exec(once)?,
+            &extra,
+        );
+        Ok(self.entry_customizer.apply(content))
+    }
+
+    /// Get the `working_dir` written as the key, unless it is changed to via the shell
+    fn get_path_key(&self) -> Option<&str> {
+        let dir = self.working_dir.as_deref();
+        dir.filter(|_| !self.working_dir_via_shell)
+    }
+
+    /// Get the `Exec` value of the desktop entry
+    ///
+    /// The backslashes and `%` of the command are escaped for the string value.
+    fn get_exec(&self, once: bool) -> Result<String> {
+        Ok(escape_exec(&self.get_command(once)?))
+    }
+
+    /// Get the quoted program of the command, the bare command name with `use_path_lookup`
+    fn get_program(&self) -> String {
+        match self.use_path_lookup {
+            true => shell_quote(&self.app_path),
+            false => shell_quote(&self.canonical_app_path),
+        }
+    }
+
+    /// Get the command to launch the app
+    ///
+    /// The path and args are quoted as the desktop entry spec requires.
+    /// The `${NAME}` placeholders in args are resolved into literal values,
+    /// as the `Exec` key doesn't expand the environment variables.
+    /// With `use_path_lookup`, the `app_path` is written as the bare command name instead.
+    fn get_command(&self, once: bool) -> Result<String> {
+        let mut command = vec![self.get_program()];
+        command.extend(self.get_resolved_args().iter().map(|x| shell_quote(x)));
+        command.extend(self.raw_args.clone());
+        let command = command.join(" ");
+
+        let cd = self
+            .working_dir
+            .as_ref()
+            .filter(|_| self.working_dir_via_shell);
+        if self.wrap_in_login_shell || once || cd.is_some() {
+            // source the login profile, e.g. `sh -lc 'exec "/path/to/app" --arg'`
+            let flags = if self.wrap_in_login_shell {
+                "-lc"
+            } else {
+                "-c"
+            };
+            let mut script = format!("exec {}", command);
+            if let Some(dir) = cd {
+                script = format!("cd {} && {}", shell_quote(dir), script);
+            }
+            if once {
+                let file = self.get_file()?.to_string_lossy().into_owned();
+                script = format!("rm -f {}; {}", shell_quote(&file), script);
+            }
+            Ok(format!("sh {} '{}'", flags, script.replace('\'', r"'\''")))
+        } else {
+            Ok(command)
+        }
+    }
+
+    /// Get the desktop entry file path, named by the normalized `app_name`,
+    /// or the `dbus_name` when `dbus_activatable`
+    fn get_file(&self) -> Result<PathBuf> {
+        if let Some(name) = self.dbus_name.as_ref().filter(|_| self.dbus_activatable) {
+            return Ok(get_dir()?.join(format!("{}.desktop", name)));
+        }
+        let identifier = normalize_identifier(&self.app_name);
+        Ok(get_dir()?.join(format!("{}.desktop", identifier)))
+    }
+
+    /// Get the file path named by the raw `app_name` used by the former versions,
+    /// if it differs from `get_file`
+    fn get_legacy_file(&self) -> Result<Option<PathBuf>> {
+        let file = get_dir()?.join(format!("{}.desktop", self.app_name));
+        Ok((!self.app_name.contains('/') && file != self.get_file()?).then_some(file))
+    }
+
+    /// Get the systemd user unit file path, named by the normalized `app_name`
+    fn get_systemd_unit_file(&self) -> Result<PathBuf> {
+        let identifier = normalize_identifier(&self.app_name);
+        Ok(get_systemd_dir()?.join(format!("{}.service", identifier)))
+    }
+
+    /// Get the link of the systemd unit in `default.target.wants`
+    fn get_systemd_unit_link(&self) -> Result<PathBuf> {
+        let identifier = normalize_identifier(&self.app_name);
+        Ok(get_systemd_dir()?
+            .join("default.target.wants")
+            .join(format!("{}.service", identifier)))
+    }
+
+    /// Get the systemd unit link and file, in the order to remove
+    fn get_systemd_unit_files(&self) -> Result<Vec<PathBuf>> {
+        Ok(vec![
+            self.get_systemd_unit_link()?,
+            self.get_systemd_unit_file()?,
+        ])
+    }
+}
+
+/// Check whether the `name` is a D-Bus well-known name, e.g. `org.example.App`
+///
+/// It has at least two `.` separated elements of `[A-Za-z0-9_-]`,
+/// none of which starts with a digit, and is at most 255 bytes.
+pub(crate) fn is_dbus_name(name: &str) -> bool {
+    let valid_element = |x: &str| {
+        !x.is_empty()
+            && !x.starts_with(|c: char| c.is_ascii_digit())
+            && x.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+    name.len() <= 255 && name.split('.').count() >= 2 && name.split('.').all(valid_element)
+}
+
+/// Check the desktop entry still parses after customized
+///
+/// The first group must be `[Desktop Entry]` with the `Exec` key, and every other line
+/// is a group header, a `Key=Value` pair, a `#` comment or blank.
+fn validate_desktop_entry(content: &str) -> Result<()> {
+    let invalid =
+        |msg: String| Error::InvalidInput(format!("the customized desktop entry {}", msg));
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'));
+    if lines.next() != Some("[Desktop Entry]") {
+        return Err(invalid(
+            "doesn't start with the group [Desktop Entry]".into(),
+        ));
+    }
+
+    let mut main_group = true;
+    let mut has_exec = false;
+    for line in lines {
+        if line.starts_with('[') && line.ends_with(']') {
+            main_group = false;
+            continue;
+        }
+        match line.split_once('=') {
+            Some((key, _)) if !key.trim().is_empty() => {
+                has_exec |= main_group && key.trim() == "Exec";
+            }
+            _ => return Err(invalid(format!("has an invalid line: {}", line))),
+        }
+    }
+    if !has_exec {
+        return Err(invalid(
+            "has no key Exec in the group [Desktop Entry]".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Split the `Exec` value into the program and the rest args,
+/// the program may be double quoted with the backslash escapes
+fn split_exec_program(exec: &str) -> (&str, &str) {
+    let mut end = exec.find(' ').unwrap_or(exec.len());
+    if let Some(rest) = exec.strip_prefix('"') {
+        end = exec.len();
+        let mut chars = rest.char_indices();
+        let mut escaped = false;
+        while let Some((i, mut c)) = chars.next() {
+            // the string escape `\\` stands for a backslash of the quoting
+            if c == '\\' {
+                let Some((_, next)) = chars.next() else { break };
+                c = next;
+            }
+            if !escaped && c == '"' {
+                end = i + 2;
+                break;
+            }
+            escaped = !escaped && c == '\\';
+        }
+    }
+    (&exec[..end], exec[end..].trim_start())
+}
+
+/// Get the systemd user unit dir
+fn get_systemd_dir() -> Result<PathBuf> {
+    Ok(get_canonical_home_dir()?
+        .join(".config")
+        .join("systemd")
+        .join("user"))
 }
 
 /// Get the autostart dir
-fn get_dir() -> PathBuf {
-    dirs::home_dir().unwrap().join(".config").join("autostart")
+fn get_dir() -> Result<PathBuf> {
+    Ok(get_canonical_home_dir()?.join(".config").join("autostart"))
 }
@@ -1,8 +1,15 @@
-use crate::{AutoLaunch, Error, Result};
+use crate::{
+    canonicalize_app_path, collect_failures, get_canonical_home_dir, is_disabled_by_env,
+    normalize_identifier,
+    render::{format_plist, plist_entry, xml_escape},
+    retry_transient_io, shell_quote, write_atomic, Action, AutoLaunch, CalendarInterval, Change,
+    CommandRunner, EnabledStatus, EntryInfo, Error, PlannedChange, Result, SharedCommandRunner,
+    SharedEntryCustomizer, StateDiff, SystemCommandRunner,
+};
 use std::fs;
-use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output};
+use std::process::Output;
+use std::time::SystemTime;
 
 /// macOS implement
 impl AutoLaunch {
@@ -11,6 +18,10 @@ impl AutoLaunch {
     /// - `app_path`: application path
     /// - `use_launch_agent`: whether use Launch Agent or AppleScript
     /// - `args`: startup args passed to the binary
+    /// - `bundle_identifiers`: bundle identifiers to locate the installed app,
+    ///   see `resolve_bundle_identifier`
+    /// - `agent_extra_config`: extra plist entries appended to the Launch Agent,
+    ///   see `AutoLaunchBuilder::set_agent_extra_config`
     ///
     /// ## Notes
     ///
@@ -18,9 +29,22 @@ impl AutoLaunch {
     ///
     /// The `app_name` should be same as the basename of the `app_path`
     ///     when `use_launch_agent` is false, or it will be corrected automatically.
+    ///     The corrected name is computed once here from the canonical `app_path`,
+    ///     without the `.app` extension, and `get_app_name` returns it.
+    ///     `enable`, `disable` and `is_enabled` all look up the login item by it,
+    ///     so the instances with different `app_name` for the same `app_path`
+    ///     manage the same login item.
     ///
-    /// The `app_path` should be the **absolute path** and **exists**,
-    ///     otherwise it will cause an error when `enable`.
+    /// The `app_path` should **exist**, otherwise `enable` returns `Error::AppPathDoesntExist`.
+    ///     The leading `~` is expanded and the relative path is resolved against the current dir
+    ///     here, see `get_canonical_app_path`, so the checked path is always absolute.
+    ///     The relative path of the Launch Agent is resolved against the dir of
+    ///     `std::env::current_exe` instead when `enable`, e.g. `helper` for the binary next to the app.
+    ///
+    /// The `app_path` can be either the `.app` bundle or the executable inside it,
+    ///     e.g. `/Applications/Foo.app` or `/Applications/Foo.app/Contents/MacOS/Foo`.
+    ///     The login item is made for the bundle, while the Launch Agent
+    ///     launches the executable, resolved from the `CFBundleExecutable` of `Info.plist`.
     ///
     /// In case using AppleScript (`use_launch_agent=false`),
     ///     only `"--hidden"` and `"--minimized"` in `args` are valid.
@@ -29,142 +53,367 @@ impl AutoLaunch {
         app_path: &str,
         use_launch_agent: bool,
         args: &[impl AsRef<str>],
+        bundle_identifiers: &[impl AsRef<str>],
+        agent_extra_config: &str,
     ) -> AutoLaunch {
-        let mut name = app_name;
+        let canonical_app_path = canonicalize_app_path(app_path);
+        let mut name = app_name.to_string();
         if !use_launch_agent {
             // the app_name should be same as the executable's name
             // when using login item
-            let end = if app_path.ends_with(".app") { 4 } else { 0 };
-            let end = app_path.len() - end;
-            let begin = match app_path.rfind('/') {
-                Some(i) => i + 1,
-                None => 0,
-            };
-            name = &app_path[begin..end];
+            name = get_login_item_name(get_bundle_path(&canonical_app_path)).into();
         }
 
         AutoLaunch {
-            app_name: name.into(),
+            app_name: name,
             app_path: app_path.into(),
+            canonical_app_path,
             use_launch_agent,
             args: args.iter().map(|s| s.as_ref().to_string()).collect(),
+            raw_args: None,
+            bundle_identifiers: bundle_identifiers
+                .iter()
+                .map(|s| s.as_ref().to_string())
+                .collect(),
+            agent_extra_config: agent_extra_config.into(),
+            label: None,
+            plist_filename: None,
+            run_at_load: true,
+            start_interval: None,
+            start_calendar_interval: None,
+            watch_paths: Vec::new(),
+            session_type: None,
+            unload_on_disable: false,
+            reload_on_change: false,
+            verify_loaded: false,
+            command_runner: SharedCommandRunner::default(),
+            binary_plist: false,
+            force_overwrite: false,
+            entry_customizer: SharedEntryCustomizer::default(),
+            file_retry_count: 2,
         }
     }
 
     /// Enable the AutoLaunch setting
     ///
+    /// Returns `Change::Unchanged` if the same plist is already written,
+    /// or the login item of the same path already exists, or `AUTO_LAUNCH_DISABLE` is set.
+    ///
+    /// The Launch Agent plist is written to a temp file and renamed into place,
+    /// so that it is never left truncated.
+    ///
     /// ## Errors
     ///
-    /// - `app_path` does not exist
-    /// - `app_path` is not absolute
+    /// - `app_path` does not exist, returns `Error::AppPathDoesntExist`
+    ///
+    /// When `app_path` is empty or relative, the application resolved from the
+    /// `bundle_identifiers` will be used instead, see `resolve_bundle_identifier`.
+    /// Otherwise, the relative `app_path` of the Launch Agent is resolved against
+    /// the dir of the current executable, and the other relative `app_path`
+    /// against the current dir, so the checked path is always absolute.
     ///
     /// #### Launch Agent
     ///
+    /// - running in the App Sandbox, see `is_sandboxed`
+    /// - `agent_extra_config` is malformed or duplicates the generated keys
+    /// - both `start_interval` and `start_calendar_interval` are set,
+    ///   or the `start_calendar_interval` fields are out of range
+    /// - `session_type` is not a known session type
+    /// - the generated plist is malformed, e.g. by the `entry_customizer`,
+    ///   returns `Error::InvalidInput` before written
     /// - failed to create dir `~/Library/LaunchAgents`
-    /// - failed to create file `~/Library/LaunchAgents/{app_name}.plist`
-    /// - failed to write bytes to the file
+    /// - failed to create file `~/Library/LaunchAgents/{identifier}.plist`,
+    ///   where the `identifier` is the `plist_filename` (or label, or `app_name`)
+    ///   normalized to be filesystem-safe
+    /// - failed to write bytes to the temp file in the same dir, or rename it to the file
+    /// - failed to convert the plist via `plutil`, when `binary_plist` is true
+    /// - the existing plist at the same path has another `Label`, returns `Error::Conflict`,
+    ///   unless `force_overwrite` is true
+    /// - failed to execute the `launchctl` command, or to load the changed plist,
+    ///   when `reload_on_change` is true
     ///
     /// #### AppleScript
     ///
     /// - failed to execute the `osascript` command, check the exit status or stderr for details
-    pub fn enable(&self) -> Result<()> {
-        let path = Path::new(&self.app_path);
+    /// - `osascript` is not installed, returns `Error::Backend`
+    /// - the app is not authorized to control System Events, returns `Error::PermissionDenied`
+    pub fn enable(&self) -> Result<Change> {
+        if is_disabled_by_env() {
+            return Ok(Change::Unchanged);
+        }
+        let app_path = self.check_app_path()?;
 
-        if !path.exists() {
-            return Err(Error::AppPathDoesntExist(path.to_path_buf()));
+        if self.use_launch_agent {
+            check_sandbox()?;
+            let file = self.get_file()?;
+            let legacy = self.get_legacy_file()?.is_some_and(|x| x.exists());
+            if !legacy
+                && file.exists()
+                && read_plist(self.runner(), &file)? == self.get_expected_plist()?
+            {
+                return Ok(Change::Unchanged);
+            }
+            // the plist exists but differs here, reload it only if the agent is loaded
+            let reload = self.reload_on_change && file.exists() && self.is_agent_loaded()?;
+            if reload {
+                // the failure means that the agent is unloaded meanwhile, go on writing
+                self.run_launchctl(&get_unload_args(&file))?;
+            }
+            self.write_plist(false)?;
+            if reload {
+                let output = self.run_launchctl(&get_load_args(&file))?;
+                if !output.status.success() {
+                    return Err(std::io::Error::other(format!(
+                        "failed to load the Launch Agent: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ))
+                    .into());
+                }
+            }
+        } else {
+            // skip if the login item already exists, or replace the stale ones
+            let paths = self.get_login_item_paths()?;
+            if paths.len() == 1 && paths[0] == get_bundle_path(&app_path) {
+                return Ok(Change::Unchanged);
+            }
+            self.delete_login_items(paths.len())?;
+
+            let output = exec_apple_script(self.runner(), &self.get_make_login_item_command())?;
+            if !output.status.success() {
+                return Err(Error::AppleScriptFailed(output.status.code().unwrap_or(1)));
+            }
         }
+        Ok(Change::Changed)
+    }
 
-        if !path.is_absolute() {
-            return Err(Error::AppPathIsNotAbsolute(path.to_path_buf()));
+    /// Enable the AutoLaunch setting for the next login only
+    ///
+    /// There is no native equivalent on macOS, so the Launch Agent launches
+    /// the app via `/bin/sh -c`, which removes the plist itself first.
+    ///
+    /// ## Errors
+    ///
+    /// - `use_launch_agent` is false, the login item can't remove itself
+    /// - the same as `enable` with Launch Agent
+    pub fn enable_once(&self) -> Result<()> {
+        if is_disabled_by_env() {
+            return Ok(());
         }
+        if !self.use_launch_agent {
+            return Err(Error::InvalidInput(
+                "enable_once requires use_launch_agent to be true".into(),
+            ));
+        }
+        check_sandbox()?;
+        self.check_app_path()?;
+        self.write_plist(true)
+    }
 
-        if self.use_launch_agent {
-            let dir = get_dir();
-            if !dir.exists() {
-                fs::create_dir(&dir)?;
-            }
+    /// Check the effective app path exists, which is already resolved to be absolute
+    ///
+    /// It is checked before writing anything, since launchd or AppleScript
+    /// would only fail at the next login without a clear message.
+    fn check_app_path(&self) -> Result<String> {
+        let app_path = self.get_effective_app_path();
+        let path = Path::new(&app_path);
+        if !path.exists() {
+            return Err(Error::AppPathDoesntExist(path.to_path_buf()));
+        }
+        Ok(app_path)
+    }
 
-            let mut args = vec![self.app_path.clone()];
-            args.extend_from_slice(&self.args);
+    /// Write the Launch Agent plist
+    fn write_plist(&self, once: bool) -> Result<()> {
+        self.validate_schedule()?;
+        self.validate_session_type()?;
+        self.validate_agent_extra_config()?;
+        self.check_label_conflict()?;
 
-            let section = args
-                .iter()
-                .map(|x| format!("<string>{}</string>", x))
-                .collect::<String>();
+        let dir = get_dir()?;
+        if !dir.exists() {
+            fs::create_dir(&dir)?;
+        }
 
-            let data = format!(
-                "{}\n{}\n\
-            <plist version=\"1.0\">\n  \
-            <dict>\n  \
-                <key>Label</key>\n  \
-                <string>{}</string>\n  \
-                <key>ProgramArguments</key>\n  \
-                <array>{}</array>\n  \
-                <key>RunAtLoad</key>\n  \
-                <true/>\n  \
-            </dict>\n\
-            </plist>",
-                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
-                r#"<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">"#,
-                self.app_name,
-                section
-            );
-            fs::File::create(self.get_file())?.write(data.as_bytes())?;
-        } else {
-            let hidden = self
-                .args
-                .iter()
-                .find(|arg| *arg == "--hidden" || *arg == "--minimized");
-
-            let props = format!(
-                "{{name:\"{}\",path:\"{}\",hidden:{}}}",
-                self.app_name,
-                self.app_path,
-                hidden.is_some()
-            );
-            let command = format!("make login item at end with properties {}", props);
-            let output = exec_apple_script(&command)?;
+        let data = self.get_plist(once)?;
+        validate_plist(&data)?;
+        let file = self.get_file()?;
+        retry_transient_io(self.file_retry_count, || {
+            write_atomic(&file, data.as_bytes())
+        })?;
+        if self.binary_plist {
+            let file = self.get_file()?.to_string_lossy().into_owned();
+            let output = self
+                .runner()
+                .run("plutil", &["-convert", "binary1", &file])?;
             if !output.status.success() {
-                return Err(Error::AppleScriptFailed(output.status.code().unwrap_or(1)));
+                return Err(std::io::Error::other(format!(
+                    "failed to convert the plist to binary: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ))
+                .into());
             }
         }
+
+        // avoid launching twice with the plist written by the former versions
+        if let Some(legacy) = self.get_legacy_file()?.filter(|x| x.exists()) {
+            retry_transient_io(self.file_retry_count, || fs::remove_file(&legacy))?;
+        }
         Ok(())
     }
 
     /// Disable the AutoLaunch setting
     ///
+    /// Returns `Change::Unchanged` if there is no plist or login item to remove.
+    ///
     /// ## Errors
     ///
     /// #### Launch Agent
     ///
-    /// - failed to remove file `~/Library/LaunchAgents/{app_name}.plist`
+    /// - failed to execute the `launchctl` command, when `unload_on_disable` is true
+    /// - failed to remove file `~/Library/LaunchAgents/{identifier}.plist`
     ///
     /// #### AppleScript
     ///
     /// - failed to execute the `osascript` command, check the exit status or stderr for details
-    pub fn disable(&self) -> Result<()> {
+    /// - `osascript` is not installed, returns `Error::Backend`
+    /// - the app is not authorized to control System Events, returns `Error::PermissionDenied`
+    pub fn disable(&self) -> Result<Change> {
+        let mut change = Change::Unchanged;
         if self.use_launch_agent {
-            let file = self.get_file();
-            if file.exists() {
-                fs::remove_file(file)?;
+            for file in self
+                .get_legacy_file()?
+                .into_iter()
+                .chain([self.get_file()?])
+            {
+                if file.exists() {
+                    if self.unload_on_disable {
+                        // the failure means that the agent is not loaded, go on removing
+                        self.run_launchctl(&get_unload_args(&file))?;
+                    }
+                    retry_transient_io(self.file_retry_count, || fs::remove_file(&file))?;
+                    change = Change::Changed;
+                }
             }
         } else {
-            let command = format!("delete login item \"{}\"", self.app_name);
-            let output = exec_apple_script(&command)?;
-            if !output.status.success() {
-                return Err(Error::AppleScriptFailed(output.status.code().unwrap_or(1)));
+            // remove all the duplicate login items
+            let count = self.get_login_item_paths()?.len();
+            self.delete_login_items(count)?;
+            if count > 0 {
+                change = Change::Changed;
             }
         }
-        Ok(())
+        Ok(change)
+    }
+
+    /// Remove the entries of both the Launch Agent and AppleScript, for uninstalling
+    ///
+    /// The login items named by either the `app_name` or the basename of the `app_path`
+    /// are removed. It is best-effort, the missing entries are skipped,
+    /// and all the entries are tried even if some of them fail.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to remove some of the entries, returns `Error::PurgeFailed`
+    pub fn purge_all(&self) -> Result<()> {
+        let entries = [true, false].map(|use_launch_agent| AutoLaunch {
+            use_launch_agent,
+            ..self.clone()
+        });
+        // the login item named by the basename of app_path
+        let corrected = AutoLaunch::new(
+            &self.app_name,
+            &self.app_path,
+            false,
+            &self.args,
+            &self.bundle_identifiers,
+            &self.agent_extra_config,
+        );
+        let corrected = (corrected.app_name != self.app_name).then_some(corrected);
+        collect_failures(
+            entries
+                .iter()
+                .chain(corrected.as_ref())
+                .map(|x| x.disable().map(drop)),
+        )
+    }
+
+    /// Check whether the AutoLaunch setting is enabled, and why not if it's not
+    ///
+    /// The Launch Agent is disabled with `<key>Disabled</key><true/>` in the plist,
+    /// or not loaded by launchd if `verify_loaded` is true, the same as `is_enabled`.
+    /// The login items can't be disabled without removal.
+    /// It is `EnabledStatus::NotRegistered` if `AUTO_LAUNCH_DISABLE` is set.
+    ///
+    /// ## Errors
+    ///
+    /// The same as `diff`, and failed to execute the `launchctl` command.
+    pub fn enabled_status(&self) -> Result<EnabledStatus> {
+        if is_disabled_by_env() {
+            return Ok(EnabledStatus::NotRegistered);
+        }
+        let mismatch = match self.diff()? {
+            StateDiff::Missing => return Ok(EnabledStatus::NotRegistered),
+            StateDiff::Differs { .. } => true,
+            StateDiff::Matches => false,
+        };
+        if self.use_launch_agent {
+            let file = [Some(self.get_file()?), self.get_legacy_file()?]
+                .into_iter()
+                .flatten()
+                .find(|x| x.exists());
+            if let Some(file) = file {
+                let content = read_plist(self.runner(), &file)?;
+                let key = content.find("<key>Disabled</key>");
+                if key.is_some_and(|i| content[i + 19..].trim_start().starts_with("<true/>")) {
+                    return Ok(EnabledStatus::RegisteredButDisabledByOs);
+                }
+            }
+            if self.verify_loaded && !self.is_agent_loaded()? {
+                return Ok(EnabledStatus::RegisteredButDisabledByOs);
+            }
+        }
+        match mismatch {
+            true => Ok(EnabledStatus::RegisteredButPathMismatch),
+            false => Ok(EnabledStatus::Enabled),
+        }
+    }
+
+    /// Get the last modified time of the Launch Agent plist, or `None` if it doesn't exist
+    ///
+    /// It is always `None` in case using AppleScript, as the login items carry no timestamp.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to read the metadata of the plist
+    pub fn entry_modified_time(&self) -> Result<Option<SystemTime>> {
+        if !self.use_launch_agent {
+            return Ok(None);
+        }
+        let file = [Some(self.get_file()?), self.get_legacy_file()?]
+            .into_iter()
+            .flatten()
+            .find(|x| x.exists());
+        match file {
+            Some(file) => Ok(Some(fs::metadata(file)?.modified()?)),
+            None => Ok(None),
+        }
     }
 
     /// Check whether the AutoLaunch setting is enabled
+    ///
+    /// For Launch Agent, it checks the plist exists, and also the agent is loaded
+    /// via `launchctl list` if `verify_loaded` is true.
     pub fn is_enabled(&self) -> Result<bool> {
+        if is_disabled_by_env() {
+            return Ok(false);
+        }
         if self.use_launch_agent {
-            Ok(self.get_file().exists())
+            let legacy = self.get_legacy_file()?.is_some_and(|x| x.exists());
+            let exists = self.get_file()?.exists() || legacy;
+            Ok(exists && (!self.verify_loaded || self.is_agent_loaded()?))
         } else {
             let command = "get the name of every login item";
-            let output = exec_apple_script(command)?;
+            let output = exec_apple_script(self.runner(), command)?;
             let mut enable = false;
             if output.status.success() {
                 let stdout = std::str::from_utf8(&output.stdout).unwrap_or("");
@@ -175,25 +424,978 @@ impl AutoLaunch {
         }
     }
 
-    /// get the plist file path
-    fn get_file(&self) -> PathBuf {
-        get_dir().join(format!("{}.plist", self.app_name))
+    /// Compare the configured entry against the one on the system
+    ///
+    /// For Launch Agent, the `ProgramArguments` are compared, and `actual` is them joined.
+    /// For AppleScript, the login item paths are compared, and `actual` is them joined.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to read the plist file
+    /// - failed to execute the `osascript` command, check the exit status or stderr for details
+    pub fn diff(&self) -> Result<StateDiff> {
+        let (actual, expected) = if self.use_launch_agent {
+            let file = [Some(self.get_file()?), self.get_legacy_file()?]
+                .into_iter()
+                .flatten()
+                .find(|x| x.exists());
+            let Some(file) = file else {
+                return Ok(StateDiff::Missing);
+            };
+            let content = read_plist(self.runner(), &file)?;
+            (
+                get_plist_program_arguments(&content),
+                self.get_program_arguments(false)?,
+            )
+        } else {
+            let paths = self.get_login_item_paths()?;
+            if paths.is_empty() {
+                return Ok(StateDiff::Missing);
+            }
+            (paths, vec![self.get_login_item_path()])
+        };
+
+        if actual == expected {
+            Ok(StateDiff::Matches)
+        } else {
+            Ok(StateDiff::Differs {
+                actual: actual.join(" "),
+            })
+        }
+    }
+
+    /// Export the entry on the system as a string, e.g. for the bug reports
+    ///
+    /// For Launch Agent, it is the path of the plist in a comment line, followed by
+    /// the plist text, converted to XML if it is binary.
+    /// For AppleScript, it is the login item name and path per line.
+    ///
+    /// ## Errors
+    ///
+    /// - the entry is not found
+    /// - failed to read the plist file
+    /// - failed to execute the `osascript` command, check the exit status or stderr for details
+    pub fn export_entry(&self) -> Result<String> {
+        let not_found = || {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("the entry `{}` is not found", self.app_name),
+            )
+        };
+        if self.use_launch_agent {
+            let file = [Some(self.get_file()?), self.get_legacy_file()?]
+                .into_iter()
+                .flatten()
+                .find(|x| x.exists())
+                .ok_or_else(not_found)?;
+            let content = read_plist(self.runner(), &file)?;
+            Ok(format!("<!-- {} -->\n{}", file.display(), content))
+        } else {
+            let paths = self.get_login_item_paths()?;
+            if paths.is_empty() {
+                return Err(not_found().into());
+            }
+            Ok(paths
+                .iter()
+                .map(|path| format!("login item \"{}\": {}\n", self.app_name, path))
+                .collect())
+        }
+    }
+
+    /// List all the Launch Agents in `~/Library/LaunchAgents` and the login items
+    ///
+    /// For Launch Agents, the `name` is the file stem of the plist and the `command`
+    /// is the joined `ProgramArguments` (or `Program`). For login items, the `name`
+    /// is the login item name and the `command` is its path.
+    ///
+    /// The plists which can't be read are skipped, and so are the login items
+    /// if the `osascript` command fails, e.g. not authorized to control System Events.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to read the dir `~/Library/LaunchAgents`
+    pub fn list_entries() -> Result<Vec<EntryInfo>> {
+        Self::list_entries_with_runner(&SystemCommandRunner)
+    }
+
+    /// The same as `list_entries`, but executes the external commands via `runner`,
+    /// see `AutoLaunchBuilder::set_command_runner`
+    pub fn list_entries_with_runner(runner: &dyn CommandRunner) -> Result<Vec<EntryInfo>> {
+        let mut entries = vec![];
+
+        let dir = get_dir()?;
+        if dir.exists() {
+            for file in fs::read_dir(dir)?.flatten() {
+                let path = file.path();
+                if path.extension().is_none_or(|ext| ext != "plist") {
+                    continue;
+                }
+                let name = match path.file_stem() {
+                    Some(stem) => stem.to_string_lossy().into_owned(),
+                    None => continue,
+                };
+                let content = match read_plist(runner, &path) {
+                    Ok(content) => content,
+                    Err(_e) => {
+                        #[cfg(feature = "log")]
+                        log::warn!("auto-launch: skipped `{}`: {}", path.display(), _e);
+                        continue;
+                    }
+                };
+                let command = get_plist_program_arguments(&content).join(" ");
+                entries.push(EntryInfo { name, command });
+            }
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+
+        match get_login_items(runner) {
+            Ok(items) => entries.extend(items),
+            Err(_e) => {
+                #[cfg(feature = "log")]
+                log::warn!("auto-launch: skipped the login items: {}", _e);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Count the entries of the backend in use for the app,
+    /// the Launch Agents named by the `label` or launching the app,
+    /// or the login items named by the `app_name`
+    ///
+    /// A count greater than 1 means duplicates, e.g. left by an older version with another name.
+    /// The plist files which can't be read or parsed are skipped.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to read the Launch Agent dir
+    /// - failed to execute the `osascript` command, check the exit status or stderr for details
+    pub fn entry_count(&self) -> Result<usize> {
+        if !self.use_launch_agent {
+            return Ok(self.get_login_item_paths()?.len());
+        }
+        let dir = get_dir()?;
+        if !dir.exists() {
+            return Ok(0);
+        }
+
+        let files = [Some(self.get_file()?), self.get_legacy_file()?];
+        let program = self.get_agent_program();
+        // also launched via `/bin/sh -c`, see `get_program_arguments`
+        let script = format!("exec {}", shell_quote(&program));
+        let launches_app = |args: &[String]| match args {
+            [shell, flag, command] if shell == "/bin/sh" && flag == "-c" => {
+                // the whole program, not a prefix of another path
+                command.match_indices(&script).any(|(i, _)| {
+                    let rest = &command[i + script.len()..];
+                    (i == 0 || command[..i].ends_with(' '))
+                        && (rest.is_empty() || rest.starts_with(' '))
+                })
+            }
+            [first, ..] => *first == program,
+            [] => false,
+        };
+
+        let mut count = 0;
+        for file in fs::read_dir(dir)?.flatten() {
+            let path = file.path();
+            if path.extension().is_none_or(|ext| ext != "plist") {
+                continue;
+            }
+            if files.contains(&Some(path.clone())) {
+                count += 1;
+                continue;
+            }
+            let content = match read_plist(self.runner(), &path) {
+                Ok(content) => content,
+                Err(_e) => {
+                    #[cfg(feature = "log")]
+                    log::warn!("auto-launch: skipped `{}`: {}", path.display(), _e);
+                    continue;
+                }
+            };
+            if launches_app(&get_plist_program_arguments(&content)) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// get whether the Launch Agent is used, or the AppleScript login item
+    pub fn get_use_launch_agent(&self) -> bool {
+        self.use_launch_agent
+    }
+
+    /// get the Launch Agent label, falls back to the `app_name` if not set
+    pub fn get_label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.app_name)
+    }
+
+    /// get the Launch Agent plist file name without `.plist`, falls back to the label if not set
+    pub fn get_plist_filename(&self) -> &str {
+        self.plist_filename.as_deref().unwrap_or(self.get_label())
+    }
+
+    /// get whether the Launch Agent runs at load
+    pub fn get_run_at_load(&self) -> bool {
+        self.run_at_load
+    }
+
+    /// get the `StartInterval` of the Launch Agent in seconds
+    pub fn get_start_interval(&self) -> Option<u32> {
+        self.start_interval
+    }
+
+    /// get the `StartCalendarInterval` of the Launch Agent
+    pub fn get_start_calendar_interval(&self) -> Option<&CalendarInterval> {
+        self.start_calendar_interval.as_ref()
+    }
+
+    /// get the `WatchPaths` of the Launch Agent
+    pub fn get_watch_paths(&self) -> &[PathBuf] {
+        &self.watch_paths
+    }
+
+    /// get the `LimitLoadToSessionType` of the Launch Agent
+    pub fn get_session_type(&self) -> Option<&str> {
+        self.session_type.as_deref()
+    }
+
+    /// get the agent extra config
+    pub fn get_agent_extra_config(&self) -> &str {
+        &self.agent_extra_config
+    }
+
+    /// get the bundle identifiers
+    pub fn get_bundle_identifiers(&self) -> &[String] {
+        &self.bundle_identifiers
+    }
+
+    /// Check whether the current process runs in the App Sandbox
+    ///
+    /// It is detected by `APP_SANDBOX_CONTAINER_ID`, or the home dir in the shape of
+    /// `~/Library/Containers/{bundle_id}/Data`. The `~/Library/LaunchAgents` of the sandboxed
+    /// app maps into the container, which launchd never reads, so the Launch Agent is unsupported.
+    /// Consider the `SMAppService` API instead.
+    pub fn is_sandboxed() -> bool {
+        if std::env::var_os("APP_SANDBOX_CONTAINER_ID").is_some_and(|x| !x.is_empty()) {
+            return true;
+        }
+        dirs::home_dir().is_some_and(|home| {
+            home.ends_with("Data")
+                && home
+                    .parent()
+                    .and_then(Path::parent)
+                    .is_some_and(|x| x.ends_with("Library/Containers"))
+        })
+    }
+
+    /// Resolve the first installed application among the `bundle_identifiers`
+    /// in order, via Spotlight (`mdfind`)
+    ///
+    /// Returns the matched bundle identifier and the application path,
+    /// or `None` if none of them is installed.
+    pub fn resolve_bundle_identifier(&self) -> Option<(String, String)> {
+        self.bundle_identifiers.iter().find_map(|id| {
+            let query = format!("kMDItemCFBundleIdentifier == '{}'", id);
+            let output = self.runner().run("mdfind", &[&query]).ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            let stdout = std::str::from_utf8(&output.stdout).unwrap_or("");
+            let path = stdout.lines().map(|x| x.trim()).find(|x| !x.is_empty())?;
+            Some((id.clone(), path.to_string()))
+        })
+    }
+
+    /// Get the app path used for the login item or Launch Agent,
+    /// falls back to the resolved bundle when `app_path` is empty or relative
+    ///
+    /// Otherwise, the relative `app_path` of the Launch Agent is resolved
+    /// against the dir of the current executable, see `resolve_relative_to_exe`.
+    fn get_effective_app_path(&self) -> String {
+        if Path::new(&self.app_path).is_relative() {
+            if let Some((_, path)) = self.resolve_bundle_identifier() {
+                return path;
+            }
+            if let Some(path) = self.resolve_relative_to_exe() {
+                return path;
+            }
+        }
+        self.canonical_app_path.clone()
+    }
+
+    /// Get the path of the login item, the `.app` bundle containing the executable
+    fn get_login_item_path(&self) -> String {
+        get_bundle_path(&self.get_effective_app_path()).into()
+    }
+
+    /// Get the program of the Launch Agent, the executable of the `.app` bundle
+    /// resolved from the `CFBundleExecutable` of its `Info.plist`
+    ///
+    /// It is the effective app path itself if it's not a bundle, or the `Info.plist` is unreadable.
+    fn get_agent_program(&self) -> String {
+        let app_path = self.get_effective_app_path();
+        let bundle = Path::new(&app_path);
+        if !app_path.trim_end_matches('/').ends_with(".app") || !bundle.is_dir() {
+            return app_path;
+        }
+        let executable = read_plist(self.runner(), &bundle.join("Contents/Info.plist"))
+            .ok()
+            .and_then(|content| get_plist_string(&content, "CFBundleExecutable"));
+        match executable {
+            Some(executable) => bundle
+                .join("Contents/MacOS")
+                .join(executable)
+                .to_string_lossy()
+                .into_owned(),
+            None => app_path,
+        }
+    }
+
+    /// Resolve the relative `app_path` against the dir of `std::env::current_exe`,
+    /// e.g. `helper` for the binary next to the running one
+    ///
+    /// Only used by the Launch Agent, and the `app_path` starting with `~` is not relative.
+    fn resolve_relative_to_exe(&self) -> Option<String> {
+        if !self.use_launch_agent || self.app_path.is_empty() || self.app_path.starts_with('~') {
+            return None;
+        }
+        let exe = std::env::current_exe().ok()?;
+        let path = exe.parent()?.join(&self.app_path);
+        Some(canonicalize_app_path(&path.to_string_lossy()))
+    }
+
+    /// Describe the changes that `enable`, `enable_once` or `disable` would make,
+    /// without touching the system
+    ///
+    /// ## Errors
+    ///
+    /// - the home dir is not found with `use_launch_agent`, returns `Error::HomeDirNotFound`
+    pub fn plan(&self, action: Action) -> Result<Vec<PlannedChange>> {
+        Ok(match (action, self.use_launch_agent) {
+            (_, true) => {
+                let mut changes = vec![];
+                if action != Action::Disable {
+                    changes.push(PlannedChange::WriteFile {
+                        path: self.get_file()?,
+                        content: self.get_plist(action == Action::EnableOnce)?,
+                    });
+                    if self.binary_plist {
+                        let file = self.get_file()?.to_string_lossy().into_owned();
+                        changes.push(PlannedChange::RunCommand {
+                            program: "plutil".into(),
+                            args: vec!["-convert".into(), "binary1".into(), file],
+                        });
+                    }
+                    if let Some(path) = self.get_legacy_file()?.filter(|x| x.exists()) {
+                        changes.push(PlannedChange::RemoveFile { path });
+                    }
+                    return Ok(changes);
+                }
+                let files = self
+                    .get_legacy_file()?
+                    .into_iter()
+                    .chain([self.get_file()?]);
+                for path in files.filter(|x| x.exists()) {
+                    if self.unload_on_disable {
+                        changes.push(PlannedChange::RunCommand {
+                            program: "launchctl".into(),
+                            args: get_unload_args(&path),
+                        });
+                    }
+                    changes.push(PlannedChange::RemoveFile { path });
+                }
+                changes
+            }
+            (Action::Enable, false) => vec![PlannedChange::RunAppleScript {
+                script: get_apple_script(&self.get_make_login_item_command()),
+            }],
+            (Action::Disable, false) => vec![PlannedChange::RunAppleScript {
+                script: get_apple_script(&self.get_delete_login_item_command()),
+            }],
+            // not supported by the login item
+            (Action::EnableOnce, false) => vec![],
+        })
+    }
+
+    /// Get the plist content as `read_plist` returns it after `enable`
+    ///
+    /// The binary plist is read back via `plutil` in its own XML layout,
+    /// so the generated plist is converted the same way to be compared.
+    fn get_expected_plist(&self) -> Result<String> {
+        let data = self.get_plist(false)?;
+        if !self.binary_plist {
+            return Ok(data);
+        }
+        let name = normalize_identifier(self.get_plist_filename());
+        let temp = std::env::temp_dir().join(format!("{}.{}.plist", name, std::process::id()));
+        fs::write(&temp, data)?;
+        let result = convert_plist_to_xml(self.runner(), &temp);
+        let _ = fs::remove_file(&temp);
+        result
+    }
+
+    /// Get the Launch Agent plist content
+    fn get_plist(&self, once: bool) -> Result<String> {
+        let mut extra = self.agent_extra_config.trim().to_string();
+        if !extra.is_empty() {
+            extra = format!("  {}\n", extra);
+        }
+
+        let content = format_plist(&self.get_plist_entries(once)?, &extra);
+        Ok(self.entry_customizer.apply(content))
+    }
+
+    /// Get the `<key>/<value>` entries we generate in the plist top-level `<dict>`
+    fn get_plist_entries(&self, once: bool) -> Result<String> {
+        let section = self
+            .get_program_arguments(once)?
+            .iter()
+            .map(|x| format!("<string>{}</string>", xml_escape(x)))
+            .collect::<String>();
+
+        let mut schedule = String::new();
+        if let Some(seconds) = self.start_interval {
+            let value = format!("<integer>{}</integer>", seconds);
+            schedule += &plist_entry("StartInterval", &value);
+        }
+        if let Some(interval) = &self.start_calendar_interval {
+            let fields = [
+                ("Minute", interval.minute),
+                ("Hour", interval.hour),
+                ("Day", interval.day),
+                ("Weekday", interval.weekday),
+                ("Month", interval.month),
+            ];
+            let dict = fields
+                .iter()
+                .filter_map(|(key, value)| Some((key, (*value)?)))
+                .map(|(key, value)| format!("<key>{}</key><integer>{}</integer>", key, value))
+                .collect::<String>();
+            schedule += &plist_entry("StartCalendarInterval", &format!("<dict>{}</dict>", dict));
+        }
+        if !self.watch_paths.is_empty() {
+            let paths = self
+                .watch_paths
+                .iter()
+                .map(|x| format!("<string>{}</string>", xml_escape(&x.to_string_lossy())))
+                .collect::<String>();
+            schedule += &plist_entry("WatchPaths", &format!("<array>{}</array>", paths));
+        }
+        if let Some(session_type) = &self.session_type {
+            let value = format!("<string>{}</string>", xml_escape(session_type));
+            schedule += &plist_entry("LimitLoadToSessionType", &value);
+        }
+
+        Ok([
+            plist_entry(
+                "Label",
+                &format!("<string>{}</string>", xml_escape(self.get_label())),
+            ),
+            plist_entry("ProgramArguments", &format!("<array>{}</array>", section)),
+            plist_entry(
+                "RunAtLoad",
+                if self.run_at_load {
+                    "<true/>"
+                } else {
+                    "<false/>"
+                },
+            ),
+            schedule,
+        ]
+        .concat())
+    }
+
+    /// Get the `ProgramArguments` of the Launch Agent
+    fn get_program_arguments(&self, once: bool) -> Result<Vec<String>> {
+        let mut args = vec![self.get_agent_program()];
+        // the plist doesn't expand the environment variables
+        args.extend(self.get_resolved_args());
+        if !once && self.raw_args.is_none() {
+            return Ok(args);
+        }
+
+        let mut command = args.iter().map(|x| shell_quote(x)).collect::<Vec<_>>();
+        // the raw args are passed via the shell verbatim
+        command.extend(self.raw_args.clone());
+        let mut script = format!("exec {}", command.join(" "));
+        if once {
+            // remove the plist before launching, so it won't load at the next login
+            let file = self.get_file()?.to_string_lossy().into_owned();
+            script = format!("rm -f {}; {}", shell_quote(&file), script);
+        }
+        Ok(vec!["/bin/sh".into(), "-c".into(), script])
+    }
+
+    /// Check the existing plist at the same path has our `Label`, unless `force_overwrite`,
+    /// so that an unrelated agent is not overwritten
+    fn check_label_conflict(&self) -> Result<()> {
+        let file = self.get_file()?;
+        if self.force_overwrite || !file.exists() {
+            return Ok(());
+        }
+        let content = read_plist(self.runner(), &file)?;
+        match get_plist_string(&content, "Label") {
+            Some(label) if label == self.get_label() => Ok(()),
+            _ => Err(Error::Conflict(file)),
+        }
+    }
+
+    /// Check the `start_interval` and `start_calendar_interval` are not set together,
+    /// and the calendar fields are in range
+    fn validate_schedule(&self) -> Result<()> {
+        let Some(interval) = &self.start_calendar_interval else {
+            return Ok(());
+        };
+        if self.start_interval.is_some() {
+            return Err(Error::InvalidInput(
+                "start_interval and start_calendar_interval are mutually exclusive".into(),
+            ));
+        }
+
+        let fields = [
+            ("minute", interval.minute, 0..=59),
+            ("hour", interval.hour, 0..=23),
+            ("day", interval.day, 1..=31),
+            ("weekday", interval.weekday, 0..=7),
+            ("month", interval.month, 1..=12),
+        ];
+        for (name, value, range) in fields {
+            if let Some(value) = value.filter(|x| !range.contains(x)) {
+                return Err(Error::InvalidInput(format!(
+                    "start_calendar_interval {} is out of range {:?}: {}",
+                    name, range, value
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the `session_type` is one of the session types known by launchd
+    fn validate_session_type(&self) -> Result<()> {
+        const SESSION_TYPES: [&str; 5] =
+            ["Aqua", "Background", "LoginWindow", "StandardIO", "System"];
+        match &self.session_type {
+            Some(session_type) if !SESSION_TYPES.contains(&session_type.as_str()) => {
+                Err(Error::InvalidInput(format!(
+                    "session_type should be one of {:?}: {}",
+                    SESSION_TYPES, session_type
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the `agent_extra_config` is well-formed and
+    /// does not duplicate the keys we generate
+    fn validate_agent_extra_config(&self) -> Result<()> {
+        let invalid = |msg: String| Error::InvalidInput(format!("agent_extra_config {}", msg));
+
+        let keys = parse_plist_keys(&self.agent_extra_config).map_err(invalid)?;
+        let mut existing = parse_plist_keys(&self.get_plist_entries(false)?).map_err(invalid)?;
+        for key in keys {
+            if existing.contains(&key) {
+                return Err(invalid(format!("duplicates the key: {}", key)));
+            }
+            existing.push(key);
+        }
+        Ok(())
+    }
+
+    /// Get the AppleScript command to make the login item
+    fn get_make_login_item_command(&self) -> String {
+        let app_path = self.get_login_item_path();
+        let hidden = self
+            .args
+            .iter()
+            .any(|arg| arg == "--hidden" || arg == "--minimized");
+
+        let props = format!(
+            "{{name:\"{}\",path:\"{}\",hidden:{}}}",
+            self.app_name, app_path, hidden
+        );
+        format!("make login item at end with properties {}", props)
+    }
+
+    /// Get the AppleScript command to delete the login item
+    fn get_delete_login_item_command(&self) -> String {
+        format!("delete login item \"{}\"", self.app_name)
+    }
+
+    /// Get the paths of the login items with the same name
+    fn get_login_item_paths(&self) -> Result<Vec<String>> {
+        exec_apple_script_list(
+            self.runner(),
+            &format!(
+                "get the path of every login item whose name is \"{}\"",
+                self.app_name
+            ),
+        )
+    }
+
+    /// Delete the login items with the same name `count` times
+    fn delete_login_items(&self, count: usize) -> Result<()> {
+        let command = self.get_delete_login_item_command();
+        for _ in 0..count {
+            let output = exec_apple_script(self.runner(), &command)?;
+            if !output.status.success() {
+                return Err(Error::AppleScriptFailed(output.status.code().unwrap_or(1)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether the Launch Agent of the `label` is loaded, via `launchctl list`
+    fn is_agent_loaded(&self) -> Result<bool> {
+        let output = self.run_launchctl(&["list".into(), self.get_label().into()])?;
+        Ok(output.status.success())
+    }
+
+    /// Execute the `launchctl` command with `args`
+    fn run_launchctl(&self, args: &[String]) -> Result<std::process::Output> {
+        let args: Vec<&str> = args.iter().map(|x| x.as_str()).collect();
+        Ok(self.runner().run("launchctl", &args)?)
+    }
+
+    /// Get the runner to execute the external commands
+    fn runner(&self) -> &dyn CommandRunner {
+        match &self.command_runner.0 {
+            Some(runner) => runner.as_ref(),
+            None => &SystemCommandRunner,
+        }
+    }
+
+    /// get the plist file path, named by the normalized `plist_filename` or label
+    fn get_file(&self) -> Result<PathBuf> {
+        let identifier = normalize_identifier(self.get_plist_filename());
+        Ok(get_dir()?.join(format!("{}.plist", identifier)))
+    }
+
+    /// Get the file path named by the raw `app_name` used by the former versions,
+    /// if it differs from `get_file`, exists, and has our `Label` or `ProgramArguments`
+    ///
+    /// Another app's plist of the same name is never migrated, removed or counted.
+    fn get_legacy_file(&self) -> Result<Option<PathBuf>> {
+        let file = get_dir()?.join(format!("{}.plist", self.app_name));
+        if self.app_name.contains('/') || file == self.get_file()? || !file.exists() {
+            return Ok(None);
+        }
+        let Ok(content) = read_plist(self.runner(), &file) else {
+            return Ok(None);
+        };
+        let owned = get_plist_string(&content, "Label").as_deref() == Some(self.get_label())
+            || get_plist_program_arguments(&content) == self.get_program_arguments(false)?;
+        Ok(owned.then_some(file))
     }
 }
 
 /// Get the Launch Agent Dir
-fn get_dir() -> PathBuf {
-    dirs::home_dir()
-        .unwrap()
+fn get_dir() -> Result<PathBuf> {
+    Ok(get_canonical_home_dir()?
         .join("Library")
-        .join("LaunchAgents")
+        .join("LaunchAgents"))
+}
+
+/// Get the `.app` bundle containing the executable, e.g. `/Applications/Foo.app`
+/// for `/Applications/Foo.app/Contents/MacOS/Foo`, or the path itself
+fn get_bundle_path(app_path: &str) -> &str {
+    match app_path.find(".app/Contents/MacOS/") {
+        Some(i) => &app_path[..i + 4],
+        None => app_path,
+    }
+}
+
+/// Get the login item name of the app path, i.e. the basename without `.app`
+fn get_login_item_name(app_path: &str) -> &str {
+    let app_path = app_path.trim_end_matches('/');
+    let name = match app_path.rfind('/') {
+        Some(i) => &app_path[i + 1..],
+        None => app_path,
+    };
+    name.strip_suffix(".app").unwrap_or(name)
+}
+
+/// Get the full AppleScript to tell System Events
+fn get_apple_script(cmd_suffix: &str) -> String {
+    format!("tell application \"System Events\" to {}", cmd_suffix)
 }
 
 /// Execute the specific AppleScript
-fn exec_apple_script(cmd_suffix: &str) -> Result<Output> {
-    let command = format!("tell application \"System Events\" to {}", cmd_suffix);
-    let output = Command::new("osascript")
-        .args(vec!["-e", &command])
-        .output()?;
+///
+/// Returns `Error::Backend` if `osascript` is not installed, e.g. on the stripped-down images,
+/// or `Error::PermissionDenied` if the app is not authorized to control System Events.
+fn exec_apple_script(runner: &dyn CommandRunner, cmd_suffix: &str) -> Result<Output> {
+    run_osascript(runner, &get_apple_script(cmd_suffix))
+}
+
+/// Execute the full AppleScript via `osascript`, see `exec_apple_script` for the errors
+fn run_osascript(runner: &dyn CommandRunner, script: &str) -> Result<Output> {
+    let output = match runner.run("osascript", &["-e", script]) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(Error::Backend(
+                "osascript unavailable, consider using the Launch Agent instead \
+                via `set_use_launch_agent(true)`"
+                    .into(),
+            ))
+        }
+        output => output?,
+    };
+    // errAEEventNotPermitted, the Automation permission of TCC is not granted
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() && stderr.contains("(-1743)") {
+        return Err(Error::PermissionDenied(format!(
+            "not authorized to control System Events, grant the Automation access in \
+            System Settings > Privacy & Security > Automation, \
+            or use the Launch Agent instead: {}",
+            stderr.trim()
+        )));
+    }
     Ok(output)
 }
+
+/// Check the generated plist is well-formed before written, with the `Label` and the program
+/// to launch, so launchd won't reject it silently at login, e.g. for an escaping bug
+/// or the bad content from the `entry_customizer`
+fn validate_plist(content: &str) -> Result<()> {
+    let invalid = |msg: String| Error::InvalidInput(format!("the generated plist {}", msg));
+    let begin = content
+        .find("<dict>")
+        .ok_or_else(|| invalid("has no <dict>".into()))?;
+    let end = content.rfind("</dict>").filter(|end| *end > begin);
+    let end = end.ok_or_else(|| invalid("has no </dict>".into()))?;
+    if !content[end..]
+        .trim_start_matches("</dict>")
+        .trim()
+        .starts_with("</plist>")
+    {
+        return Err(invalid("has no </plist> after the top-level dict".into()));
+    }
+
+    let keys = parse_plist_keys(&content[begin + 6..end]).map_err(invalid)?;
+    if !keys.iter().any(|x| x == "Label") {
+        return Err(invalid("has no key: Label".into()));
+    }
+    if !keys
+        .iter()
+        .any(|x| x == "ProgramArguments" || x == "Program")
+    {
+        return Err(invalid("has no key: ProgramArguments".into()));
+    }
+    Ok(())
+}
+
+/// Get the args of `launchctl` to load the Launch Agent of the plist
+fn get_load_args(file: &Path) -> Vec<String> {
+    vec!["load".into(), file.to_string_lossy().into_owned()]
+}
+
+/// Get the args of `launchctl` to unload the Launch Agent of the plist
+fn get_unload_args(file: &Path) -> Vec<String> {
+    vec!["unload".into(), file.to_string_lossy().into_owned()]
+}
+
+/// Execute the specific AppleScript which returns a list
+fn exec_apple_script_list(runner: &dyn CommandRunner, cmd_suffix: &str) -> Result<Vec<String>> {
+    let output = exec_apple_script(runner, cmd_suffix)?;
+    if !output.status.success() {
+        return Err(Error::AppleScriptFailed(output.status.code().unwrap_or(1)));
+    }
+    let stdout = std::str::from_utf8(&output.stdout).unwrap_or("");
+    Ok(stdout
+        .split(",")
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect())
+}
+
+/// Get the name and path of every login item
+///
+/// They are queried together, one item per line separated by a tab,
+/// since the names and paths may contain the `,` of the AppleScript list.
+fn get_login_items(runner: &dyn CommandRunner) -> Result<Vec<EntryInfo>> {
+    let script = r#"set itemLines to ""
+tell application "System Events"
+    repeat with x in every login item
+        set itemLines to itemLines & (name of x as text) & tab & (path of x as text) & linefeed
+    end repeat
+end tell
+itemLines"#;
+    let output = run_osascript(runner, script)?;
+    if !output.status.success() {
+        return Err(Error::AppleScriptFailed(output.status.code().unwrap_or(1)));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, command)| EntryInfo {
+            name: name.into(),
+            command: command.into(),
+        })
+        .collect())
+}
+
+/// Read the plist file as XML, the binary plist is converted via `plutil`
+fn read_plist(runner: &dyn CommandRunner, file: &Path) -> Result<String> {
+    let data = fs::read(file)?;
+    if !data.starts_with(b"bplist") {
+        return Ok(String::from_utf8_lossy(&data).into_owned());
+    }
+    convert_plist_to_xml(runner, file)
+}
+
+/// Convert the plist file to XML via `plutil`, without changing the file
+fn convert_plist_to_xml(runner: &dyn CommandRunner, file: &Path) -> Result<String> {
+    let file = file.to_string_lossy();
+    let output = runner.run("plutil", &["-convert", "xml1", "-o", "-", &file])?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "failed to convert the plist to XML: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Get the `<string>` value of the top-level key in the plist content
+fn get_plist_string(content: &str, key: &str) -> Option<String> {
+    let rest = &content[content.find(&format!("<key>{}</key>", key))?..];
+    let rest = rest[rest.find("</key>")? + 6..].trim_start();
+    let rest = rest.strip_prefix("<string>")?;
+    Some(xml_unescape(&rest[..rest.find("</string>")?]))
+}
+
+/// Get the `ProgramArguments` (or `Program`) of the plist content
+fn get_plist_program_arguments(content: &str) -> Vec<String> {
+    let value = |key: &str| {
+        let rest = &content[content.find(&format!("<key>{}</key>", key))?..];
+        let rest = &rest[rest.find("</key>")? + 6..];
+        let rest = rest.trim_start();
+        let (end_tag, begin) = match rest.strip_prefix("<array>") {
+            Some(_) => ("</array>", 7),
+            None => ("</string>", 0),
+        };
+        Some(&rest[begin..rest.find(end_tag)?])
+    };
+
+    let strings = |xml: &str| {
+        xml.split("<string>")
+            .skip(1)
+            .filter_map(|x| x.split("</string>").next())
+            .map(xml_unescape)
+            .collect::<Vec<_>>()
+    };
+
+    match value("ProgramArguments") {
+        Some(array) => strings(array),
+        None => value("Program").map(strings).unwrap_or_default(),
+    }
+}
+
+/// Unescape the text of the plist `<string>`
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parse a sequence of plist `<key>/<value>` pairs and return the top-level keys
+///
+/// This is a minimal well-formedness check rather than a full XML parser,
+/// it makes sure the tags are balanced and each `<key>` is followed by a value.
+fn parse_plist_keys(xml: &str) -> std::result::Result<Vec<String>, String> {
+    let mut keys = vec![];
+    let mut stack: Vec<&str> = vec![];
+    let mut expect_key = true;
+    let mut text_start = 0;
+    let mut rest = xml;
+
+    while let Some(begin) = rest.find('<') {
+        let text = &rest[..begin];
+        if stack.is_empty() && !text.trim().is_empty() {
+            return Err(format!("has unexpected text: {}", text.trim()));
+        }
+        check_entities(text)?;
+
+        let offset = xml.len() - rest.len() + begin;
+        rest = &rest[begin..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").ok_or("has an unclosed comment")?;
+            rest = &comment[end + 3..];
+            continue;
+        }
+        let end = rest.find('>').ok_or("has an unclosed tag")?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            match stack.pop() {
+                Some(open) if open == name => {}
+                _ => return Err(format!("has an unexpected closing tag: </{}>", name)),
+            }
+            if stack.is_empty() {
+                if name == "key" {
+                    keys.push(xml[text_start..offset].trim().to_string());
+                }
+                expect_key = name != "key";
+            }
+        } else {
+            let self_closing = tag.ends_with('/');
+            let name = tag.trim_end_matches('/').split_whitespace().next();
+            let name = name.ok_or("has an empty tag")?;
+            if stack.is_empty() && expect_key != (name == "key") {
+                return Err(format!("has an unexpected tag: <{}>", name));
+            }
+            if self_closing {
+                if stack.is_empty() {
+                    expect_key = true;
+                }
+            } else {
+                stack.push(name);
+                text_start = xml.len() - rest.len();
+            }
+        }
+    }
+
+    if !rest.trim().is_empty() {
+        return Err(format!("has unexpected text: {}", rest.trim()));
+    }
+    if let Some(open) = stack.pop() {
+        return Err(format!("has an unclosed tag: <{}>", open));
+    }
+    if !expect_key {
+        return Err("has a key without value".into());
+    }
+    Ok(keys)
+}
+
+/// Check the `&` in the text are valid entity references
+fn check_entities(text: &str) -> std::result::Result<(), String> {
+    for (i, _) in text.match_indices('&') {
+        let entity = &text[i + 1..];
+        let end = entity.find(';').unwrap_or(entity.len());
+        let name = &entity[..end];
+        let valid = matches!(name, "amp" | "lt" | "gt" | "quot" | "apos")
+            || name.strip_prefix('#').is_some_and(|x| {
+                x.strip_prefix('x').map_or(x.parse::<u32>().is_ok(), |x| {
+                    u32::from_str_radix(x, 16).is_ok()
+                })
+            });
+        if end == entity.len() || !valid {
+            return Err(format!("has an invalid entity: &{}", name));
+        }
+    }
+    Ok(())
+}
+
+/// Return `Error::SandboxUnsupported` in the App Sandbox
+fn check_sandbox() -> Result<()> {
+    match AutoLaunch::is_sandboxed() {
+        true => Err(Error::SandboxUnsupported),
+        false => Ok(()),
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{shell_quote, AutoLaunchBuilder, Error, Platform, Result};
+
+/// The XML declaration and DOCTYPE of the plist
+pub(crate) const PLIST_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">"#;
+
+/// Render the autostart entry for the target platform, regardless of the host
+///
+/// It never touches the system, e.g. to generate the entry at the build time
+/// on a CI runner of another OS:
+///
+/// - Linux: the XDG autostart desktop entry
+/// - macOS: the Launch Agent plist
+/// - Windows: the command line of the `Run` registry value
+///
+/// Only the base entry is rendered, from the name, path, args, raw args, startup sentinel,
+/// the `description` and `display_name` on Linux, and the `label` and `run_at_load` on macOS.
+/// The `app_path` and args are written as is, since the path resolution
+/// and `${NAME}` placeholders depend on the target.
+///
+/// ## Errors
+///
+/// - `app_name` or `app_path` is not set
+/// - `args` and `raw_args` are set together, returns `Error::InvalidInput`
+///
+/// ## Usage
+///
+/// ```rust
+/// use auto_launch::{render_entry, AutoLaunchBuilder, Platform};
+///
+/// let entry = render_entry(
+///     Platform::Linux,
+///     AutoLaunchBuilder::new()
+///         .set_app_name("the-app")
+///         .set_app_path("/usr/bin/the-app")
+///         .set_args(&["--minimized"]),
+/// )
+/// .unwrap();
+/// assert!(entry.contains("Exec=/usr/bin/the-app --minimized"));
+/// ```
+pub fn render_entry(target: Platform, builder: &AutoLaunchBuilder) -> Result<String> {
+    let app_name = builder
+        .app_name
+        .as_ref()
+        .ok_or(Error::AppNameNotSpecified)?;
+    let app_path = builder
+        .app_path
+        .as_ref()
+        .ok_or(Error::AppPathNotSpecified)?;
+    if builder.args.is_some() && builder.raw_args.is_some() {
+        return Err(Error::InvalidInput(
+            "args and raw_args can't be set together".into(),
+        ));
+    }
+    let (args, raw_args) = builder.args_with_sentinel();
+
+    Ok(match target {
+        Platform::Linux => {
+            let mut command = vec![shell_quote(app_path)];
+            command.extend(args.iter().map(|x| shell_quote(x)));
+            command.extend(raw_args);
+            let description = match &builder.description {
+                Some(description) => description.clone(),
+                None => format!("{} startup script", app_name),
+            };
+            let name = builder.display_name.as_ref().unwrap_or(app_name);
+            format_desktop_entry(name, &description, &escape_exec(&command.join(" ")), "")
+        }
+        Platform::MacOS => {
+            let mut program_arguments = vec![app_path.clone()];
+            program_arguments.extend(args);
+            if let Some(raw_args) = raw_args {
+                let mut command = program_arguments
+                    .iter()
+                    .map(|x| shell_quote(x))
+                    .collect::<Vec<_>>();
+                command.push(raw_args);
+                let script = format!("exec {}", command.join(" "));
+                program_arguments = vec!["/bin/sh".into(), "-c".into(), script];
+            }
+            let section = program_arguments
+                .iter()
+                .map(|x| format!("<string>{}</string>", xml_escape(x)))
+                .collect::<String>();
+            let label = builder.label.as_ref().unwrap_or(app_name);
+            let entries = [
+                plist_entry("Label", &format!("<string>{}</string>", xml_escape(label))),
+                plist_entry("ProgramArguments", &format!("<array>{}</array>", section)),
+                plist_entry(
+                    "RunAtLoad",
+                    if builder.run_at_load.unwrap_or(true) {
+                        "<true/>"
+                    } else {
+                        "<false/>"
+                    },
+                ),
+            ]
+            .concat();
+            format_plist(&entries, "")
+        }
+        Platform::Windows => {
+            let mut command = vec![quote_program(app_path)];
+            command.extend(args.iter().map(|x| quote_arg(x)));
+            command.extend(raw_args);
+            command.join(" ")
+        }
+    })
+}
+
+/// Format the desktop entry with the `extra` keys, each ended with a newline
+pub(crate) fn format_desktop_entry(name: &str, comment: &str, exec: &str, extra: &str) -> String {
+    format!(
+        "[Desktop Entry]\n\
+        Type=Application\n\
+        Version=1.0\n\
+        Name={}\n\
+        Comment={}\n\
+        Exec={}\n\
+        StartupNotify=false\n\
+        {}\
+        Terminal=false",
+        name, comment, exec, extra
+    )
+}
+
+/// Escape the backslashes and `%` of the command for the `Exec` string value
+pub(crate) fn escape_exec(command: &str) -> String {
+    command.replace('\\', r"\\").replace('%', "%%")
+}
+
+/// Format the plist with the `<key>/<value>` entries and the `extra` config in the top-level `<dict>`
+pub(crate) fn format_plist(entries: &str, extra: &str) -> String {
+    format!(
+        "{}\n\
+        <plist version=\"1.0\">\n  \
+        <dict>\n\
+        {}{}  \
+        </dict>\n\
+        </plist>",
+        PLIST_HEADER, entries, extra
+    )
+}
+
+/// Escape the text of the plist `<string>`
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Format a `<key>/<value>` entry of the plist `<dict>`
+pub(crate) fn plist_entry(key: &str, value: &str) -> String {
+    format!("  <key>{}</key>\n  {}\n", key, value)
+}
+
+/// Quote the program path, which is taken up to the next `"` without escapes
+pub(crate) fn quote_program(path: &str) -> String {
+    if path.contains([' ', '\t']) {
+        format!("\"{}\"", path)
+    } else {
+        path.into()
+    }
+}
+
+/// Quote the arg with the rules of `CommandLineToArgvW`, if necessary
+///
+/// The backslashes are literal unless followed by a `"`,
+/// so they are doubled before the escaped `"` and the closing `"`.
+pub(crate) fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\u{b}', '"']) {
+        return arg.into();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        let escapes = if c == '"' {
+            backslashes * 2 + 1
+        } else {
+            backslashes
+        };
+        quoted.push_str(&"\\".repeat(escapes));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
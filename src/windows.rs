@@ -1,83 +1,255 @@
-use crate::{AutoLaunch, Result};
-use windows_registry::{Key, CURRENT_USER, LOCAL_MACHINE};
+use crate::{
+    canonicalize_app_path, collect_failures, expand_placeholders, is_disabled_by_env,
+    render::{quote_arg, quote_program},
+    Action, AutoLaunch, Change, EnabledStatus, EntryInfo, Error, PlannedChange, RegistryView,
+    Result, StateDiff, WindowsEnableMode,
+};
+use std::time::{Duration, SystemTime};
+use windows_registry::{Key, CURRENT_USER, LOCAL_MACHINE, USERS};
 use windows_result::HRESULT;
 
-const ADMIN_AL_REGKEY: &str = "SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Run";
 const AL_REGKEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run";
+const RUN_ONCE_REGKEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\RunOnce";
 const ADMIN_TASK_MANAGER_OVERRIDE_REGKEY: &str =
     "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Explorer\\StartupApproved\\Run32";
 const TASK_MANAGER_OVERRIDE_REGKEY: &str =
     "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Explorer\\StartupApproved\\Run";
+
+/// The `Run`, `RunOnce` and Task Manager override keys under a registry hive
+#[derive(Clone, Copy)]
+struct RegKeys {
+    run: &'static str,
+    run_once: &'static str,
+    task_manager_override: &'static str,
+}
+
+const CURRENT_USER_REGKEYS: RegKeys = RegKeys {
+    run: AL_REGKEY,
+    run_once: RUN_ONCE_REGKEY,
+    task_manager_override: TASK_MANAGER_OVERRIDE_REGKEY,
+};
+
+/// A registry hive, with the view to open the keys under it
+#[derive(Clone, Copy)]
+struct Hive {
+    key: &'static Key,
+    name: &'static str,
+    /// `KEY_WOW64_32KEY` or `KEY_WOW64_64KEY`, or 0 for the view of the process
+    view: u32,
+}
+
+const CURRENT_USER_HIVE: Hive = Hive {
+    key: CURRENT_USER,
+    name: "HKEY_CURRENT_USER",
+    view: 0,
+};
+const USERS_HIVE: Hive = Hive {
+    key: USERS,
+    name: "HKEY_USERS",
+    view: 0,
+};
 const TASK_MANAGER_OVERRIDE_ENABLED_VALUE: [u8; 12] = [
     0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
+/// The placeholder in args replaced by the dir of `app_path`, not an environment variable
+const APP_DIR_PLACEHOLDER: &str = "APP_DIR";
+const KEY_SET_VALUE: u32 = 0x0002;
+const KEY_CREATE_SUB_KEY: u32 = 0x0004;
+const KEY_READ: u32 = 0x20019;
+const KEY_WRITE: u32 = 0x20006;
+const KEY_WOW64_64KEY: u32 = 0x0100;
+const KEY_WOW64_32KEY: u32 = 0x0200;
 const E_ACCESSDENIED: HRESULT = HRESULT::from_win32(0x80070005_u32);
 const E_FILENOTFOUND: HRESULT = HRESULT::from_win32(0x80070002_u32);
+const E_PRIVILEGENOTHELD: HRESULT = HRESULT::from_win32(0x80070522_u32);
+const E_CANTWRITE: HRESULT = HRESULT::from_win32(0x800703F5_u32);
+const E_SHARINGVIOLATION: HRESULT = HRESULT::from_win32(0x80070020_u32);
+const E_LOCKVIOLATION: HRESULT = HRESULT::from_win32(0x80070021_u32);
 
 /// Windows implement
 impl AutoLaunch {
     /// Create a new AutoLaunch instance
     /// - `app_name`: application name
     /// - `app_path`: application path
+    /// - `enable_mode`: the registry scope to write the entry, see `WindowsEnableMode`
     /// - `args`: startup args passed to the binary
     ///
     /// ## Notes
     ///
     /// The parameters of `AutoLaunch::new` are different on each platform.
-    pub fn new(app_name: &str, app_path: &str, args: &[impl AsRef<str>]) -> AutoLaunch {
+    ///
+    /// The `app_name` is the registry value name, written via the wide (UTF-16) APIs,
+    /// so the non-ASCII names like `测试应用` round-trip as is.
+    pub fn new(
+        app_name: &str,
+        app_path: &str,
+        enable_mode: WindowsEnableMode,
+        args: &[impl AsRef<str>],
+    ) -> AutoLaunch {
         AutoLaunch {
             app_name: app_name.into(),
             app_path: app_path.into(),
+            canonical_app_path: canonicalize_app_path(app_path),
             args: args.iter().map(|s| s.as_ref().to_string()).collect(),
+            raw_args: None,
+            enable_mode,
+            manage_task_manager_override: true,
+            registry_retry_count: 3,
+            value_namespace: None,
+            registry_view: RegistryView::Default,
         }
     }
 
     /// Enable the AutoLaunch setting
     ///
+    /// Returns `Change::Unchanged` if the same `Run` value is already written
+    /// and not disabled in Task Manager, or `AUTO_LAUNCH_DISABLE` is set.
+    ///
     /// ## Errors
     ///
     /// - failed to open the registry key
     /// - failed to set value
-    pub fn enable(&self) -> Result<()> {
-        self.enable_as_admin()
-            .or_else(|e| {
-                if e.code() == E_ACCESSDENIED {
-                    self.enable_as_current_user()
-                } else {
-                    Err(e)
+    pub fn enable(&self) -> Result<Change> {
+        if is_disabled_by_env() {
+            return Ok(Change::Unchanged);
+        }
+        if self.diff()? == StateDiff::Matches && self.is_enabled()? {
+            return Ok(Change::Unchanged);
+        }
+        self.enable_with_scope()?;
+        Ok(Change::Changed)
+    }
+
+    /// Enable the AutoLaunch setting, and return the scope that took effect,
+    /// either `WindowsEnableMode::System` or `WindowsEnableMode::CurrentUser`
+    ///
+    /// In `Dynamic` mode, it falls back to `HKEY_CURRENT_USER` when
+    /// `HKEY_LOCAL_MACHINE` is not writable by the current process.
+    /// It returns the configured `enable_mode` without writing when `AUTO_LAUNCH_DISABLE` is set.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to open the registry key
+    /// - failed to set value
+    pub fn enable_with_scope(&self) -> Result<WindowsEnableMode> {
+        if is_disabled_by_env() {
+            return Ok(self.enable_mode);
+        }
+        let scope = match self.enable_mode {
+            WindowsEnableMode::Dynamic => match self.enable_as_admin() {
+                Err(e) if is_permission_error(e.code()) => self
+                    .enable_as_current_user()
+                    .map(|_| WindowsEnableMode::CurrentUser),
+                res => res.map(|_| WindowsEnableMode::System),
+            },
+            WindowsEnableMode::CurrentUser => self
+                .enable_as_current_user()
+                .map(|_| WindowsEnableMode::CurrentUser),
+            WindowsEnableMode::System => self.enable_as_admin().map(|_| WindowsEnableMode::System),
+        }
+        .map_err(std::io::Error::from)?;
+        Ok(scope)
+    }
+
+    /// Enable the AutoLaunch setting, then poll the `Run` value until the `timeout`
+    /// to confirm it persists
+    ///
+    /// Some security tools or "startup optimizers" remove the value shortly after written,
+    /// which turns into a detectable error instead of a silent failure.
+    ///
+    /// ## Errors
+    ///
+    /// - the same as `enable`
+    /// - the `Run` value is removed within the `timeout`, returns `Error::EntryRemoved`
+    pub fn enable_and_confirm(&self, timeout: Duration) -> Result<()> {
+        if is_disabled_by_env() {
+            return Ok(());
+        }
+        self.enable()?;
+        let interval = timeout.min(Duration::from_millis(100));
+        let start = std::time::Instant::now();
+        loop {
+            std::thread::sleep(interval);
+            if !self.is_registered()? {
+                return Err(Error::EntryRemoved(self.get_value_name()));
+            }
+            if start.elapsed() >= timeout {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Enable the AutoLaunch setting for the next login only, via the `RunOnce` registry key
+    ///
+    /// Windows deletes the `RunOnce` value before launching the app.
+    /// The registry hive is chosen the same way as `enable`,
+    /// and the value is not removed by `disable`.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to open the registry key
+    /// - failed to set value
+    pub fn enable_once(&self) -> Result<()> {
+        if is_disabled_by_env() {
+            return Ok(());
+        }
+        let set_run_once = |hk: Hive, path: &str| self.set_run_value(&hk.create(path)?);
+        let admin_run_once = self.get_admin_regkeys().run_once;
+        match self.enable_mode {
+            WindowsEnableMode::Dynamic => match set_run_once(self.admin_hive(), admin_run_once) {
+                Err(e) if is_permission_error(e.code()) => {
+                    set_run_once(CURRENT_USER_HIVE, RUN_ONCE_REGKEY)
                 }
-            })
-            .map_err(std::io::Error::from)?;
+                res => res,
+            },
+            WindowsEnableMode::CurrentUser => set_run_once(CURRENT_USER_HIVE, RUN_ONCE_REGKEY),
+            WindowsEnableMode::System => set_run_once(self.admin_hive(), admin_run_once),
+        }
+        .map_err(std::io::Error::from)?;
         Ok(())
     }
 
     fn enable_as_admin(&self) -> windows_registry::Result<()> {
-        LOCAL_MACHINE.create(ADMIN_AL_REGKEY)?.set_string(
-            &self.app_name,
-            &format!("{} {}", &self.app_path, &self.args.join(" ")),
-        )?;
-        // this key maybe not found
-        if let Ok(key) = LOCAL_MACHINE.create(ADMIN_TASK_MANAGER_OVERRIDE_REGKEY) {
+        let keys = self.get_admin_regkeys();
+        let hk = self.admin_hive();
+        self.retry_transient(|| self.set_run_value(&hk.create(keys.run)?))?;
+        if !self.manage_task_manager_override {
+            return Ok(());
+        }
+        // the key is created if absent on clean installs, so Task Manager has a record,
+        // and skipped only if that is denied
+        if let Ok(key) = hk.create(keys.task_manager_override) {
             key.set_bytes(
-                &self.app_name,
+                self.get_value_name(),
                 windows_registry::Type::Bytes,
-                &TASK_MANAGER_OVERRIDE_ENABLED_VALUE,
+                &self.get_enabled_override_value(&key),
             )?;
         }
         Ok(())
     }
 
     fn enable_as_current_user(&self) -> windows_registry::Result<()> {
-        CURRENT_USER.create(AL_REGKEY)?.set_string(
-            &self.app_name,
-            &format!("{} {}", &self.app_path, &self.args.join(" ")),
-        )?;
-        // this key maybe not found
-        if let Ok(key) = CURRENT_USER.create(TASK_MANAGER_OVERRIDE_REGKEY) {
+        self.enable_as_user(CURRENT_USER_HIVE, AL_REGKEY, TASK_MANAGER_OVERRIDE_REGKEY)
+    }
+
+    /// Write the `Run` value and the Task Manager override under the user hive
+    fn enable_as_user(
+        &self,
+        hk: Hive,
+        run: &str,
+        task_manager_override: &str,
+    ) -> windows_registry::Result<()> {
+        self.retry_transient(|| self.set_run_value(&hk.create(run)?))?;
+        if !self.manage_task_manager_override {
+            return Ok(());
+        }
+        // the key is created if absent on clean installs, so Task Manager has a record,
+        // and skipped only if that is denied
+        if let Ok(key) = hk.create(task_manager_override) {
             key.set_bytes(
-                &self.app_name,
+                self.get_value_name(),
                 windows_registry::Type::Bytes,
-                &TASK_MANAGER_OVERRIDE_ENABLED_VALUE,
+                &self.get_enabled_override_value(&key),
             )?;
         }
         Ok(())
@@ -85,71 +257,411 @@ impl AutoLaunch {
 
     /// Disable the AutoLaunch setting
     ///
+    /// Returns `Change::Unchanged` if there is no `Run` value to remove.
+    /// In `Dynamic` mode, the value is removed from both hives, the missing one is skipped.
+    ///
     /// ## Errors
     ///
     /// - failed to open the registry key
     /// - failed to delete value
-    pub fn disable(&self) -> Result<()> {
-        self.disable_as_admin()
-            .or_else(|e| {
-                if e.code() == E_ACCESSDENIED {
-                    self.disable_as_current_user()
-                } else {
-                    Err(e)
-                }
-            })
-            .map_err(std::io::Error::from)?;
-        Ok(())
+    /// - the entry exists under `HKEY_LOCAL_MACHINE` but the current process
+    ///   is not elevated, returns `Error::PermissionDenied`
+    pub fn disable(&self) -> Result<Change> {
+        if self.diff()? == StateDiff::Missing {
+            return Ok(Change::Unchanged);
+        }
+        let admin_run = self.get_admin_regkeys().run;
+        let disable_as_admin = || self.remove_value_if_exists(self.admin_hive(), admin_run);
+        let disable_as_current_user = || self.remove_value_if_exists(CURRENT_USER_HIVE, AL_REGKEY);
+        match self.enable_mode {
+            WindowsEnableMode::Dynamic => match disable_as_admin() {
+                // the value left under `HKEY_LOCAL_MACHINE` is reported below
+                Err(e) if is_permission_error(e.code()) => Ok(()),
+                res => res,
+            }
+            .and_then(|_| disable_as_current_user()),
+            WindowsEnableMode::CurrentUser => disable_as_current_user(),
+            WindowsEnableMode::System => disable_as_admin(),
+        }
+        .map_err(std::io::Error::from)?;
+
+        // the entry enabled by an elevated process is still there
+        if self.enable_mode == WindowsEnableMode::Dynamic
+            && self
+                .is_registered_at(self.admin_hive(), admin_run)
+                .unwrap_or(false)
+        {
+            return Err(Error::PermissionDenied(format!(
+                "the entry `{}` exists under HKEY_LOCAL_MACHINE, \
+                elevation is required to remove it",
+                self.app_name
+            )));
+        }
+        Ok(Change::Changed)
     }
 
-    fn disable_as_admin(&self) -> windows_registry::Result<()> {
-        LOCAL_MACHINE
-            .create(ADMIN_AL_REGKEY)?
-            .remove_value(&self.app_name)?;
-        Ok(())
+    /// Move the entry from `HKEY_CURRENT_USER` to `HKEY_LOCAL_MACHINE`,
+    /// along with its Task Manager override, without leaving a duplicate
+    ///
+    /// Returns `Change::Unchanged` if there is no `Run` value under `HKEY_CURRENT_USER`.
+    /// The source value is removed only after the target one is written,
+    /// and the target one is rolled back if it fails, so the entry stays in exactly one hive.
+    ///
+    /// ## Errors
+    ///
+    /// - the current process is not elevated, returns `Error::PermissionDenied`
+    /// - failed to read, write or remove the values
+    pub fn promote_to_system(&self) -> Result<Change> {
+        self.move_entry(
+            (CURRENT_USER_HIVE, CURRENT_USER_REGKEYS),
+            (self.admin_hive(), self.get_admin_regkeys()),
+        )
+    }
+
+    /// Move the entry from `HKEY_LOCAL_MACHINE` to `HKEY_CURRENT_USER`,
+    /// the reverse of `promote_to_system`
+    ///
+    /// Returns `Change::Unchanged` if there is no `Run` value under `HKEY_LOCAL_MACHINE`.
+    ///
+    /// ## Errors
+    ///
+    /// - the current process is not elevated, returns `Error::PermissionDenied`
+    /// - failed to read, write or remove the values
+    pub fn demote_to_current_user(&self) -> Result<Change> {
+        self.move_entry(
+            (self.admin_hive(), self.get_admin_regkeys()),
+            (CURRENT_USER_HIVE, CURRENT_USER_REGKEYS),
+        )
+    }
+
+    /// Move the `Run` value and the Task Manager override between the hives,
+    /// preserving the value types
+    fn move_entry(&self, from: (Hive, RegKeys), to: (Hive, RegKeys)) -> Result<Change> {
+        let (from_hk, from_keys) = from;
+        let (to_hk, to_keys) = to;
+        let (from_run, from_override) = (from_keys.run, from_keys.task_manager_override);
+        let (to_run, to_override) = (to_keys.run, to_keys.task_manager_override);
+        let name = self.get_value_name();
+        let map_err = |e: windows_result::Error| match is_permission_error(e.code()) {
+            true => Error::PermissionDenied(format!(
+                "elevation is required to move the entry `{}` \
+                between HKEY_LOCAL_MACHINE and HKEY_CURRENT_USER",
+                self.app_name
+            )),
+            false => std::io::Error::from(e).into(),
+        };
+
+        let run_value = match from_hk.open(from_run).and_then(|key| key.get_value(&name)) {
+            Ok(value) => value,
+            Err(e) if e.code() == E_FILENOTFOUND => return Ok(Change::Unchanged),
+            Err(e) => return Err(map_err(e)),
+        };
+        let override_value = from_hk
+            .open(from_override)
+            .and_then(|key| key.get_value(&name))
+            .ok();
+
+        let write = || -> windows_registry::Result<()> {
+            to_hk.create(to_run)?.set_value(&name, &run_value)?;
+            if let Some(value) = &override_value {
+                to_hk.create(to_override)?.set_value(&name, value)?;
+            }
+            Ok(())
+        };
+        let remove_source = || from_hk.create(from_run)?.remove_value(&name);
+        if let Err(e) = write().and_then(|_| remove_source()) {
+            // best-effort, the entry is still in the source hive
+            let _ = to_hk.create(to_run).and_then(|key| key.remove_value(&name));
+            if override_value.is_some() {
+                let _ = to_hk
+                    .create(to_override)
+                    .and_then(|key| key.remove_value(&name));
+            }
+            return Err(map_err(e));
+        }
+
+        // the entry is moved, the stale override is harmless without its `Run` value
+        if override_value.is_some() {
+            from_hk
+                .create(from_override)
+                .and_then(|key| key.remove_value(&name))
+                .map_err(map_err)?;
+        }
+        Ok(Change::Changed)
     }
 
-    fn disable_as_current_user(&self) -> windows_registry::Result<()> {
-        CURRENT_USER
-            .create(AL_REGKEY)?
-            .remove_value(&self.app_name)?;
+    /// Remove the `Run`, `RunOnce` and Task Manager override values
+    /// of both `HKEY_LOCAL_MACHINE` and `HKEY_CURRENT_USER`, for uninstalling
+    ///
+    /// It is best-effort regardless of the `enable_mode`, the missing values are skipped,
+    /// and all the values are tried even if some of them fail.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to remove some of the values, returns `Error::PurgeFailed`,
+    ///   e.g. the values under `HKEY_LOCAL_MACHINE` without elevation
+    pub fn purge_all(&self) -> Result<()> {
+        let (admin_hive, admin_keys) = (self.admin_hive(), self.get_admin_regkeys());
+        let keys = [
+            (admin_hive, admin_keys.run),
+            (admin_hive, admin_keys.run_once),
+            (admin_hive, admin_keys.task_manager_override),
+            (CURRENT_USER_HIVE, AL_REGKEY),
+            (CURRENT_USER_HIVE, RUN_ONCE_REGKEY),
+            (CURRENT_USER_HIVE, TASK_MANAGER_OVERRIDE_REGKEY),
+        ];
+        let results = keys.into_iter().map(|(hk, path)| {
+            Ok(self
+                .remove_value_if_exists(hk, path)
+                .map_err(std::io::Error::from)?)
+        });
+        collect_failures(results)
+    }
+
+    /// Remove the `Run` and Task Manager override values of `HKEY_LOCAL_MACHINE`
+    /// and every user hive loaded under `HKEY_USERS`, for the machine-wide uninstalling
+    ///
+    /// The hives of the users not logged in are not loaded, so they are not touched.
+    /// It is best-effort like `purge_all`, all the hives are tried even if some of them fail.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to enumerate `HKEY_USERS`, or to remove some of the values,
+    ///   returns `Error::PurgeFailed`, e.g. the other users' values without elevation
+    pub fn disable_all_users(&self) -> Result<()> {
+        let (admin_hive, admin_keys) = (self.admin_hive(), self.get_admin_regkeys());
+        let mut keys = vec![
+            (admin_hive, admin_keys.run.to_string()),
+            (admin_hive, admin_keys.task_manager_override.to_string()),
+        ];
+        let mut results = vec![];
+        match USERS.keys() {
+            // the `{SID}_Classes` keys are the file associations, not the user hives
+            Ok(sids) => keys.extend(
+                sids.filter(|sid| !sid.ends_with("_Classes"))
+                    .flat_map(|sid| {
+                        [
+                            format!("{}\\{}", sid, AL_REGKEY),
+                            format!("{}\\{}", sid, TASK_MANAGER_OVERRIDE_REGKEY),
+                        ]
+                    })
+                    .map(|path| (USERS_HIVE, path)),
+            ),
+            Err(e) => results.push(Err(std::io::Error::from(e).into())),
+        }
+        results.extend(keys.iter().map(|(hk, path)| {
+            Ok(self
+                .remove_value_if_exists(*hk, path)
+                .map_err(std::io::Error::from)?)
+        }));
+        collect_failures(results)
+    }
+
+    /// Remove the value under the `path` of `hk`, skipping it if it doesn't exist
+    fn remove_value_if_exists(&self, hk: Hive, path: &str) -> windows_registry::Result<()> {
+        let exists = hk
+            .open(path)
+            .and_then(|key| key.get_type(self.get_value_name()))
+            .is_ok();
+        if exists {
+            hk.create(path)?.remove_value(self.get_value_name())?;
+        }
         Ok(())
     }
 
     /// Check whether the AutoLaunch setting is enabled
+    ///
+    /// The entry disabled in Task Manager is treated as not enabled,
+    /// unless `manage_task_manager_override` is false.
+    ///
+    /// Each hive is evaluated on its own, with the override of the same hive,
+    /// so the `StartupApproved` key missing in the other hive doesn't matter:
+    ///
+    /// | `Run` value | `StartupApproved` key | override value | enabled |
+    /// |-------------|-----------------------|----------------|---------|
+    /// | absent      | any                   | any            | false   |
+    /// | present     | absent                | -              | true    |
+    /// | present     | present               | absent         | true    |
+    /// | present     | present               | enabled        | true    |
+    /// | present     | present               | disabled       | false   |
+    ///
+    /// In `Dynamic` mode, it is enabled if either hive is enabled.
     pub fn is_enabled(&self) -> Result<bool> {
-        let res = match self.is_enabled_as_admin() {
-            Ok(false) => self.is_enabled_as_current_user(),
-            Err(e) if e.code() == E_ACCESSDENIED => self.is_enabled_as_current_user(),
-            Ok(enabled) => Ok(enabled),
-            Err(e) => Err(e),
+        if is_disabled_by_env() {
+            return Ok(false);
+        }
+        let res = match self.enable_mode {
+            WindowsEnableMode::Dynamic => match self.is_enabled_as_admin() {
+                Ok(false) => self.is_enabled_as_current_user(),
+                Err(e) if is_permission_error(e.code()) => self.is_enabled_as_current_user(),
+                Ok(enabled) => Ok(enabled),
+                Err(e) => Err(e),
+            },
+            WindowsEnableMode::CurrentUser => self.is_enabled_as_current_user(),
+            WindowsEnableMode::System => self.is_enabled_as_admin(),
         }
         .map_err(std::io::Error::from)?;
         Ok(res)
     }
 
     fn is_enabled_as_admin(&self) -> windows_registry::Result<bool> {
-        let adm_enabled = LOCAL_MACHINE
-            .open(ADMIN_AL_REGKEY)?
-            .get_string(&self.app_name)
-            .map(|_| true)
-            .or_else(|e| {
-                if e.code() == E_FILENOTFOUND {
-                    Ok(false)
-                } else {
-                    Err(e)
-                }
-            })?;
+        let keys = self.get_admin_regkeys();
+        let hk = self.admin_hive();
+        let adm_enabled = self.is_registered_at(hk, keys.run)?;
         let task_manager_enabled = self
-            .task_manager_enabled(LOCAL_MACHINE, ADMIN_TASK_MANAGER_OVERRIDE_REGKEY)
+            .task_manager_enabled(hk, keys.task_manager_override)
             .unwrap_or(true);
         Ok(adm_enabled && task_manager_enabled)
     }
 
     fn is_enabled_as_current_user(&self) -> windows_registry::Result<bool> {
-        let al_enabled = CURRENT_USER
-            .open(AL_REGKEY)?
-            .get_string(&self.app_name)
+        self.is_enabled_as_user(CURRENT_USER_HIVE, AL_REGKEY, TASK_MANAGER_OVERRIDE_REGKEY)
+    }
+
+    /// Check the `Run` value and the Task Manager override under the user hive
+    fn is_enabled_as_user(
+        &self,
+        hk: Hive,
+        run: &str,
+        task_manager_override: &str,
+    ) -> windows_registry::Result<bool> {
+        let al_enabled = self.is_registered_at(hk, run)?;
+        let task_manager_enabled = self
+            .task_manager_enabled(hk, task_manager_override)
+            .unwrap_or(true);
+        Ok(al_enabled && task_manager_enabled)
+    }
+
+    /// Enable the AutoLaunch setting for another user, under `HKEY_USERS\{sid}`
+    ///
+    /// It is for the installers running as SYSTEM, whose `HKEY_CURRENT_USER` is not the user's.
+    /// The `Run` value and the Task Manager override are written the same way as
+    /// the `CurrentUser` mode, regardless of the `enable_mode`.
+    ///
+    /// ## Errors
+    ///
+    /// - the hive of the user is not loaded, e.g. the user is not logged in,
+    ///   or the `sid` is malformed, returns `Error::InvalidInput`
+    /// - failed to set value, e.g. the current process is not elevated
+    pub fn enable_for_user(&self, sid: &str) -> Result<()> {
+        if is_disabled_by_env() {
+            return Ok(());
+        }
+        let (run, task_manager_override) = get_user_regkeys(sid)?;
+        self.enable_as_user(USERS_HIVE, &run, &task_manager_override)
+            .map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    /// Disable the AutoLaunch setting of another user, under `HKEY_USERS\{sid}`
+    ///
+    /// The Task Manager override is removed along with the `Run` value,
+    /// so it doesn't apply to the entry enabled again later.
+    /// Returns `Change::Unchanged` if there is no `Run` value to remove.
+    ///
+    /// ## Errors
+    ///
+    /// - the hive of the user is not loaded, or the `sid` is malformed, returns `Error::InvalidInput`
+    /// - failed to delete value, e.g. the current process is not elevated
+    pub fn disable_for_user(&self, sid: &str) -> Result<Change> {
+        let (run, task_manager_override) = get_user_regkeys(sid)?;
+        let registered = self
+            .is_registered_at(USERS_HIVE, &run)
+            .map_err(std::io::Error::from)?;
+        self.remove_value_if_exists(USERS_HIVE, &run)
+            .and_then(|_| self.remove_value_if_exists(USERS_HIVE, &task_manager_override))
+            .map_err(std::io::Error::from)?;
+        match registered {
+            true => Ok(Change::Changed),
+            false => Ok(Change::Unchanged),
+        }
+    }
+
+    /// Check whether the AutoLaunch setting of another user is enabled,
+    /// under `HKEY_USERS\{sid}`, see the truth table of `is_enabled`
+    ///
+    /// ## Errors
+    ///
+    /// - the hive of the user is not loaded, or the `sid` is malformed, returns `Error::InvalidInput`
+    /// - failed to read the registry values
+    pub fn is_enabled_for_user(&self, sid: &str) -> Result<bool> {
+        if is_disabled_by_env() {
+            return Ok(false);
+        }
+        let (run, task_manager_override) = get_user_regkeys(sid)?;
+        let enabled = self
+            .is_enabled_as_user(USERS_HIVE, &run, &task_manager_override)
+            .map_err(std::io::Error::from)?;
+        Ok(enabled)
+    }
+
+    /// Check whether the `Run` value is registered, regardless of the Task Manager override
+    ///
+    /// The registry hives are looked up the same way as `diff`.
+    /// It is false if the value was never written or deleted by other tools,
+    /// while `is_enabled` is also false if the user disabled it in Task Manager.
+    ///
+    /// ```no_run
+    /// # use auto_launch::{AutoLaunch, WindowsEnableMode};
+    /// let auto = AutoLaunch::new("the-app", "C:\\the-app.exe", WindowsEnableMode::Dynamic, &[""]);
+    /// if auto.is_registered().unwrap() && !auto.is_enabled().unwrap() {
+    ///     println!("disabled in Task Manager");
+    /// }
+    /// ```
+    pub fn is_registered(&self) -> Result<bool> {
+        let value = self.find_run_value().map_err(std::io::Error::from)?;
+        Ok(value.is_some())
+    }
+
+    /// Check whether the entry is not disabled by the user in Task Manager
+    ///
+    /// The override of the hive where the `Run` value is registered is read,
+    /// so it is true if the `Run` value is not registered,
+    /// or `manage_task_manager_override` is false.
+    ///
+    /// Combined with `is_registered`, it tells "not registered"
+    /// from "registered but disabled in Task Manager".
+    pub fn is_task_manager_enabled(&self) -> Result<bool> {
+        let value = self.find_run_value().map_err(std::io::Error::from)?;
+        let enabled = match value {
+            Some((hk, keys, _)) => self.task_manager_enabled(hk, keys.task_manager_override),
+            None => None,
+        };
+        Ok(enabled.unwrap_or(true))
+    }
+
+    /// Check whether the AutoLaunch setting is enabled, and why not if it's not
+    ///
+    /// It combines `is_registered`, `is_task_manager_enabled` and `diff`.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to read the registry values
+    pub fn enabled_status(&self) -> Result<EnabledStatus> {
+        if !self.is_registered()? {
+            return Ok(EnabledStatus::NotRegistered);
+        }
+        if !self.is_task_manager_enabled()? {
+            return Ok(EnabledStatus::RegisteredButDisabledByOs);
+        }
+        match self.diff()? {
+            StateDiff::Differs { .. } => Ok(EnabledStatus::RegisteredButPathMismatch),
+            _ => Ok(EnabledStatus::Enabled),
+        }
+    }
+
+    /// Check whether the `Run` value exists under the registry key
+    ///
+    /// The value is treated as registered regardless of its type,
+    /// so a value written by other tools as non-`REG_SZ` doesn't cause an error.
+    /// The missing `Run` key, e.g. on a clean install, is the same as the missing value.
+    fn is_registered_at(&self, hk: Hive, path: &str) -> windows_registry::Result<bool> {
+        let key = match hk.open(path) {
+            Ok(key) => key,
+            Err(e) if e.code() == E_FILENOTFOUND => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        key.get_type(self.get_value_name())
             .map(|_| true)
             .or_else(|e| {
                 if e.code() == E_FILENOTFOUND {
@@ -157,22 +669,709 @@ impl AutoLaunch {
                 } else {
                     Err(e)
                 }
-            })?;
-        let task_manager_enabled = self
-            .task_manager_enabled(CURRENT_USER, TASK_MANAGER_OVERRIDE_REGKEY)
-            .unwrap_or(true);
-        Ok(al_enabled && task_manager_enabled)
+            })
     }
 
-    fn task_manager_enabled(&self, hk: &Key, path: &str) -> Option<bool> {
-        let task_manager_override_raw_value = hk.open(path).ok()?.get_value(&self.app_name).ok()?;
-        last_eight_bytes_all_zeros(&task_manager_override_raw_value)
+    /// Compare the configured entry against the `Run` registry value on the system
+    ///
+    /// The registry hives are looked up the same way as `is_enabled`,
+    /// and `actual` is the value data, empty if it is not a string.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to open the registry key
+    /// - failed to read the value
+    pub fn diff(&self) -> Result<StateDiff> {
+        let value = self.find_run_value().map_err(std::io::Error::from)?;
+        let Some((_, _, value)) = value else {
+            return Ok(StateDiff::Missing);
+        };
+        let actual = String::try_from(value).unwrap_or_default();
+        if actual == self.get_run_value() {
+            Ok(StateDiff::Matches)
+        } else {
+            Ok(StateDiff::Differs { actual })
+        }
+    }
+
+    /// Export the `Run` value on the system as a string, e.g. for the bug reports
+    ///
+    /// It is similar to the `.reg` files, the key path with the hive in brackets,
+    /// followed by the value name and the data, or the hex bytes if it is not a string.
+    ///
+    /// ## Errors
+    ///
+    /// - the `Run` value is not found
+    /// - failed to open the registry key
+    /// - failed to read the value
+    pub fn export_entry(&self) -> Result<String> {
+        let value = self.find_run_value().map_err(std::io::Error::from)?;
+        let Some((hk, keys, value)) = value else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("the value `{}` is not found", self.get_value_name()),
+            )
+            .into());
+        };
+        let data = match String::try_from(value.clone()) {
+            Ok(data) => format!("\"{}\"", data),
+            Err(_) => {
+                let bytes = value.iter().map(|b| format!("{:02x}", b));
+                format!("hex:{}", bytes.collect::<Vec<_>>().join(","))
+            }
+        };
+        Ok(format!(
+            "[{}\\{}]\n\"{}\"={}\n",
+            hk.name,
+            hk.display_path(keys.run),
+            self.get_value_name(),
+            data
+        ))
+    }
+
+    /// Get the last write time of the registry key holding the `Run` value,
+    /// or `None` if the value is not found
+    ///
+    /// The registry only tracks the time per key, so it is the last time
+    /// any value under the `Run` key was written, not only this entry.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to open the registry key
+    /// - failed to query the key info
+    pub fn entry_modified_time(&self) -> Result<Option<SystemTime>> {
+        let value = self.find_run_value().map_err(std::io::Error::from)?;
+        let Some((hk, keys, _)) = value else {
+            return Ok(None);
+        };
+        let key = hk.open(keys.run).map_err(std::io::Error::from)?;
+
+        let mut last_write_time = [0u32; 2];
+        // SAFETY: the key handle is valid during the call, and the unused out params are null
+        let code = unsafe {
+            RegQueryInfoKeyW(
+                key.as_raw(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                &mut last_write_time,
+            )
+        };
+        if code != 0 {
+            return Err(std::io::Error::from_raw_os_error(code as i32).into());
+        }
+        Ok(Some(filetime_to_system_time(last_write_time)))
+    }
+
+    /// Get the program path of the `Run` value on the system, without the args,
+    /// or `None` if the value is not found or is not a string
+    ///
+    /// It tells which binary is registered, e.g. when another install of the same app
+    /// wrote the value with a different path, to decide whether to overwrite it.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to open the registry key
+    /// - failed to read the value
+    pub fn registered_path(&self) -> Result<Option<String>> {
+        let value = self.find_run_value().map_err(std::io::Error::from)?;
+        let command = value.and_then(|(_, _, value)| String::try_from(value).ok());
+        Ok(command.map(|command| split_program(&command).0.to_string()))
+    }
+
+    /// Replace the program path of the `Run` value with `new_path`,
+    /// and keep the args after it as is
+    ///
+    /// It preserves the args added manually by the user when upgrading,
+    /// unlike `enable` which rewrites the whole value.
+    ///
+    /// ## Errors
+    ///
+    /// - the `Run` value is not found, or is not a string
+    /// - failed to open the registry key
+    /// - failed to set value
+    pub fn update_path_preserving_args(&self, new_path: &str) -> Result<()> {
+        let value = self.find_run_value().map_err(std::io::Error::from)?;
+        let not_found = || {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("the string value `{}` is not found", self.get_value_name()),
+            )
+        };
+        let (hk, keys, value) = value.ok_or_else(not_found)?;
+        let ty = value.ty();
+        let command = String::try_from(value).map_err(|_| not_found())?;
+
+        let (_, args) = split_program(&command);
+        let mut data = quote_program(&canonicalize_app_path(new_path));
+        if !args.is_empty() {
+            data = format!("{} {}", data, args);
+        }
+        // keep the `REG_EXPAND_SZ` type as is
+        let mut value = windows_registry::Value::from(data.as_str());
+        value.set_ty(ty);
+        hk.create(keys.run)
+            .and_then(|key| key.set_value(self.get_value_name(), &value))
+            .map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    /// Get the keys under `HKEY_LOCAL_MACHINE` of the `registry_view`
+    ///
+    /// The `Run` keys have the same paths as `HKEY_CURRENT_USER` in both views,
+    /// while the override of the 32-bit view is `StartupApproved\Run32`.
+    fn get_admin_regkeys(&self) -> RegKeys {
+        match self.registry_view {
+            RegistryView::Default | RegistryView::Force32 => RegKeys {
+                task_manager_override: ADMIN_TASK_MANAGER_OVERRIDE_REGKEY,
+                ..CURRENT_USER_REGKEYS
+            },
+            RegistryView::Force64 => CURRENT_USER_REGKEYS,
+        }
+    }
+
+    /// Get `HKEY_LOCAL_MACHINE` in the `registry_view`
+    fn admin_hive(&self) -> Hive {
+        get_admin_hive(self.registry_view)
+    }
+
+    /// Get the registry value name, which is `{value_namespace}.{app_name}`
+    /// if the `value_namespace` is set, otherwise the `app_name`
+    ///
+    /// It is the exact name written under both the `Run` and the Task Manager override
+    /// (`StartupApproved\Run`) keys, to locate the entry by the other tools.
+    pub fn get_value_name(&self) -> String {
+        match &self.value_namespace {
+            Some(namespace) => format!("{}.{}", namespace, self.app_name),
+            None => self.app_name.clone(),
+        }
+    }
+
+    /// Find the `Run` value in the registry hives looked up the same way as `is_enabled`,
+    /// along with the keys of the hive
+    fn find_run_value(
+        &self,
+    ) -> windows_registry::Result<Option<(Hive, RegKeys, windows_registry::Value)>> {
+        let keys = match self.enable_mode {
+            WindowsEnableMode::Dynamic => vec![
+                (self.admin_hive(), self.get_admin_regkeys()),
+                (CURRENT_USER_HIVE, CURRENT_USER_REGKEYS),
+            ],
+            WindowsEnableMode::CurrentUser => vec![(CURRENT_USER_HIVE, CURRENT_USER_REGKEYS)],
+            WindowsEnableMode::System => vec![(self.admin_hive(), self.get_admin_regkeys())],
+        };
+        for (hk, keys) in keys {
+            match hk
+                .open(keys.run)
+                .and_then(|key| key.get_value(self.get_value_name()))
+            {
+                Ok(value) => return Ok(Some((hk, keys, value))),
+                Err(e) if e.code() == E_FILENOTFOUND || is_permission_error(e.code()) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    /// List all the string values under the `Run` registry keys,
+    /// of `HKEY_LOCAL_MACHINE` and `HKEY_CURRENT_USER` in order
+    ///
+    /// The `name` is the registry value name and the `command` is its data.
+    /// The `HKEY_LOCAL_MACHINE` values are of `RegistryView::Default`,
+    /// see `list_entries_in_view` for the other view.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to open the registry key
+    /// - failed to enumerate the values
+    pub fn list_entries() -> Result<Vec<EntryInfo>> {
+        Self::list_entries_in_view(RegistryView::Default)
+    }
+
+    /// The same as `list_entries`, but the `HKEY_LOCAL_MACHINE` values are of the `view`
+    pub fn list_entries_in_view(view: RegistryView) -> Result<Vec<EntryInfo>> {
+        let mut entries = vec![];
+        for hk in [get_admin_hive(view), CURRENT_USER_HIVE] {
+            let key = match hk.open(AL_REGKEY) {
+                Ok(key) => key,
+                Err(e) if e.code() == E_FILENOTFOUND => continue,
+                Err(e) => return Err(std::io::Error::from(e).into()),
+            };
+            let values = key.values().map_err(std::io::Error::from)?;
+            for (name, value) in values {
+                if let Ok(command) = String::try_from(value) {
+                    entries.push(EntryInfo { name, command });
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Count the `Run` values of both `HKEY_LOCAL_MACHINE` in the `registry_view`
+    /// and `HKEY_CURRENT_USER` for the app, named by the `app_name` or launching the `app_path`
+    ///
+    /// A count greater than 1 means duplicates, e.g. left in both hives by the `Dynamic` mode
+    /// with and without elevation.
+    ///
+    /// ## Errors
+    ///
+    /// - failed to read the registry values
+    pub fn entry_count(&self) -> Result<usize> {
+        let name = self.get_value_name();
+        let count = Self::list_entries_in_view(self.registry_view)?
+            .iter()
+            .filter(|entry| {
+                let (program, _) = split_program(&entry.command);
+                entry.name.eq_ignore_ascii_case(&name)
+                    || program.eq_ignore_ascii_case(&self.canonical_app_path)
+            })
+            .count();
+        Ok(count)
+    }
+
+    /// Get the `Run` registry key path of `HKEY_CURRENT_USER`, where the entries are written
+    ///
+    /// The one of `HKEY_LOCAL_MACHINE` is the same path in the `registry_view`,
+    /// i.e. `SOFTWARE\WOW6432Node` in the 32-bit view.
+    pub fn run_registry_key_path() -> &'static str {
+        AL_REGKEY
+    }
+
+    /// Get the `StartupApproved` registry key path of `HKEY_CURRENT_USER`,
+    /// where the Task Manager overrides are written
+    ///
+    /// The one of `HKEY_LOCAL_MACHINE` is `StartupApproved\Run32` instead.
+    pub fn startup_approved_registry_key_path() -> &'static str {
+        TASK_MANAGER_OVERRIDE_REGKEY
+    }
+
+    /// Check whether an elevated process is required to `enable` with the `enable_mode`
+    ///
+    /// - `System` always requires elevation
+    /// - `CurrentUser` never requires elevation
+    /// - `Dynamic` requires elevation to use `HKEY_LOCAL_MACHINE`,
+    ///   which is probed by opening the key for writing with the current process token
+    pub fn requires_elevation(&self) -> bool {
+        match self.enable_mode {
+            WindowsEnableMode::Dynamic => !self.admin_hive().is_writable(AL_REGKEY),
+            WindowsEnableMode::CurrentUser => false,
+            WindowsEnableMode::System => true,
+        }
+    }
+
+    /// get the registry scope to write the entry
+    pub fn get_windows_enable_mode(&self) -> WindowsEnableMode {
+        self.enable_mode
+    }
+
+    /// get how many times to retry writing the `Run` value on the transient errors
+    pub fn get_registry_retry_count(&self) -> u32 {
+        self.registry_retry_count
+    }
+
+    /// get the registry view of `HKEY_LOCAL_MACHINE`
+    pub fn get_registry_view(&self) -> RegistryView {
+        self.registry_view
+    }
+
+    /// get whether the Task Manager override is managed
+    pub fn get_manage_task_manager_override(&self) -> bool {
+        self.manage_task_manager_override
+    }
+
+    /// Describe the changes that `enable`, `enable_once` or `disable` would make,
+    /// without touching the system
+    ///
+    /// The registry hive is chosen the same way as `enable` and `disable`,
+    /// in `Dynamic` mode it is `HKEY_LOCAL_MACHINE` if writable, otherwise `HKEY_CURRENT_USER`.
+    /// The `Run` values to remove are listed for every hive `disable` removes them from
+    /// and where they exist, i.e. both hives in `Dynamic` mode.
+    /// It never fails on Windows, the `Result` is for the same signature as the other platforms.
+    pub fn plan(&self, action: Action) -> Result<Vec<PlannedChange>> {
+        let use_admin = match self.enable_mode {
+            WindowsEnableMode::Dynamic => self.admin_hive().is_writable(AL_REGKEY),
+            WindowsEnableMode::CurrentUser => false,
+            WindowsEnableMode::System => true,
+        };
+        let (hk, keys) = if use_admin {
+            (self.admin_hive(), self.get_admin_regkeys())
+        } else {
+            (CURRENT_USER_HIVE, CURRENT_USER_REGKEYS)
+        };
+        let (key, override_key, run_once_key) =
+            (keys.run, keys.task_manager_override, keys.run_once);
+
+        Ok(match action {
+            Action::Enable => {
+                let mut changes = vec![self.plan_run_value(hk, key)];
+                if self.manage_task_manager_override {
+                    changes.push(PlannedChange::SetRegistryBytes {
+                        hive: hk.name.into(),
+                        key: hk.display_path(override_key),
+                        name: self.get_value_name(),
+                        data: hk.open(override_key).map_or_else(
+                            |_| TASK_MANAGER_OVERRIDE_ENABLED_VALUE.to_vec(),
+                            |key| self.get_enabled_override_value(&key),
+                        ),
+                    });
+                }
+                changes
+            }
+            Action::Disable => {
+                let hives = match self.enable_mode {
+                    WindowsEnableMode::Dynamic => vec![
+                        (self.admin_hive(), self.get_admin_regkeys().run),
+                        (CURRENT_USER_HIVE, AL_REGKEY),
+                    ],
+                    _ => vec![(hk, key)],
+                };
+                hives
+                    .into_iter()
+                    .filter(|(hk, key)| self.is_registered_at(*hk, key).unwrap_or(false))
+                    .map(|(hk, key)| PlannedChange::RemoveRegistryValue {
+                        hive: hk.name.into(),
+                        key: hk.display_path(key),
+                        name: self.get_value_name(),
+                    })
+                    .collect()
+            }
+            Action::EnableOnce => vec![self.plan_run_value(hk, run_once_key)],
+        })
+    }
+
+    /// Get the command line stored in the `Run` registry value
+    ///
+    /// The path and args are quoted in the way `CommandLineToArgvW` splits them.
+    /// The `${NAME}` placeholders in args are converted to `%NAME%`,
+    /// which Windows expands on login as the value is written as `REG_EXPAND_SZ`,
+    /// except `${APP_DIR}` which is replaced by the dir of `app_path` at once.
+    fn get_run_value(&self) -> String {
+        let lookup = |name: &str| match name {
+            APP_DIR_PLACEHOLDER => Some(self.get_app_dir()),
+            _ => Some(format!("%{}%", name)),
+        };
+        let mut command = vec![quote_program(&self.canonical_app_path)];
+        command.extend(
+            self.args
+                .iter()
+                .map(|x| quote_arg(&expand_placeholders(x, lookup))),
+        );
+        command.extend(self.raw_args.clone());
+        command.join(" ")
+    }
+
+    /// Retry the registry operation with backoff on the transient errors,
+    /// when the key is held by another process, up to `registry_retry_count` times
+    ///
+    /// The permission errors are not transient, they are returned at once for the fallback.
+    fn retry_transient<T>(
+        &self,
+        op: impl Fn() -> windows_registry::Result<T>,
+    ) -> windows_registry::Result<T> {
+        let mut delay = std::time::Duration::from_millis(50);
+        let mut retries = 0;
+        loop {
+            match op() {
+                Err(e) if is_transient_error(e.code()) && retries < self.registry_retry_count => {
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                    retries += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Get the dir of the canonical `app_path`, for the `${APP_DIR}` placeholder
+    fn get_app_dir(&self) -> String {
+        let path = std::path::Path::new(&self.canonical_app_path);
+        let dir = path.parent().unwrap_or(path);
+        dir.to_string_lossy().into_owned()
+    }
+
+    /// Check whether the args contain the `${NAME}` placeholders of the environment variables,
+    /// so the `Run` value is written as `REG_EXPAND_SZ`
+    fn has_placeholders(&self) -> bool {
+        let lookup = |name: &str| (name != APP_DIR_PLACEHOLDER).then(String::new);
+        self.args
+            .iter()
+            .any(|x| expand_placeholders(x, lookup) != *x)
+    }
+
+    /// Write the `Run` value under the registry key, see `get_run_value`
+    fn set_run_value(&self, key: &Key) -> windows_registry::Result<()> {
+        let data = self.get_run_value();
+        if !self.has_placeholders() {
+            return key.set_string(&self.get_value_name(), &data);
+        }
+        let mut value = windows_registry::Value::from(data.as_str());
+        value.set_ty(windows_registry::Type::ExpandString);
+        key.set_value(self.get_value_name(), &value)
+    }
+
+    /// Plan writing the `Run` value under the registry key, see `set_run_value`
+    fn plan_run_value(&self, hk: Hive, key: &str) -> PlannedChange {
+        let (hive, key, name, data) = (
+            hk.name.into(),
+            hk.display_path(key),
+            self.get_value_name(),
+            self.get_run_value(),
+        );
+        match self.has_placeholders() {
+            true => PlannedChange::SetRegistryExpandString {
+                hive,
+                key,
+                name,
+                data,
+            },
+            false => PlannedChange::SetRegistryString {
+                hive,
+                key,
+                name,
+                data,
+            },
+        }
+    }
+
+    /// Read the Task Manager override under the `StartupApproved` key of the hive,
+    /// see the truth table of `is_enabled`
+    ///
+    /// Returns `None` if there is no override to honor, which is treated as enabled:
+    /// the key or the value is absent, the blob is malformed,
+    /// or `manage_task_manager_override` is false.
+    fn task_manager_enabled(&self, hk: Hive, path: &str) -> Option<bool> {
+        if !self.manage_task_manager_override {
+            return None;
+        }
+        let key = hk.open(path).ok()?;
+        let task_manager_override_raw_value = key.get_value(self.get_value_name()).ok()?;
+        is_override_flag_enabled(&task_manager_override_raw_value)
+    }
+
+    /// Get the enabled Task Manager override to write into the `StartupApproved` key
+    ///
+    /// Only the leading flag of the existing value is flipped, so the trailing
+    /// `FILETIME` set by Task Manager or the user is preserved.
+    fn get_enabled_override_value(&self, key: &Key) -> Vec<u8> {
+        let mut value = TASK_MANAGER_OVERRIDE_ENABLED_VALUE.to_vec();
+        if let Ok(existing) = key.get_value(self.get_value_name()) {
+            if existing.len() == value.len() {
+                value[4..].copy_from_slice(&existing[4..]);
+            }
+        }
+        value
+    }
+}
+
+/// Get `HKEY_LOCAL_MACHINE` in the registry view,
+/// opened via the `KEY_WOW64_32KEY` or `KEY_WOW64_64KEY` access flag
+fn get_admin_hive(view: RegistryView) -> Hive {
+    let view = match view {
+        RegistryView::Default | RegistryView::Force32 => KEY_WOW64_32KEY,
+        RegistryView::Force64 => KEY_WOW64_64KEY,
+    };
+    Hive {
+        key: LOCAL_MACHINE,
+        name: "HKEY_LOCAL_MACHINE",
+        view,
+    }
+}
+
+/// Get the `Run` and Task Manager override keys of the user hive under `HKEY_USERS`,
+/// checking the hive is loaded
+fn get_user_regkeys(sid: &str) -> Result<(String, String)> {
+    if sid.is_empty() || sid.contains('\\') {
+        return Err(Error::InvalidInput(format!("`{}` is not a user SID", sid)));
+    }
+    if let Err(e) = USERS.open(sid) {
+        return Err(match e.code() == E_FILENOTFOUND {
+            true => Error::InvalidInput(format!(
+                "the hive of the user `{}` is not loaded under HKEY_USERS, \
+                e.g. the user is not logged in",
+                sid
+            )),
+            false => std::io::Error::from(e).into(),
+        });
+    }
+    Ok((
+        format!("{}\\{}", sid, AL_REGKEY),
+        format!("{}\\{}", sid, TASK_MANAGER_OVERRIDE_REGKEY),
+    ))
+}
+
+impl Hive {
+    /// Open the existing key under the hive for reading
+    fn open(&self, path: &str) -> windows_registry::Result<Key> {
+        self.open_with(path, KEY_READ)
+    }
+
+    /// Open the key under the hive for reading and writing, created if missing
+    fn create(&self, path: &str) -> windows_registry::Result<Key> {
+        let wide_path = to_wide(path);
+        let mut handle = std::ptr::null_mut();
+        // SAFETY: the path is null-terminated and outlives the call, the optional params are null
+        let code = unsafe {
+            RegCreateKeyExW(
+                self.key.as_raw(),
+                wide_path.as_ptr(),
+                0,
+                std::ptr::null(),
+                0,
+                KEY_READ | KEY_WRITE | self.view_of(path),
+                std::ptr::null(),
+                &mut handle,
+                std::ptr::null_mut(),
+            )
+        };
+        HRESULT::from_win32(code).ok()?;
+        // SAFETY: the handle is opened above and owned by `Key`, which closes it on drop
+        Ok(unsafe { Key::from_raw(handle) })
+    }
+
+    /// Open the existing key under the hive with the `access`
+    fn open_with(&self, path: &str, access: u32) -> windows_registry::Result<Key> {
+        let wide_path = to_wide(path);
+        let mut handle = std::ptr::null_mut();
+        // SAFETY: the path is null-terminated and outlives the call
+        let code = unsafe {
+            RegOpenKeyExW(
+                self.key.as_raw(),
+                wide_path.as_ptr(),
+                0,
+                access | self.view_of(path),
+                &mut handle,
+            )
+        };
+        HRESULT::from_win32(code).ok()?;
+        // SAFETY: the handle is opened above and owned by `Key`, which closes it on drop
+        Ok(unsafe { Key::from_raw(handle) })
+    }
+
+    /// Check whether the key is writable by the current process, without creating it
+    ///
+    /// The existing key is opened with `KEY_SET_VALUE` access only,
+    /// and the missing key is probed by the access to create it under its parent.
+    fn is_writable(&self, path: &str) -> bool {
+        self.is_accessible(path, KEY_SET_VALUE)
+    }
+
+    /// Check whether the key can be opened with the `access`,
+    /// or created under the closest existing parent if it's missing
+    fn is_accessible(&self, path: &str, access: u32) -> bool {
+        match self.open_with(path, access) {
+            Ok(_) => true,
+            Err(e) if e.code() == E_FILENOTFOUND => path
+                .rsplit_once('\\')
+                .is_some_and(|(parent, _)| self.is_accessible(parent, KEY_CREATE_SUB_KEY)),
+            Err(_) => false,
+        }
+    }
+
+    /// Get the access flag of the view to open the key at `path`
+    ///
+    /// The `StartupApproved` keys are read by Task Manager in the 64-bit view only,
+    /// e.g. `StartupApproved\Run32` for the `Run` values of the 32-bit view.
+    fn view_of(&self, path: &str) -> u32 {
+        match self.view != 0 && path.contains("\\StartupApproved\\") {
+            true => KEY_WOW64_64KEY,
+            false => self.view,
+        }
     }
+
+    /// Get the path of the key as stored in the registry, e.g. for `plan`,
+    /// which is under `WOW6432Node` in the 32-bit view
+    fn display_path(&self, path: &str) -> String {
+        match (self.view_of(path), path.split_once('\\')) {
+            (KEY_WOW64_32KEY, Some((root, rest))) => format!("{}\\WOW6432Node\\{}", root, rest),
+            _ => path.into(),
+        }
+    }
+}
+
+/// Encode the path as the null-terminated UTF-16 string for the Win32 APIs
+fn to_wide(path: &str) -> Vec<u16> {
+    path.encode_utf16().chain([0]).collect()
+}
+
+/// Split the command line into the program path and the rest args,
+/// the program path is taken up to the next `"` if quoted, otherwise the next whitespace
+fn split_program(command: &str) -> (&str, &str) {
+    let command = command.trim_start();
+    let (program, rest) = match command.strip_prefix('"') {
+        Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+        None => command.split_once([' ', '\t']).unwrap_or((command, "")),
+    };
+    (program, rest.trim())
+}
+
+/// Check whether the error means the registry key is not writable by the current process,
+/// which makes the `Dynamic` mode fall back to `HKEY_CURRENT_USER`
+fn is_permission_error(code: HRESULT) -> bool {
+    [E_ACCESSDENIED, E_PRIVILEGENOTHELD, E_CANTWRITE].contains(&code)
+}
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegCreateKeyExW(
+        hkey: *mut core::ffi::c_void,
+        lpsubkey: *const u16,
+        reserved: u32,
+        lpclass: *const u16,
+        dwoptions: u32,
+        samdesired: u32,
+        lpsecurityattributes: *const core::ffi::c_void,
+        phkresult: *mut *mut core::ffi::c_void,
+        lpdwdisposition: *mut u32,
+    ) -> u32;
+    fn RegOpenKeyExW(
+        hkey: *mut core::ffi::c_void,
+        lpsubkey: *const u16,
+        uloptions: u32,
+        samdesired: u32,
+        phkresult: *mut *mut core::ffi::c_void,
+    ) -> u32;
+    fn RegQueryInfoKeyW(
+        hkey: *mut core::ffi::c_void,
+        lpclass: *mut u16,
+        lpcchclass: *mut u32,
+        lpreserved: *const u32,
+        lpcsubkeys: *mut u32,
+        lpcbmaxsubkeylen: *mut u32,
+        lpcbmaxclasslen: *mut u32,
+        lpcvalues: *mut u32,
+        lpcbmaxvaluenamelen: *mut u32,
+        lpcbmaxvaluelen: *mut u32,
+        lpcbsecuritydescriptor: *mut u32,
+        lpftlastwritetime: *mut [u32; 2],
+    ) -> u32;
+}
+
+/// Convert the `FILETIME`, the 100-nanosecond intervals since 1601-01-01, into `SystemTime`
+fn filetime_to_system_time([low, high]: [u32; 2]) -> SystemTime {
+    const UNIX_EPOCH_INTERVALS: u64 = 116_444_736_000_000_000;
+    let intervals = (u64::from(high) << 32 | u64::from(low)).saturating_sub(UNIX_EPOCH_INTERVALS);
+    let since_epoch = Duration::from_secs(intervals / 10_000_000)
+        + Duration::from_nanos(intervals % 10_000_000 * 100);
+    SystemTime::UNIX_EPOCH + since_epoch
+}
+
+/// Check whether the error is caused by another process holding the registry key
+fn is_transient_error(code: HRESULT) -> bool {
+    [E_SHARINGVIOLATION, E_LOCKVIOLATION].contains(&code)
 }
 
-fn last_eight_bytes_all_zeros(bytes: &[u8]) -> Option<bool> {
+/// Check the leading flag of the Task Manager override, which is odd when disabled,
+/// e.g. `0x03` by Task Manager, and even when enabled, e.g. `0x02` or `0x06`
+fn is_override_flag_enabled(bytes: &[u8]) -> Option<bool> {
     if bytes.len() < 8 {
         return None;
     }
-    Some(bytes.iter().rev().take(8).all(|v| *v == 0u8))
+    Some(bytes[0] & 0x01 == 0)
 }
@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod unit_test {
-    use auto_launch::{AutoLaunch, AutoLaunchBuilder};
+    use auto_launch::{
+        render_entry, AutoLaunch, AutoLaunchBuilder, Change, Error, Platform, StateDiff,
+        WindowsEnableMode, LAUNCHED_AT_STARTUP_ARG,
+    };
     use std::env::current_dir;
 
     pub fn get_test_bin(name: &str) -> String {
@@ -24,6 +27,15 @@ mod unit_test {
         assert!(AutoLaunch::is_support());
     }
 
+    fn _assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        _assert_send_sync::<AutoLaunch>();
+        _assert_send_sync::<AutoLaunchBuilder>();
+        _assert_send_sync::<Error>();
+    }
+
     #[cfg(not(target_os = "macos"))]
     #[test]
     fn test_builder() {
@@ -45,6 +57,387 @@ mod unit_test {
         auto.disable().unwrap();
         assert!(!auto.is_enabled().unwrap());
     }
+
+    #[test]
+    fn test_change() {
+        let app_path = get_test_bin("auto-launch-test");
+        let build = |args: &[&str]| {
+            AutoLaunchBuilder::new()
+                .set_app_name("AutoLaunchChangeTest")
+                .set_app_path(&app_path)
+                .set_use_launch_agent(true)
+                .set_args(args)
+                .build()
+                .unwrap()
+        };
+        let auto = build(&["--minimized"]);
+
+        auto.disable().unwrap();
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        assert_eq!(auto.enable().unwrap(), Change::Unchanged);
+        // rewritten with the updated args
+        let updated = build(&["--hidden"]);
+        assert_eq!(updated.enable().unwrap(), Change::Changed);
+        assert_eq!(updated.enable().unwrap(), Change::Unchanged);
+
+        assert_eq!(auto.disable().unwrap(), Change::Changed);
+        assert_eq!(auto.disable().unwrap(), Change::Unchanged);
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_builder_raw_args() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchRawArgsTest")
+            .set_app_path(&app_path)
+            .set_raw_args(r#"--a --b="x y""#);
+
+        let auto = builder.build().unwrap();
+        assert_eq!(auto.get_raw_args(), Some(r#"--a --b="x y""#));
+        assert!(auto.get_args().is_empty());
+
+        // mutually exclusive with the structured args
+        let result = builder.set_args(&["--c"]).build();
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+
+    /// Run in a child process with `AUTO_LAUNCH_DISABLE=1`, not to affect the other tests
+    #[test]
+    fn test_disable_env() {
+        let app_path = get_test_bin("auto-launch-test");
+        if std::env::var_os("AUTO_LAUNCH_DISABLE").is_none() {
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["unit_test::test_disable_env", "--exact"])
+                .env("AUTO_LAUNCH_DISABLE", "1")
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "{}", stdout);
+            assert!(stdout.contains("1 passed"), "{}", stdout);
+            return;
+        }
+
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchDisableEnvTest")
+            .set_app_path(&app_path)
+            .build()
+            .unwrap();
+        assert_eq!(auto.enable().unwrap(), Change::Unchanged);
+        assert!(!auto.is_enabled().unwrap());
+        assert_eq!(auto.diff().unwrap(), StateDiff::Missing);
+    }
+
+    #[test]
+    fn test_builder_app_path_from_current_exe() {
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("auto-launch-test")
+            .set_app_path_from_current_exe();
+        let app_path = std::path::PathBuf::from(builder.app_path.clone().unwrap());
+        assert!(app_path.is_absolute());
+        assert!(app_path.exists());
+        assert_eq!(app_path, std::env::current_exe().unwrap());
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn test_builder_clear() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("auto-launch-test")
+            .set_app_path(&app_path)
+            .set_args(&["--minimized"]);
+        assert_eq!(builder.build().unwrap().get_args(), &["--minimized"]);
+
+        let auto = builder.clear_args().build().unwrap();
+        assert!(auto.get_args().is_empty());
+        assert_eq!(auto.get_raw_args(), None);
+
+        // the raw args can be set after clearing the args
+        let auto = builder
+            .set_args(&["--a"])
+            .clear_args()
+            .set_raw_args("--b")
+            .build()
+            .unwrap();
+        assert_eq!(auto.get_raw_args(), Some("--b"));
+
+        // not rejected by the strict mode on other platforms once cleared
+        builder
+            .set_bundle_identifiers(&["com.github.auto-launch-test"])
+            .clear_bundle_identifiers();
+        assert!(builder.clone().set_strict(true).build().is_ok());
+
+        builder.reset();
+        assert!(matches!(builder.build(), Err(Error::AppNameNotSpecified)));
+        let auto = builder
+            .set_app_name("auto-launch-test")
+            .set_app_path(&app_path)
+            .build()
+            .unwrap();
+        assert!(auto.get_args().is_empty());
+        assert_eq!(auto.get_raw_args(), None);
+    }
+
+    #[test]
+    fn test_builder_strict() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("auto-launch-test")
+            .set_app_path(&app_path);
+        let rejected = |builder: &AutoLaunchBuilder| {
+            // ignored by default, rejected in the strict mode
+            assert!(builder.build().is_ok());
+            let result = builder.clone().set_strict(true).build();
+            matches!(result, Err(Error::InvalidInput(msg)) if !msg.is_empty())
+        };
+
+        assert!(!rejected(&builder));
+        assert_eq!(
+            rejected(builder.clone().set_use_launch_agent(true)),
+            cfg!(not(target_os = "macos"))
+        );
+        assert_eq!(
+            rejected(builder.clone().set_label("com.example.test")),
+            cfg!(not(target_os = "macos"))
+        );
+        assert_eq!(
+            rejected(
+                builder
+                    .clone()
+                    .set_windows_enable_mode(WindowsEnableMode::System)
+            ),
+            cfg!(not(target_os = "windows"))
+        );
+        assert_eq!(
+            rejected(builder.clone().set_manage_task_manager_override(false)),
+            cfg!(not(target_os = "windows"))
+        );
+        assert_eq!(
+            rejected(builder.clone().set_wrap_in_login_shell(true)),
+            cfg!(not(target_os = "linux"))
+        );
+    }
+
+    #[test]
+    fn test_entry_modified_time() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchModifiedTimeTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_args(&["--minimized"])
+            .build()
+            .unwrap();
+
+        auto.disable().unwrap();
+        assert_eq!(auto.entry_modified_time().unwrap(), None);
+
+        auto.enable().unwrap();
+        let modified = auto.entry_modified_time().unwrap().unwrap();
+        // recent, allowing the coarse timestamp slightly ahead of the clock
+        let elapsed = modified.elapsed().unwrap_or_default();
+        assert!(
+            elapsed < std::time::Duration::from_secs(60),
+            "{:?}",
+            elapsed
+        );
+
+        auto.disable().unwrap();
+        assert_eq!(auto.entry_modified_time().unwrap(), None);
+    }
+
+    #[test]
+    fn test_ensure_enabled() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchEnsureTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_args(&["--minimized"])
+            .build()
+            .unwrap();
+        auto.disable().unwrap();
+
+        assert!(auto.ensure_enabled().unwrap());
+        assert!(auto.is_enabled().unwrap());
+        let modified = auto.entry_modified_time().unwrap();
+
+        // no write for the second time
+        assert!(!auto.ensure_enabled().unwrap());
+        assert_eq!(auto.entry_modified_time().unwrap(), modified);
+
+        assert!(auto.ensure_disabled().unwrap());
+        assert!(!auto.ensure_disabled().unwrap());
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_rename_from() {
+        let app_path = get_test_bin("auto-launch-test");
+        let build = |app_name: &str| {
+            AutoLaunchBuilder::new()
+                .set_app_name(app_name)
+                .set_app_path(&app_path)
+                .set_use_launch_agent(true)
+                .set_args(&["--minimized"])
+                .build()
+                .unwrap()
+        };
+        let old = build("AutoLaunchRenameOld");
+        let new = build("AutoLaunchRenameNew");
+
+        old.enable().unwrap();
+        new.disable().unwrap();
+        new.rename_from("AutoLaunchRenameOld").unwrap();
+        assert!(!old.is_enabled().unwrap());
+        assert!(new.is_enabled().unwrap());
+
+        // no-op for the entry under the same name
+        new.rename_from("AutoLaunchRenameNew").unwrap();
+        assert!(new.is_enabled().unwrap());
+
+        new.disable().unwrap();
+        assert!(!new.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_startup_sentinel() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchMarkStartupTest")
+            .set_app_path(&app_path)
+            .set_args(&["--minimized"]);
+        assert_eq!(builder.build().unwrap().get_args(), ["--minimized"]);
+
+        builder.set_startup_sentinel(true);
+        assert_eq!(
+            builder.build().unwrap().get_args(),
+            ["--minimized", LAUNCHED_AT_STARTUP_ARG]
+        );
+    }
+
+    /// The sentinel is appended to the raw args, if set instead of the args
+    #[test]
+    fn test_startup_sentinel_raw_args() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchMarkStartupRawArgsTest")
+            .set_app_path(&app_path)
+            .set_raw_args("--a --b=\"x y\"")
+            .set_startup_sentinel(true);
+        let auto = builder.build().unwrap();
+        assert!(auto.get_args().is_empty());
+        assert_eq!(
+            auto.get_raw_args(),
+            Some(format!("--a --b=\"x y\" {}", LAUNCHED_AT_STARTUP_ARG).as_str())
+        );
+
+        let windows = render_entry(Platform::Windows, &builder).unwrap();
+        assert!(
+            windows.ends_with(&format!(" --a --b=\"x y\" {}", LAUNCHED_AT_STARTUP_ARG)),
+            "{}",
+            windows
+        );
+    }
+
+    #[test]
+    fn test_render_entry() {
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchRenderTest")
+            .set_app_path("/path/to/the app")
+            .set_args(&["--minimized", "100%"])
+            .set_startup_sentinel(true);
+
+        let linux = render_entry(Platform::Linux, &builder).unwrap();
+        let exec = format!(
+            "Exec=\"/path/to/the app\" --minimized 100%% {}",
+            LAUNCHED_AT_STARTUP_ARG
+        );
+        assert!(linux.starts_with("[Desktop Entry]\n"), "{}", linux);
+        assert!(linux.lines().any(|x| x == exec), "{}", linux);
+        assert!(linux.contains("Comment=AutoLaunchRenderTest startup script\n"));
+
+        let macos = render_entry(Platform::MacOS, &builder).unwrap();
+        let program_arguments = format!(
+            "<array><string>/path/to/the app</string><string>--minimized</string>\
+            <string>100%</string><string>{}</string></array>",
+            LAUNCHED_AT_STARTUP_ARG
+        );
+        assert!(macos.contains(&program_arguments), "{}", macos);
+        assert!(macos.contains("<string>AutoLaunchRenderTest</string>"));
+        assert!(macos.contains("<key>RunAtLoad</key>\n  <true/>"));
+
+        let windows = render_entry(Platform::Windows, &builder).unwrap();
+        assert_eq!(
+            windows,
+            format!(
+                "\"/path/to/the app\" --minimized 100% {}",
+                LAUNCHED_AT_STARTUP_ARG
+            )
+        );
+
+        // the same checks as build
+        assert!(matches!(
+            render_entry(Platform::Linux, builder.clone().set_raw_args("--a")),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            render_entry(Platform::Windows, &AutoLaunchBuilder::new()),
+            Err(Error::AppNameNotSpecified)
+        ));
+    }
+
+    #[test]
+    fn test_launched_at_startup() {
+        if std::env::var_os("AUTO_LAUNCH_TEST_CHILD").is_none() {
+            assert!(!AutoLaunch::launched_at_startup());
+            // the args after `--` are the test filters, matching no other test
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "unit_test::test_launched_at_startup",
+                    "--exact",
+                    "--",
+                    LAUNCHED_AT_STARTUP_ARG,
+                ])
+                .env("AUTO_LAUNCH_TEST_CHILD", "1")
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "{}", stdout);
+            assert!(stdout.contains("1 passed"), "{}", stdout);
+            return;
+        }
+
+        assert!(AutoLaunch::launched_at_startup());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async() {
+        let app_name = "AutoLaunchAsyncTest";
+        let app_path = get_test_bin("auto-launch-test");
+
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name(app_name)
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_args(&["--minimized"])
+            .build()
+            .unwrap();
+
+        auto.enable_async().await.unwrap();
+        assert!(auto.is_enabled_async().await.unwrap());
+        auto.disable_async().await.unwrap();
+        assert!(!auto.is_enabled_async().await.unwrap());
+    }
 }
 
 #[cfg(windows)]
@@ -53,9 +446,17 @@ mod windows_unit_test {
     use std::error::Error;
 
     use crate::unit_test::*;
-    use auto_launch::AutoLaunch;
-    use windows_registry::{Key as RegKey, CURRENT_USER, LOCAL_MACHINE};
+    use auto_launch::{
+        Action, AutoLaunch, AutoLaunchBuilder, Backend, Change, EnabledStatus, PlannedChange,
+        RegistryView, StateDiff, WindowsEnableMode, LAUNCHED_AT_STARTUP_ARG,
+    };
+    use std::time::Duration;
+    use windows_registry::{Key as RegKey, CURRENT_USER, LOCAL_MACHINE, USERS};
 
+    static RUN_REGKEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run";
+    static ADMIN_RUN_REGKEY: &str =
+        "SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Run";
+    static RUN_ONCE_REGKEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\RunOnce";
     static TASK_MANAGER_OVERRIDE_REGKEY: &str =
         "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Explorer\\StartupApproved\\Run";
     static ADMIN_TASK_MANAGER_OVERRIDE_REGKEY: &str =
@@ -130,187 +531,4763 @@ mod windows_unit_test {
     }
 
     #[test]
-    fn test_windows() {
-        let app_name = "AutoLaunchTest";
+    fn test_windows_registry_key_path() {
+        assert_eq!(AutoLaunch::run_registry_key_path(), RUN_REGKEY);
+        assert_eq!(
+            AutoLaunch::startup_approved_registry_key_path(),
+            TASK_MANAGER_OVERRIDE_REGKEY
+        );
+    }
+
+    #[test]
+    fn test_windows_active_backend() {
+        let app_path = get_test_bin("auto-launch-test");
+        for mode in [
+            WindowsEnableMode::Dynamic,
+            WindowsEnableMode::CurrentUser,
+            WindowsEnableMode::System,
+        ] {
+            let auto = AutoLaunch::new("AutoLaunchBackendTest", &app_path, mode, &[""]);
+            assert_eq!(auto.active_backend(), Backend::RegistryRun);
+        }
+    }
+
+    #[test]
+    fn test_windows_plan() {
+        let app_name = "AutoLaunchPlanTest";
         let app_path = get_test_bin("auto-launch-test");
         let args = &["--minimized"];
         let app_path = app_path.as_str();
 
-        let auto = AutoLaunch::new(app_name, app_path, args);
-
-        assert_eq!(auto.get_app_name(), app_name);
+        let auto = AutoLaunch::new(app_name, app_path, WindowsEnableMode::Dynamic, args);
+        let plan = auto.plan(Action::Enable).unwrap();
+        let (hive, key, data) = match &plan[0] {
+            PlannedChange::SetRegistryString {
+                hive, key, data, ..
+            } => (hive.clone(), key.clone(), data.clone()),
+            change => panic!("unexpected change: {:?}", change),
+        };
+        // planning should not touch the system
+        assert!(!auto.is_enabled().unwrap());
 
+        let root = match hive.as_str() {
+            "HKEY_LOCAL_MACHINE" => LOCAL_MACHINE,
+            _ => CURRENT_USER,
+        };
         auto.enable().unwrap();
-        assert!(auto.is_enabled().unwrap());
+        assert_eq!(root.open(&key).unwrap().get_string(app_name).unwrap(), data);
+        assert_eq!(
+            auto.plan(Action::Disable).unwrap(),
+            vec![PlannedChange::RemoveRegistryValue {
+                hive: hive.clone(),
+                key: key.clone(),
+                name: app_name.into(),
+            }]
+        );
         auto.disable().unwrap();
         assert!(!auto.is_enabled().unwrap());
+        assert_eq!(auto.plan(Action::Disable).unwrap(), vec![]);
 
-        if get_task_manager_override_subkey().is_some() {
-            // windows can enable after disabled by task manager
-            auto.enable().unwrap();
-            assert!(auto.is_enabled().unwrap());
-            set_task_manager_override_value(app_name, TASK_MANAGER_OVERRIDE_TEST_DATA[0].1);
-            set_admin_task_manager_override_value(app_name, TASK_MANAGER_OVERRIDE_TEST_DATA[0].1)
-                .unwrap_or(());
-
-            assert!(!auto.is_enabled().unwrap());
-
-            auto.enable().unwrap();
-            assert!(auto.is_enabled().unwrap());
+        // the entries in both hives are removed by `disable` in `Dynamic` mode,
+        // `HKEY_LOCAL_MACHINE` is written only when run elevated
+        let current_user =
+            AutoLaunch::new(app_name, app_path, WindowsEnableMode::CurrentUser, args);
+        let system = AutoLaunch::new(app_name, app_path, WindowsEnableMode::System, args);
+        current_user.enable().unwrap();
+        let mut expected = vec![];
+        if hive == "HKEY_LOCAL_MACHINE" {
+            system.enable().unwrap();
+            expected.push(PlannedChange::RemoveRegistryValue {
+                hive: hive.clone(),
+                key: key.clone(),
+                name: app_name.into(),
+            });
+        }
+        expected.push(PlannedChange::RemoveRegistryValue {
+            hive: "HKEY_CURRENT_USER".into(),
+            key: RUN_REGKEY.into(),
+            name: app_name.into(),
+        });
+        assert_eq!(auto.plan(Action::Disable).unwrap(), expected);
+        auto.disable().unwrap();
+        assert!(!current_user.is_enabled().unwrap());
+        assert!(!system.is_enabled().unwrap());
+        delete_task_manager_override_value(app_name).ok();
+    }
 
-            // test windows task manager overrides
-            delete_task_manager_override_value(app_name).ok(); // Ensure previous test runs are cleaned up
+    #[test]
+    fn test_windows_list_entries() {
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
 
-            assert_eq!(auto.get_app_name(), app_name);
-            auto.enable().unwrap();
-            assert!(auto.is_enabled().unwrap());
+        let auto1 = AutoLaunch::new(
+            "AutoLaunchListTest1",
+            app_path,
+            WindowsEnableMode::Dynamic,
+            &["--one"],
+        );
+        let auto2 = AutoLaunch::new(
+            "AutoLaunchListTest2",
+            app_path,
+            WindowsEnableMode::Dynamic,
+            &["--two"],
+        );
+        auto1.enable().unwrap();
+        auto2.enable().unwrap();
 
-            for (expected_enabled, value) in TASK_MANAGER_OVERRIDE_TEST_DATA {
-                set_task_manager_override_value(app_name, value);
-                set_admin_task_manager_override_value(app_name, value).unwrap_or(());
-                assert_eq!(
-                    auto.is_enabled().unwrap(),
-                    expected_enabled,
-                    "{:02X?}",
-                    value
-                );
-            }
+        let entries = AutoLaunch::list_entries().unwrap();
+        let find = |name: &str| entries.iter().find(|e| e.name == name).cloned();
+        assert_eq!(
+            find("AutoLaunchListTest1").unwrap().command,
+            format!("{} --one", app_path)
+        );
+        assert_eq!(
+            find("AutoLaunchListTest2").unwrap().command,
+            format!("{} --two", app_path)
+        );
 
-            auto.disable().unwrap();
-            assert!(!auto.is_enabled().unwrap());
-        }
+        auto1.disable().unwrap();
+        auto2.disable().unwrap();
+        let entries = AutoLaunch::list_entries().unwrap();
+        assert!(entries
+            .iter()
+            .all(|e| !e.name.starts_with("AutoLaunchListTest")));
     }
-}
-
-#[cfg(target_os = "macos")]
-#[cfg(test)]
-mod macos_unit_test {
-    use crate::unit_test::*;
-    use auto_launch::{AutoLaunch, AutoLaunchBuilder};
 
     #[test]
-    fn test_macos_new() {
-        let name_1 = "AutoLaunchTest"; // different name
-        let name_2 = "auto-launch-test"; // same name
-
-        let args = &["--minimized", "--hidden"];
+    fn test_windows_task_manager_override_written() {
+        let app_name = "AutoLaunchOverrideWrittenTest";
         let app_path = get_test_bin("auto-launch-test");
-        let app_path = app_path.as_str();
+        let auto = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--minimized"],
+        );
+        delete_task_manager_override_value(app_name).ok();
+        auto.enable().unwrap();
 
-        // applescript
-        let auto1 = AutoLaunch::new(name_1, app_path, false, args);
-        let auto2 = AutoLaunch::new(name_2, app_path, false, args);
-        // launch agent
-        let auto3 = AutoLaunch::new(name_1, app_path, true, args);
-        let auto4 = AutoLaunch::new(name_2, app_path, true, args);
+        // the key is created if absent, with the enabled bytes
+        let subkey = get_task_manager_override_subkey().unwrap();
+        let value = subkey.get_value(app_name).unwrap();
+        assert_eq!(value[0], 0x02);
+        assert!(value[4..].iter().all(|x| *x == 0));
 
-        // app_name will be revised
-        assert_eq!(auto1.get_app_name(), name_2);
-        assert_eq!(auto2.get_app_name(), name_2);
-        // there is not limited when using launch agent
-        assert_eq!(auto3.get_app_name(), name_1);
-        assert_eq!(auto4.get_app_name(), name_2);
+        auto.disable().unwrap();
+        delete_task_manager_override_value(app_name).ok();
     }
 
     #[test]
-    fn test_macos_main() {
-        let app_name = "auto-launch-test";
+    fn test_windows_task_manager_override_keeps_timestamp() {
+        let app_name = "AutoLaunchOverrideTimestampTest";
         let app_path = get_test_bin("auto-launch-test");
-        let args = &["--minimized", "--hidden"];
-        let app_path = app_path.as_str();
-
-        // path not exists
-        let app_name_not = "Calculator1";
-        let app_path_not = "/Applications/Calculator1.app";
+        let auto = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--minimized"],
+        );
+        auto.enable().unwrap();
 
-        // use applescript
-        let auto1 = AutoLaunch::new(app_name, app_path, false, args);
-        assert_eq!(auto1.get_app_name(), app_name);
-        auto1.enable().unwrap();
-        assert!(auto1.is_enabled().unwrap());
-        auto1.disable().unwrap();
-        assert!(!auto1.is_enabled().unwrap());
+        // disabled by the user with a custom timestamp
+        let disabled = TASK_MANAGER_OVERRIDE_TEST_DATA[0].1;
+        set_task_manager_override_value(app_name, disabled);
+        assert!(!auto.is_enabled().unwrap());
 
-        let auto2 = AutoLaunch::new(app_name_not, app_path_not, false, args);
-        assert_eq!(auto2.get_app_name(), app_name_not);
-        assert!(auto2.enable().is_err());
-        assert!(!auto2.is_enabled().unwrap());
+        // only the flag is flipped, the timestamp is kept
+        let mut expected = disabled.to_vec();
+        expected[0] = 0x02;
+        match &auto.plan(Action::Enable).unwrap()[1] {
+            PlannedChange::SetRegistryBytes { data, .. } => assert_eq!(data, &expected),
+            change => panic!("unexpected change: {:?}", change),
+        }
+        auto.enable().unwrap();
+        let subkey = get_task_manager_override_subkey().unwrap();
+        assert_eq!(subkey.get_value(app_name).unwrap().to_vec(), expected);
+        assert!(auto.is_task_manager_enabled().unwrap());
+        assert!(auto.is_enabled().unwrap());
 
-        // use launch agent
-        let auto1 = AutoLaunch::new(app_name, app_path, true, args);
-        assert_eq!(auto1.get_app_name(), app_name);
-        auto1.enable().unwrap();
-        assert!(auto1.is_enabled().unwrap());
-        auto1.disable().unwrap();
-        assert!(!auto1.is_enabled().unwrap());
+        auto.disable().unwrap();
+        delete_task_manager_override_value(app_name).ok();
+    }
 
-        let auto2 = AutoLaunch::new(app_name, app_path_not, true, args);
-        assert_eq!(auto2.get_app_name(), app_name); // will not change the name
-        assert!(auto2.enable().is_err());
-        assert!(!auto2.is_enabled().unwrap());
-        auto2.disable().unwrap();
-        assert!(!auto2.is_enabled().unwrap());
+    #[test]
+    fn test_windows_unmanaged_task_manager_override() {
+        let app_name = "AutoLaunchUnmanagedTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
 
-        // test builder
         let auto = AutoLaunchBuilder::new()
             .set_app_name(app_name)
             .set_app_path(app_path)
-            .set_args(args)
+            .set_manage_task_manager_override(false)
             .build()
             .unwrap();
+        assert!(!auto.get_manage_task_manager_override());
 
-        assert_eq!(auto.get_app_name(), app_name);
+        delete_task_manager_override_value(app_name).ok();
         auto.enable().unwrap();
         assert!(auto.is_enabled().unwrap());
+        // the override value is not written
+        if let Some(subkey) = get_task_manager_override_subkey() {
+            assert!(subkey.get_value(app_name).is_err());
+        }
+        if let Some(subkey) = get_admin_task_manager_override_subkey() {
+            assert!(subkey.get_value(app_name).is_err());
+        }
+
+        // the override value is not read
+        if get_task_manager_override_subkey().is_some() {
+            set_task_manager_override_value(app_name, TASK_MANAGER_OVERRIDE_TEST_DATA[0].1);
+            set_admin_task_manager_override_value(app_name, TASK_MANAGER_OVERRIDE_TEST_DATA[0].1)
+                .unwrap_or(());
+            assert!(auto.is_enabled().unwrap());
+            delete_task_manager_override_value(app_name).ok();
+        }
+
         auto.disable().unwrap();
         assert!(!auto.is_enabled().unwrap());
+    }
 
-        // use launch agent
-        let auto = AutoLaunchBuilder::new()
-            .set_app_name(app_name)
-            .set_app_path(app_path)
-            .set_use_launch_agent(true)
-            .set_args(args)
+    #[test]
+    fn test_windows_purge_all() {
+        let app_name = "AutoLaunchPurgeTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(app_name, app_path, WindowsEnableMode::CurrentUser, &[""]);
+        auto.enable().unwrap();
+        auto.enable_once().unwrap();
+
+        auto.purge_all().unwrap();
+        for key in [RUN_REGKEY, RUN_ONCE_REGKEY, TASK_MANAGER_OVERRIDE_REGKEY] {
+            if let Ok(key) = CURRENT_USER.open(key) {
+                assert!(key.get_value(app_name).is_err());
+            }
+        }
+        assert!(!auto.is_enabled().unwrap());
+        // nothing to purge
+        auto.purge_all().unwrap();
+    }
+
+    #[test]
+    fn test_windows_disable_all_users() {
+        let app_name = "AutoLaunchAllUsersTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(app_name, &app_path, WindowsEnableMode::CurrentUser, &[""]);
+
+        // the hives writable by the current process, at least the current user's
+        let mut written = vec![];
+        for sid in USERS
+            .keys()
+            .unwrap()
+            .filter(|sid| !sid.ends_with("_Classes"))
+        {
+            let path = format!("{}\\{}", sid, RUN_REGKEY);
+            if let Ok(key) = USERS.create(&path) {
+                if key.set_string(app_name, &app_path).is_ok() {
+                    written.push((USERS, path));
+                }
+            }
+        }
+        assert!(!written.is_empty());
+        if std::env::var_os("AUTO_LAUNCH_TEST_UNELEVATED").is_none() {
+            let key = LOCAL_MACHINE.create(ADMIN_RUN_REGKEY).unwrap();
+            key.set_string(app_name, &app_path).unwrap();
+            written.push((LOCAL_MACHINE, ADMIN_RUN_REGKEY.to_string()));
+        }
+
+        auto.disable_all_users().unwrap();
+        for (hk, path) in written {
+            assert!(hk.open(&path).unwrap().get_string(app_name).is_err());
+        }
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    /// The SID is taken from `AUTO_LAUNCH_TEST_USER_SID`,
+    /// or the first loaded hive writable by the current process
+    #[test]
+    fn test_windows_enable_for_user() {
+        let app_name = "AutoLaunchForUserTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::System,
+            &["--minimized"],
+        );
+
+        // not loaded or malformed
+        for sid in ["S-1-5-21-0-0-0-1000", "", "S-1-5-18\\SOFTWARE"] {
+            let result = auto.enable_for_user(sid);
+            assert!(
+                matches!(result, Err(auto_launch::Error::InvalidInput(_))),
+                "{}",
+                sid
+            );
+            let result = auto.is_enabled_for_user(sid);
+            assert!(
+                matches!(result, Err(auto_launch::Error::InvalidInput(_))),
+                "{}",
+                sid
+            );
+        }
+
+        let sid = std::env::var("AUTO_LAUNCH_TEST_USER_SID").unwrap_or_else(|_| {
+            USERS
+                .keys()
+                .unwrap()
+                .filter(|sid| !sid.ends_with("_Classes"))
+                .find(|sid| USERS.create(format!("{}\\{}", sid, RUN_REGKEY)).is_ok())
+                .unwrap()
+        });
+        let run_key = format!("{}\\{}", sid, RUN_REGKEY);
+        auto.disable_for_user(&sid).unwrap();
+        assert!(!auto.is_enabled_for_user(&sid).unwrap());
+
+        auto.enable_for_user(&sid).unwrap();
+        assert!(auto.is_enabled_for_user(&sid).unwrap());
+        let value = USERS.open(&run_key).unwrap().get_string(app_name).unwrap();
+        assert_eq!(value, format!("{} --minimized", app_path));
+
+        let override_key = format!("{}\\{}", sid, TASK_MANAGER_OVERRIDE_REGKEY);
+        assert!(USERS
+            .open(&override_key)
+            .unwrap()
+            .get_value(app_name)
+            .is_ok());
+
+        // the override is removed along with the `Run` value
+        assert_eq!(auto.disable_for_user(&sid).unwrap(), Change::Changed);
+        assert_eq!(auto.disable_for_user(&sid).unwrap(), Change::Unchanged);
+        assert!(!auto.is_enabled_for_user(&sid).unwrap());
+        assert!(USERS
+            .open(&override_key)
+            .unwrap()
+            .get_value(app_name)
+            .is_err());
+    }
+
+    /// The value names are written and read via the wide registry APIs
+    #[test]
+    fn test_windows_unicode_app_name() {
+        let app_name = "测试应用 🚀";
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--minimized"],
+        );
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+
+        auto.enable().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        assert!(CURRENT_USER
+            .open(RUN_REGKEY)
+            .unwrap()
+            .get_string(app_name)
+            .is_ok());
+        // the name is not truncated or garbled when enumerated
+        let entries = AutoLaunch::list_entries().unwrap();
+        assert!(entries.iter().any(|e| e.name == app_name));
+
+        if let Some(subkey) = get_task_manager_override_subkey() {
+            assert!(subkey.get_value(app_name).is_ok());
+            set_task_manager_override_value(app_name, TASK_MANAGER_OVERRIDE_TEST_DATA[0].1);
+            assert!(!auto.is_enabled().unwrap());
+            auto.enable().unwrap();
+            assert!(auto.is_enabled().unwrap());
+            delete_task_manager_override_value(app_name).ok();
+        }
+
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+        let entries = AutoLaunch::list_entries().unwrap();
+        assert!(entries.iter().all(|e| e.name != app_name));
+    }
+
+    #[test]
+    fn test_windows_entry_count() {
+        // a copy of the test bin, not to count the entries of the other tests
+        let app_path = std::env::temp_dir().join("auto-launch-entry-count-test.exe");
+        std::fs::copy(get_test_bin("auto-launch-test"), &app_path).unwrap();
+        let app_path = app_path.to_str().unwrap();
+        let new = |app_name, args| {
+            AutoLaunch::new(app_name, app_path, WindowsEnableMode::CurrentUser, args)
+        };
+
+        let auto = new("AutoLaunchEntryCountTest", &["--one"]);
+        auto.disable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 0);
+        auto.enable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 1);
+
+        // a duplicate under another name launching the same app
+        let duplicate = new("AutoLaunchEntryCountDuplicate", &["--two"]);
+        duplicate.enable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 2);
+
+        duplicate.disable().unwrap();
+        auto.disable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_windows_raw_args() {
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchRawArgsTest")
+            .set_app_path("C:\\path\\to\\the app.exe")
+            .set_raw_args(r#"--a --b="x y""#)
             .build()
             .unwrap();
+        match &auto.plan(Action::Enable).unwrap()[0] {
+            PlannedChange::SetRegistryString { data, .. } => {
+                assert_eq!(data, r#""C:\path\to\the app.exe" --a --b="x y""#)
+            }
+            change => panic!("unexpected change: {:?}", change),
+        }
+    }
 
-        assert_eq!(auto.get_app_name(), app_name);
+    #[test]
+    fn test_windows_startup_sentinel() {
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchSentinelTest")
+            .set_app_path("C:\\path\\to\\the app.exe")
+            .set_args(&["--minimized"])
+            .set_startup_sentinel(true)
+            .build()
+            .unwrap();
+        match &auto.plan(Action::Enable).unwrap()[0] {
+            PlannedChange::SetRegistryString { data, .. } => assert_eq!(
+                data,
+                &format!(
+                    r#""C:\path\to\the app.exe" --minimized {}"#,
+                    LAUNCHED_AT_STARTUP_ARG
+                )
+            ),
+            change => panic!("unexpected change: {:?}", change),
+        }
+    }
+
+    #[test]
+    fn test_windows_placeholders() {
+        let app_name = "AutoLaunchPlaceholderTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+        let args = &["--config=${APPDATA}\\app.toml"];
+
+        let auto = AutoLaunch::new(app_name, app_path, WindowsEnableMode::CurrentUser, args);
+        let expected = format!("{} --config=%APPDATA%\\app.toml", app_path);
+        assert_eq!(
+            auto.plan(Action::Enable).unwrap()[0],
+            PlannedChange::SetRegistryExpandString {
+                hive: "HKEY_CURRENT_USER".into(),
+                key: RUN_REGKEY.into(),
+                name: app_name.into(),
+                data: expected.clone(),
+            }
+        );
+
+        auto.enable().unwrap();
+        let key = CURRENT_USER.open(RUN_REGKEY).unwrap();
+        let value = key.get_value(app_name).unwrap();
+        assert_eq!(value.ty(), windows_registry::Type::ExpandString);
+        assert_eq!(String::try_from(value).unwrap(), expected);
+        assert_eq!(auto.diff().unwrap(), StateDiff::Matches);
+
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_windows_app_dir_placeholder() {
+        let app_name = "AutoLaunchAppDirTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_dir = std::path::Path::new(&app_path).parent().unwrap();
+        let config = format!("--config={}\\app.toml", app_dir.display());
+        let config = match config.contains(' ') {
+            true => format!("\"{}\"", config),
+            false => config,
+        };
+
+        // expanded at once, so the value is a plain string
+        let args = &["--config=${APP_DIR}\\app.toml"];
+        let auto = AutoLaunch::new(app_name, &app_path, WindowsEnableMode::CurrentUser, args);
+        let expected = format!("{} {}", app_path, config);
+        assert_eq!(
+            auto.plan(Action::Enable).unwrap()[0],
+            PlannedChange::SetRegistryString {
+                hive: "HKEY_CURRENT_USER".into(),
+                key: RUN_REGKEY.into(),
+                name: app_name.into(),
+                data: expected.clone(),
+            }
+        );
+        auto.enable().unwrap();
+        let value = CURRENT_USER
+            .open(RUN_REGKEY)
+            .unwrap()
+            .get_value(app_name)
+            .unwrap();
+        assert_eq!(value.ty(), windows_registry::Type::String);
+        assert_eq!(String::try_from(value).unwrap(), expected);
+
+        // the environment variables are kept for `REG_EXPAND_SZ`
+        let args = &["--config=${APP_DIR}\\app.toml", "--log=${TEMP}\\app.log"];
+        let auto = AutoLaunch::new(app_name, &app_path, WindowsEnableMode::CurrentUser, args);
+        auto.enable().unwrap();
+        let value = CURRENT_USER
+            .open(RUN_REGKEY)
+            .unwrap()
+            .get_value(app_name)
+            .unwrap();
+        assert_eq!(value.ty(), windows_registry::Type::ExpandString);
+        assert_eq!(
+            String::try_from(value).unwrap(),
+            format!("{} {} --log=%TEMP%\\app.log", app_path, config)
+        );
+        assert_eq!(auto.diff().unwrap(), StateDiff::Matches);
+
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_windows_registry_retry_count() {
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(
+            "AutoLaunchRetryTest",
+            app_path,
+            WindowsEnableMode::Dynamic,
+            &[""],
+        );
+        assert_eq!(auto.get_registry_retry_count(), 3);
+
+        // no retry at all
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchRetryTest")
+            .set_app_path(app_path)
+            .set_registry_retry_count(0)
+            .build()
+            .unwrap();
+        assert_eq!(auto.get_registry_retry_count(), 0);
         auto.enable().unwrap();
         assert!(auto.is_enabled().unwrap());
         auto.disable().unwrap();
         assert!(!auto.is_enabled().unwrap());
     }
-}
 
-#[cfg(target_os = "linux")]
-#[cfg(test)]
-mod linux_unit_test {
-    use crate::unit_test::*;
-    use auto_launch::AutoLaunch;
+    #[test]
+    fn test_windows_registration_and_task_manager_status() {
+        let app_name = "AutoLaunchStatusTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(app_name, app_path, WindowsEnableMode::Dynamic, &[""]);
+        auto.disable().unwrap();
+        assert!(!auto.is_registered().unwrap());
+        assert!(auto.is_task_manager_enabled().unwrap());
+
+        auto.enable().unwrap();
+        assert!(auto.is_registered().unwrap());
+        assert!(auto.is_task_manager_enabled().unwrap());
+
+        if get_task_manager_override_subkey().is_some() {
+            for (expected_enabled, value) in TASK_MANAGER_OVERRIDE_TEST_DATA {
+                set_task_manager_override_value(app_name, value);
+                set_admin_task_manager_override_value(app_name, value).unwrap_or(());
+                // registered but maybe disabled in Task Manager
+                assert!(auto.is_registered().unwrap());
+                assert_eq!(
+                    auto.is_task_manager_enabled().unwrap(),
+                    expected_enabled,
+                    "{:02X?}",
+                    value
+                );
+                assert_eq!(auto.is_enabled().unwrap(), expected_enabled);
+            }
+            delete_task_manager_override_value(app_name).ok();
+        }
+
+        auto.disable().unwrap();
+        assert!(!auto.is_registered().unwrap());
+    }
 
+    /// The override of the other hive is ignored, whether the `StartupApproved` key exists or not
     #[test]
-    fn test_linux() {
-        let app_name = "AutoLaunchTest";
+    fn test_windows_task_manager_override_one_hive() {
+        let app_name = "AutoLaunchOneHiveTest";
         let app_path = get_test_bin("auto-launch-test");
-        let args = &["--minimized"];
         let app_path = app_path.as_str();
 
-        // default test
-        let auto1 = AutoLaunch::new(app_name, app_path, args);
+        let auto = AutoLaunch::new(app_name, app_path, WindowsEnableMode::CurrentUser, &[""]);
+        auto.enable().unwrap();
+        if get_task_manager_override_subkey().is_none() {
+            auto.disable().unwrap();
+            return;
+        }
+        let delete_admin_override = || {
+            if let Some(subkey) = get_admin_task_manager_override_subkey() {
+                subkey.remove_value(app_name).ok();
+            }
+        };
 
-        assert_eq!(auto1.get_app_name(), app_name);
-        auto1.enable().unwrap();
-        assert!(auto1.is_enabled().unwrap());
-        auto1.disable().unwrap();
-        assert!(!auto1.is_enabled().unwrap());
+        // the override present in the current user hive only
+        delete_admin_override();
+        for (expected_enabled, value) in TASK_MANAGER_OVERRIDE_TEST_DATA {
+            set_task_manager_override_value(app_name, value);
+            assert_eq!(
+                auto.is_enabled().unwrap(),
+                expected_enabled,
+                "{:02X?}",
+                value
+            );
+            assert_eq!(auto.is_task_manager_enabled().unwrap(), expected_enabled);
+        }
 
-        // test args
-        let auto2 = AutoLaunch::new(app_name, app_path, args);
+        // the override absent in the current user hive, whatever the admin hive holds
+        delete_task_manager_override_value(app_name).ok();
+        assert!(auto.is_enabled().unwrap());
+        for (_, value) in TASK_MANAGER_OVERRIDE_TEST_DATA {
+            if set_admin_task_manager_override_value(app_name, value).is_err() {
+                break;
+            }
+            assert!(auto.is_enabled().unwrap(), "{:02X?}", value);
+            assert!(auto.is_task_manager_enabled().unwrap());
+        }
+        delete_admin_override();
 
-        assert_eq!(auto2.get_app_name(), app_name);
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_windows_registration_deleted_externally() {
+        let app_name = "AutoLaunchDeletedTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(app_name, app_path, WindowsEnableMode::CurrentUser, &[""]);
+        auto.enable().unwrap();
+        assert!(auto.is_registered().unwrap());
+
+        // the value deleted by other tools, not disabled in Task Manager
+        CURRENT_USER
+            .create(RUN_REGKEY)
+            .unwrap()
+            .remove_value(app_name)
+            .unwrap();
+        assert!(!auto.is_registered().unwrap());
+        assert!(auto.is_task_manager_enabled().unwrap());
+        assert!(!auto.is_enabled().unwrap());
+
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_windows_value_namespace() {
+        let app_name = "AutoLaunchNamespaceTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let build = |namespace: &str, args: &[&str]| {
+            AutoLaunchBuilder::new()
+                .set_app_name(app_name)
+                .set_app_path(&app_path)
+                .set_windows_enable_mode(WindowsEnableMode::CurrentUser)
+                .set_value_namespace(namespace)
+                .set_args(args)
+                .build()
+                .unwrap()
+        };
+        let auto1 = build("VendorA", &["--one"]);
+        let auto2 = build("VendorB", &["--two"]);
+        assert_eq!(auto1.get_value_name(), "VendorA.AutoLaunchNamespaceTest");
+        assert_eq!(auto2.get_value_name(), "VendorB.AutoLaunchNamespaceTest");
+
+        auto1.enable().unwrap();
         auto2.enable().unwrap();
+        let key = CURRENT_USER.open(RUN_REGKEY).unwrap();
+        assert!(key.get_string(app_name).is_err());
+        assert!(key
+            .get_string(auto1.get_value_name())
+            .unwrap()
+            .ends_with("--one"));
+        assert!(key
+            .get_string(auto2.get_value_name())
+            .unwrap()
+            .ends_with("--two"));
+        // the same name is used for the Task Manager override
+        let subkey = get_task_manager_override_subkey().unwrap();
+        assert!(subkey.get_value(app_name).is_err());
+        assert!(subkey.get_value(auto1.get_value_name()).is_ok());
+        assert!(subkey.get_value(auto2.get_value_name()).is_ok());
+
+        // disabling one doesn't touch the other
+        auto1.disable().unwrap();
+        assert!(!auto1.is_enabled().unwrap());
         assert!(auto2.is_enabled().unwrap());
         auto2.disable().unwrap();
         assert!(!auto2.is_enabled().unwrap());
+        delete_task_manager_override_value(&auto1.get_value_name()).ok();
+        delete_task_manager_override_value(&auto2.get_value_name()).ok();
+    }
+
+    #[test]
+    fn test_windows_enable_and_confirm() {
+        let app_name = "AutoLaunchConfirmTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(app_name, app_path, WindowsEnableMode::CurrentUser, &[""]);
+        auto.enable_and_confirm(Duration::from_millis(300)).unwrap();
+        assert!(auto.is_enabled().unwrap());
+
+        // removed by another agent within the window
+        let remover = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            CURRENT_USER
+                .create(RUN_REGKEY)
+                .unwrap()
+                .remove_value(app_name)
+                .unwrap();
+        });
+        let result = auto.enable_and_confirm(Duration::from_secs(2));
+        remover.join().unwrap();
+        assert!(matches!(result, Err(auto_launch::Error::EntryRemoved(name)) if name == app_name));
+        assert!(!auto.is_registered().unwrap());
+
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_windows_enabled_status() {
+        let app_name = "AutoLaunchEnabledStatusTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(
+            app_name,
+            app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--one"],
+        );
+        auto.disable().ok();
+        assert_eq!(auto.enabled_status().unwrap(), EnabledStatus::NotRegistered);
+
+        auto.enable().unwrap();
+        assert_eq!(auto.enabled_status().unwrap(), EnabledStatus::Enabled);
+        let other = AutoLaunch::new(
+            app_name,
+            app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--two"],
+        );
+        assert_eq!(
+            other.enabled_status().unwrap(),
+            EnabledStatus::RegisteredButPathMismatch
+        );
+
+        if get_task_manager_override_subkey().is_some() {
+            set_task_manager_override_value(app_name, TASK_MANAGER_OVERRIDE_TEST_DATA[0].1);
+            assert_eq!(
+                auto.enabled_status().unwrap(),
+                EnabledStatus::RegisteredButDisabledByOs
+            );
+            delete_task_manager_override_value(app_name).ok();
+        }
+
+        auto.disable().unwrap();
+        assert_eq!(auto.enabled_status().unwrap(), EnabledStatus::NotRegistered);
+    }
+
+    #[test]
+    fn test_windows_non_string_value() {
+        let app_name = "AutoLaunchNonStringTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(
+            app_name,
+            app_path,
+            WindowsEnableMode::Dynamic,
+            &["--minimized"],
+        );
+        // the value written by other tools as a DWORD
+        CURRENT_USER
+            .create(RUN_REGKEY)
+            .unwrap()
+            .set_u32(app_name, 1)
+            .unwrap();
+        assert!(auto.is_enabled().unwrap());
+
+        CURRENT_USER
+            .open(RUN_REGKEY)
+            .unwrap()
+            .remove_value(app_name)
+            .unwrap();
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    /// Requires the entry `AutoLaunchElevatedTest` to be enabled by an elevated process
+    /// under `HKEY_LOCAL_MACHINE` first, then run unelevated with `AUTO_LAUNCH_TEST_UNELEVATED=1`
+    #[test]
+    fn test_windows_disable_elevated_entry() {
+        if std::env::var_os("AUTO_LAUNCH_TEST_UNELEVATED").is_none() {
+            return;
+        }
+
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            "AutoLaunchElevatedTest",
+            &app_path,
+            WindowsEnableMode::Dynamic,
+            &["--minimized"],
+        );
+        let err = auto.disable().unwrap_err();
+        assert!(
+            matches!(err, auto_launch::Error::PermissionDenied(_)),
+            "{:?}",
+            err
+        );
+        assert!(auto.is_enabled().unwrap());
+    }
+
+    /// The missing value of the other hive is skipped, elevated or not
+    #[test]
+    fn test_windows_dynamic_disable_current_user_entry() {
+        let app_name = "AutoLaunchDynamicDisableTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let current_user = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--minimized"],
+        );
+        let dynamic = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::Dynamic,
+            &["--minimized"],
+        );
+        current_user.enable().unwrap();
+
+        assert_eq!(dynamic.disable().unwrap(), Change::Changed);
+        assert!(!current_user.is_enabled().unwrap());
+        assert!(!dynamic.is_enabled().unwrap());
+        assert_eq!(dynamic.disable().unwrap(), Change::Unchanged);
+        delete_task_manager_override_value(app_name).ok();
+    }
+
+    /// `HKEY_LOCAL_MACHINE` is written only when run elevated,
+    /// otherwise the denial is checked with `AUTO_LAUNCH_TEST_UNELEVATED=1`
+    #[test]
+    fn test_windows_promote_to_system() {
+        let app_name = "AutoLaunchPromoteTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let admin_run_regkey = "SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Run";
+        let registered = |hk: &RegKey, path: &str| {
+            hk.open(path)
+                .map(|key| key.get_type(app_name).is_ok())
+                .unwrap_or(false)
+        };
+        let auto = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--minimized"],
+        );
+        auto.disable().unwrap();
+        assert_eq!(auto.promote_to_system().unwrap(), Change::Unchanged);
+        auto.enable().unwrap();
+
+        if std::env::var_os("AUTO_LAUNCH_TEST_UNELEVATED").is_some() {
+            let err = auto.promote_to_system().unwrap_err();
+            assert!(
+                matches!(err, auto_launch::Error::PermissionDenied(_)),
+                "{:?}",
+                err
+            );
+            assert!(registered(CURRENT_USER, RUN_REGKEY));
+            assert!(!registered(LOCAL_MACHINE, admin_run_regkey));
+            auto.disable().unwrap();
+            return;
+        }
+
+        let run_value = CURRENT_USER
+            .open(RUN_REGKEY)
+            .unwrap()
+            .get_string(app_name)
+            .unwrap();
+        assert_eq!(auto.promote_to_system().unwrap(), Change::Changed);
+        assert!(!registered(CURRENT_USER, RUN_REGKEY));
+        assert!(!registered(CURRENT_USER, TASK_MANAGER_OVERRIDE_REGKEY));
+        assert_eq!(
+            LOCAL_MACHINE
+                .open(admin_run_regkey)
+                .unwrap()
+                .get_string(app_name)
+                .unwrap(),
+            run_value
+        );
+        assert!(registered(
+            LOCAL_MACHINE,
+            ADMIN_TASK_MANAGER_OVERRIDE_REGKEY
+        ));
+
+        assert_eq!(auto.demote_to_current_user().unwrap(), Change::Changed);
+        assert!(registered(CURRENT_USER, RUN_REGKEY));
+        assert!(!registered(LOCAL_MACHINE, admin_run_regkey));
+        assert!(!registered(
+            LOCAL_MACHINE,
+            ADMIN_TASK_MANAGER_OVERRIDE_REGKEY
+        ));
+        assert!(auto.is_enabled().unwrap());
+
+        auto.disable().unwrap();
+    }
+
+    /// The `Dynamic` mode is checked when run unelevated with `AUTO_LAUNCH_TEST_UNELEVATED=1`
+    #[test]
+    fn test_windows_requires_elevation() {
+        let app_path = get_test_bin("auto-launch-test");
+        let new =
+            |mode| AutoLaunch::new("AutoLaunchElevationTest", &app_path, mode, &["--minimized"]);
+
+        assert!(new(WindowsEnableMode::System).requires_elevation());
+        assert!(!new(WindowsEnableMode::CurrentUser).requires_elevation());
+        if std::env::var_os("AUTO_LAUNCH_TEST_UNELEVATED").is_some() {
+            assert!(new(WindowsEnableMode::Dynamic).requires_elevation());
+        }
+    }
+
+    /// Split the command line with the rules of `CommandLineToArgvW`
+    fn split_command_line(command: &str) -> Vec<String> {
+        let mut argv = vec![];
+        let mut chars = command.chars().peekable();
+        // the program path is taken up to the next `"` without escapes
+        let program = match chars.next_if_eq(&'"') {
+            Some(_) => chars.by_ref().take_while(|c| *c != '"').collect(),
+            None => chars.by_ref().take_while(|c| *c != ' ').collect(),
+        };
+        argv.push(program);
+
+        loop {
+            while chars.next_if_eq(&' ').is_some() {}
+            if chars.peek().is_none() {
+                return argv;
+            }
+            let mut arg = String::new();
+            let mut quoted = false;
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => {
+                        let mut backslashes = 1;
+                        while chars.next_if_eq(&'\\').is_some() {
+                            backslashes += 1;
+                        }
+                        if chars.peek() == Some(&'"') {
+                            arg.push_str(&"\\".repeat(backslashes / 2));
+                            if backslashes % 2 == 1 {
+                                arg.push(chars.next().unwrap());
+                            }
+                        } else {
+                            arg.push_str(&"\\".repeat(backslashes));
+                        }
+                    }
+                    '"' => quoted = !quoted,
+                    ' ' if !quoted => break,
+                    c => arg.push(c),
+                }
+            }
+            argv.push(arg);
+        }
+    }
+
+    #[test]
+    fn test_windows_args_quoting() {
+        let app_name = "AutoLaunchQuotingTest";
+        let app_path = "C:\\path\\to\\the app.exe";
+        let args = [
+            "--title=My App",
+            r#"say "hi""#,
+            r"C:\dir\",
+            r#"C:\dir\"quoted""#,
+            "",
+        ];
+        let auto = AutoLaunch::new(app_name, app_path, WindowsEnableMode::CurrentUser, &args);
+
+        let data = match &auto.plan(Action::Enable).unwrap()[0] {
+            PlannedChange::SetRegistryString { data, .. } => data.clone(),
+            change => panic!("unexpected change: {:?}", change),
+        };
+        let argv = split_command_line(&data);
+        assert_eq!(argv[0], app_path);
+        assert_eq!(argv[1..], args);
+    }
+
+    #[test]
+    fn test_windows_builder_enable_mode() {
+        let app_path = get_test_bin("auto-launch-test");
+        let build = |mode: Option<WindowsEnableMode>| {
+            let mut builder = AutoLaunchBuilder::new();
+            builder
+                .set_app_name("AutoLaunchBuilderModeTest")
+                .set_app_path(&app_path);
+            if let Some(mode) = mode {
+                builder.set_windows_enable_mode(mode);
+            }
+            builder.build().unwrap()
+        };
+
+        assert_eq!(
+            build(None).get_windows_enable_mode(),
+            WindowsEnableMode::Dynamic
+        );
+        assert_eq!(
+            build(Some(WindowsEnableMode::Dynamic)).get_windows_enable_mode(),
+            WindowsEnableMode::Dynamic
+        );
+        for (mode, expected_hive) in [
+            (WindowsEnableMode::CurrentUser, "HKEY_CURRENT_USER"),
+            (WindowsEnableMode::System, "HKEY_LOCAL_MACHINE"),
+        ] {
+            let auto = build(Some(mode));
+            assert_eq!(auto.get_windows_enable_mode(), mode);
+            match &auto.plan(Action::Enable).unwrap()[0] {
+                PlannedChange::SetRegistryString { hive, .. } => assert_eq!(hive, expected_hive),
+                change => panic!("unexpected change: {:?}", change),
+            }
+        }
+    }
+
+    #[test]
+    fn test_windows_registry_view() {
+        let app_name = "AutoLaunchRegistryViewTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let build = |view: Option<RegistryView>| {
+            let mut builder = AutoLaunchBuilder::new();
+            builder
+                .set_app_name(app_name)
+                .set_app_path(&app_path)
+                .set_windows_enable_mode(WindowsEnableMode::System);
+            if let Some(view) = view {
+                builder.set_registry_view(view);
+            }
+            builder.build().unwrap()
+        };
+
+        assert_eq!(build(None).get_registry_view(), RegistryView::Default);
+        for (view, expected_key) in [
+            (None, ADMIN_RUN_REGKEY),
+            (Some(RegistryView::Force32), ADMIN_RUN_REGKEY),
+            (Some(RegistryView::Force64), RUN_REGKEY),
+        ] {
+            let auto = build(view);
+            match &auto.plan(Action::Enable).unwrap()[0] {
+                PlannedChange::SetRegistryString { hive, key, .. } => {
+                    assert_eq!(hive, "HKEY_LOCAL_MACHINE");
+                    assert_eq!(key, expected_key);
+                }
+                change => panic!("unexpected change: {:?}", change),
+            }
+
+            if std::env::var_os("AUTO_LAUNCH_TEST_UNELEVATED").is_some() {
+                continue;
+            }
+            auto.enable().unwrap();
+            assert!(auto.is_enabled().unwrap());
+            assert!(LOCAL_MACHINE
+                .open(expected_key)
+                .unwrap()
+                .get_string(app_name)
+                .is_ok());
+            // listed and counted in the same view
+            let entries = AutoLaunch::list_entries_in_view(view.unwrap_or_default()).unwrap();
+            assert!(entries.iter().any(|e| e.name == app_name));
+            assert_eq!(auto.entry_count().unwrap(), 1);
+            auto.disable().unwrap();
+            assert!(!auto.is_enabled().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_windows_diff() {
+        let app_name = "AutoLaunchDiffTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let new = |args: &[&str]| {
+            AutoLaunch::new(app_name, &app_path, WindowsEnableMode::CurrentUser, args)
+        };
+        let auto = new(&["--minimized"]);
+        assert_eq!(auto.diff().unwrap(), StateDiff::Missing);
+
+        auto.enable().unwrap();
+        assert_eq!(auto.diff().unwrap(), StateDiff::Matches);
+
+        // the value written with the outdated args
+        new(&["--hidden"]).enable().unwrap();
+        assert_eq!(
+            auto.diff().unwrap(),
+            StateDiff::Differs {
+                actual: format!("{} --hidden", app_path)
+            }
+        );
+
+        auto.disable().unwrap();
+        assert_eq!(auto.diff().unwrap(), StateDiff::Missing);
+    }
+
+    #[test]
+    fn test_windows_export_entry() {
+        let app_name = "AutoLaunchExportTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--minimized"],
+        );
+        assert!(auto.export_entry().is_err());
+
+        auto.enable().unwrap();
+        assert_eq!(
+            auto.export_entry().unwrap(),
+            format!(
+                "[HKEY_CURRENT_USER\\{}]\n\"{}\"=\"{} --minimized\"\n",
+                RUN_REGKEY, app_name, app_path
+            )
+        );
+
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_windows_update_path_preserving_args() {
+        let app_name = "AutoLaunchUpdatePathTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--minimized"],
+        );
+        assert!(auto.update_path_preserving_args(&app_path).is_err());
+
+        let key = CURRENT_USER.create(RUN_REGKEY).unwrap();
+        for (value, expected) in [
+            (
+                r#""C:\old dir\app.exe" --minimized --user-flag"#,
+                format!("{} --minimized --user-flag", app_path),
+            ),
+            (
+                r#"C:\old\app.exe --minimized "--title=My App""#,
+                format!(r#"{} --minimized "--title=My App""#, app_path),
+            ),
+            (r"C:\old\app.exe", app_path.clone()),
+        ] {
+            key.set_string(app_name, value).unwrap();
+            auto.update_path_preserving_args(&app_path).unwrap();
+            assert_eq!(key.get_string(app_name).unwrap(), expected);
+        }
+
+        key.remove_value(app_name).unwrap();
+    }
+
+    #[test]
+    fn test_windows_registered_path() {
+        let app_name = "AutoLaunchRegisteredPathTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--minimized"],
+        );
+        assert_eq!(auto.registered_path().unwrap(), None);
+
+        auto.enable().unwrap();
+        assert_eq!(auto.registered_path().unwrap(), Some(app_path.clone()));
+
+        // written by another installer
+        let key = CURRENT_USER.create(RUN_REGKEY).unwrap();
+        for (data, expected) in [
+            (
+                "\"C:\\Program Files\\App\\app.exe\" --minimized",
+                "C:\\Program Files\\App\\app.exe",
+            ),
+            ("\"C:\\App\\app.exe\"", "C:\\App\\app.exe"),
+            ("C:\\App\\app.exe --minimized", "C:\\App\\app.exe"),
+            ("C:\\App\\app.exe", "C:\\App\\app.exe"),
+        ] {
+            key.set_string(app_name, data).unwrap();
+            assert_eq!(auto.registered_path().unwrap().as_deref(), Some(expected));
+        }
+
+        auto.disable().unwrap();
+        assert_eq!(auto.registered_path().unwrap(), None);
+    }
+
+    #[test]
+    fn test_windows_enable_once() {
+        let app_name = "AutoLaunchOnceTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--minimized"],
+        );
+
+        assert_eq!(
+            auto.plan(Action::EnableOnce).unwrap(),
+            vec![PlannedChange::SetRegistryString {
+                hive: "HKEY_CURRENT_USER".into(),
+                key: RUN_ONCE_REGKEY.into(),
+                name: app_name.into(),
+                data: format!("{} --minimized", app_path),
+            }]
+        );
+
+        auto.enable_once().unwrap();
+        let key = CURRENT_USER.open(RUN_ONCE_REGKEY).unwrap();
+        assert_eq!(
+            key.get_string(app_name).unwrap(),
+            format!("{} --minimized", app_path)
+        );
+        // the persistent entry is not written
+        assert!(!auto.is_enabled().unwrap());
+
+        key.remove_value(app_name).unwrap();
+    }
+
+    /// The admin-denied path is checked when run unelevated with `AUTO_LAUNCH_TEST_UNELEVATED=1`
+    #[test]
+    fn test_windows_enable_with_scope() {
+        let app_name = "AutoLaunchScopeTest";
+        let app_path = get_test_bin("auto-launch-test");
+
+        let auto = AutoLaunch::new(
+            app_name,
+            &app_path,
+            WindowsEnableMode::CurrentUser,
+            &["--minimized"],
+        );
+        assert_eq!(
+            auto.enable_with_scope().unwrap(),
+            WindowsEnableMode::CurrentUser
+        );
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+
+        if std::env::var_os("AUTO_LAUNCH_TEST_UNELEVATED").is_some() {
+            let auto = AutoLaunch::new(
+                app_name,
+                &app_path,
+                WindowsEnableMode::Dynamic,
+                &["--minimized"],
+            );
+            // fall back to the current user when the admin key is denied
+            assert_eq!(
+                auto.enable_with_scope().unwrap(),
+                WindowsEnableMode::CurrentUser
+            );
+            assert!(CURRENT_USER
+                .open(RUN_REGKEY)
+                .unwrap()
+                .get_string(app_name)
+                .is_ok());
+            auto.disable().unwrap();
+            assert!(!auto.is_enabled().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_windows() {
+        let app_name = "AutoLaunchTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let args = &["--minimized"];
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(app_name, app_path, WindowsEnableMode::Dynamic, args);
+
+        assert_eq!(auto.get_app_name(), app_name);
+
+        auto.enable().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+
+        if get_task_manager_override_subkey().is_some() {
+            // windows can enable after disabled by task manager
+            auto.enable().unwrap();
+            assert!(auto.is_enabled().unwrap());
+            set_task_manager_override_value(app_name, TASK_MANAGER_OVERRIDE_TEST_DATA[0].1);
+            set_admin_task_manager_override_value(app_name, TASK_MANAGER_OVERRIDE_TEST_DATA[0].1)
+                .unwrap_or(());
+
+            assert!(!auto.is_enabled().unwrap());
+
+            auto.enable().unwrap();
+            assert!(auto.is_enabled().unwrap());
+
+            // test windows task manager overrides
+            delete_task_manager_override_value(app_name).ok(); // Ensure previous test runs are cleaned up
+
+            assert_eq!(auto.get_app_name(), app_name);
+            auto.enable().unwrap();
+            assert!(auto.is_enabled().unwrap());
+
+            for (expected_enabled, value) in TASK_MANAGER_OVERRIDE_TEST_DATA {
+                set_task_manager_override_value(app_name, value);
+                set_admin_task_manager_override_value(app_name, value).unwrap_or(());
+                assert_eq!(
+                    auto.is_enabled().unwrap(),
+                    expected_enabled,
+                    "{:02X?}",
+                    value
+                );
+            }
+
+            auto.disable().unwrap();
+            assert!(!auto.is_enabled().unwrap());
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[cfg(test)]
+mod macos_unit_test {
+    use crate::unit_test::*;
+    use auto_launch::{
+        Action, AutoLaunch, AutoLaunchBuilder, AutoLaunchGroup, Backend, CalendarInterval, Change,
+        CommandRunner, EnabledStatus, EntryCustomizer, EntryInfo, Error, PlannedChange, StateDiff,
+        LAUNCHED_AT_STARTUP_ARG,
+    };
+    use std::fs;
+    use std::os::unix::process::ExitStatusExt;
+    use std::path::PathBuf;
+    use std::process::{ExitStatus, Output};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_macos_new() {
+        let name_1 = "AutoLaunchTest"; // different name
+        let name_2 = "auto-launch-test"; // same name
+
+        let args = &["--minimized", "--hidden"];
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        // applescript
+        let auto1 = AutoLaunch::new(name_1, app_path, false, args, &[] as &[&str], "");
+        let auto2 = AutoLaunch::new(name_2, app_path, false, args, &[] as &[&str], "");
+        // launch agent
+        let auto3 = AutoLaunch::new(name_1, app_path, true, args, &[] as &[&str], "");
+        let auto4 = AutoLaunch::new(name_2, app_path, true, args, &[] as &[&str], "");
+
+        // app_name will be revised
+        assert_eq!(auto1.get_app_name(), name_2);
+        assert_eq!(auto2.get_app_name(), name_2);
+        // there is not limited when using launch agent
+        assert_eq!(auto3.get_app_name(), name_1);
+        assert_eq!(auto4.get_app_name(), name_2);
+    }
+
+    #[test]
+    fn test_macos_plan() {
+        let app_name = "AutoLaunchPlanTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let args = &["--minimized"];
+        let app_path = app_path.as_str();
+
+        // use launch agent
+        let auto = AutoLaunch::new(app_name, app_path, true, args, &[] as &[&str], "");
+        assert_eq!(auto.plan(Action::Disable).unwrap(), vec![]);
+
+        let plan = auto.plan(Action::Enable).unwrap();
+        let (path, content) = match &plan[..] {
+            [PlannedChange::WriteFile { path, content }] => (path.clone(), content.clone()),
+            _ => panic!("unexpected plan: {:?}", plan),
+        };
+        assert!(!path.exists());
+
+        auto.enable().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(
+            auto.plan(Action::Disable).unwrap(),
+            vec![PlannedChange::RemoveFile { path: path.clone() }]
+        );
+        auto.disable().unwrap();
+        assert!(!path.exists());
+
+        // use applescript
+        let auto = AutoLaunch::new(app_name, app_path, false, args, &[] as &[&str], "");
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::RunAppleScript { script }] => {
+                assert!(script.contains("make login item"));
+                assert!(script.contains(app_path));
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+    }
+
+    #[test]
+    fn test_macos_new_with_all_params() {
+        let app_path = get_test_bin("auto-launch-test");
+        let bundle_identifiers = &["com.example.auto-launch-test"];
+        let agent_extra_config = "<key>KeepAlive</key><true/>";
+        let auto = AutoLaunch::new(
+            "AutoLaunchNewTest",
+            &app_path,
+            true,
+            &["--minimized"],
+            bundle_identifiers,
+            agent_extra_config,
+        );
+        assert_eq!(auto.get_bundle_identifiers(), bundle_identifiers);
+        assert_eq!(auto.get_agent_extra_config(), agent_extra_config);
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => {
+                assert!(content.contains("<key>KeepAlive</key><true/>"))
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+
+        // the same as the builder
+        let built = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchNewTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_args(&["--minimized"])
+            .set_bundle_identifiers(bundle_identifiers)
+            .set_agent_extra_config(agent_extra_config)
+            .build()
+            .unwrap();
+        assert_eq!(built, auto);
+
+        auto.enable().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_macos_bundle_identifiers() {
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("Finder")
+            .set_app_path("")
+            .set_use_launch_agent(true)
+            .set_bundle_identifiers(&["com.example.not-installed", "com.apple.finder"])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            auto.get_bundle_identifiers(),
+            &["com.example.not-installed", "com.apple.finder"]
+        );
+        // the Spotlight index may be unavailable on CI
+        if let Some((id, path)) = auto.resolve_bundle_identifier() {
+            assert_eq!(id, "com.apple.finder");
+            assert!(path.ends_with("Finder.app"));
+        }
+
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("NotInstalled")
+            .set_app_path("")
+            .set_use_launch_agent(true)
+            .set_bundle_identifiers(&["com.example.not-installed"])
+            .build()
+            .unwrap();
+        assert_eq!(auto.resolve_bundle_identifier(), None);
+        assert!(auto.enable().is_err());
+    }
+
+    #[test]
+    fn test_macos_agent_extra_config() {
+        let app_name = "AutoLaunchExtraConfigTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+        let build = |config: &str| {
+            AutoLaunchBuilder::new()
+                .set_app_name(app_name)
+                .set_app_path(app_path)
+                .set_use_launch_agent(true)
+                .set_agent_extra_config(config)
+                .build()
+                .unwrap()
+        };
+
+        // valid config is merged into the plist
+        let auto = build(
+            "<key>LimitLoadToSessionType</key><string>Aqua</string>\n\
+            <key>KeepAlive</key><dict><key>SuccessfulExit</key><false/></dict>",
+        );
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => {
+                assert!(content.contains("<key>LimitLoadToSessionType</key><string>Aqua</string>"));
+                assert!(content.contains("<key>SuccessfulExit</key><false/>"));
+                assert!(content.ends_with("</dict>\n</plist>"));
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+        auto.enable().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+
+        // malformed config
+        for config in [
+            "<key>KeepAlive</key><true/",
+            "<key>KeepAlive</key>",
+            "<key>KeepAlive</key><string>a & b</string>",
+            "<key>KeepAlive</key><dict></array>",
+            "KeepAlive <true/>",
+        ] {
+            let err = build(config).enable().unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidInput(_)),
+                "{}: {:?}",
+                config,
+                err
+            );
+        }
+
+        // duplicated keys
+        for config in [
+            "<key>Label</key><string>other</string>",
+            "<key>KeepAlive</key><true/><key>KeepAlive</key><false/>",
+        ] {
+            let err = build(config).enable().unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidInput(_)),
+                "{}: {:?}",
+                config,
+                err
+            );
+        }
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_macos_list_entries() {
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto1 = AutoLaunch::new(
+            "AutoLaunchListTest1",
+            app_path,
+            true,
+            &["--one"],
+            &[] as &[&str],
+            "",
+        );
+        let auto2 = AutoLaunch::new(
+            "AutoLaunchListTest2",
+            app_path,
+            true,
+            &["--two"],
+            &[] as &[&str],
+            "",
+        );
+        auto1.enable().unwrap();
+        auto2.enable().unwrap();
+
+        let entries = AutoLaunch::list_entries().unwrap();
+        let find = |name: &str| entries.iter().find(|e| e.name == name).cloned();
+        assert_eq!(
+            find("AutoLaunchListTest1").unwrap().command,
+            format!("{} --one", app_path)
+        );
+        assert_eq!(
+            find("AutoLaunchListTest2").unwrap().command,
+            format!("{} --two", app_path)
+        );
+
+        auto1.disable().unwrap();
+        auto2.disable().unwrap();
+        let entries = AutoLaunch::list_entries().unwrap();
+        assert!(entries
+            .iter()
+            .all(|e| !e.name.starts_with("AutoLaunchListTest")));
+    }
+
+    #[test]
+    fn test_macos_list_entries_with_runner() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchListRunnerTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .build()
+            .unwrap();
+        auto.enable().unwrap();
+        let has_agent = |entries: &[EntryInfo]| {
+            entries
+                .iter()
+                .any(|e| e.name == "AutoLaunchListRunnerTest" && e.command == app_path)
+        };
+
+        // the name and path of each login item are kept together
+        let runner = FakeRunner {
+            stdout: "A, B\t/Applications/A, B.app\nC\t/Applications/C.app\n".into(),
+            ..Default::default()
+        };
+        let entries = AutoLaunch::list_entries_with_runner(&runner).unwrap();
+        assert!(has_agent(&entries));
+        let login_items = [
+            EntryInfo {
+                name: "A, B".into(),
+                command: "/Applications/A, B.app".into(),
+            },
+            EntryInfo {
+                name: "C".into(),
+                command: "/Applications/C.app".into(),
+            },
+        ];
+        assert!(entries.ends_with(&login_items), "{:?}", entries);
+        assert_eq!(runner.calls.lock().unwrap()[0][0], "osascript");
+
+        // the Launch Agents are still listed without the login items
+        let denied = FakeRunner {
+            stderr: "execution error: Not authorized (-1743)".into(),
+            exit_code: 1,
+            ..Default::default()
+        };
+        let missing = FakeRunner {
+            missing: true,
+            ..Default::default()
+        };
+        for runner in [denied, missing] {
+            let entries = AutoLaunch::list_entries_with_runner(&runner).unwrap();
+            assert!(has_agent(&entries));
+        }
+
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_macos_normalize_identifier() {
+        let app_name = "My/App 2.0";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(
+            app_name,
+            app_path,
+            true,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+        let (path, content) = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { path, content }] => (path.clone(), content.clone()),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        assert_eq!(path.file_name().unwrap(), "My_App_2.0.plist");
+        // the label is kept
+        assert!(content.contains("<string>My/App 2.0</string>"));
+
+        auto.enable().unwrap();
+        assert!(path.exists());
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_macos_args_order() {
+        let app_path = get_test_bin("auto-launch-test");
+        let args = ["--minimized", "--hidden", "--port=8080"];
+        let auto = AutoLaunch::new(
+            "AutoLaunchArgsOrderTest",
+            &app_path,
+            true,
+            &args,
+            &[] as &[&str],
+            "",
+        );
+
+        // the executable path first, then every arg in order
+        let expected = format!(
+            "<array><string>{}</string>\
+            <string>--minimized</string>\
+            <string>--hidden</string>\
+            <string>--port=8080</string></array>",
+            app_path
+        );
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }, ..] => {
+                assert!(content.contains(&expected), "{}", content)
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+    }
+
+    #[test]
+    fn test_macos_args_escaping() {
+        let app_path = get_test_bin("auto-launch-test");
+        let args = ["--title=My App", "a & b", "<tag>"];
+        let auto = AutoLaunch::new(
+            "AutoLaunchEscapingTest",
+            &app_path,
+            true,
+            &args,
+            &[] as &[&str],
+            "",
+        );
+
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }, ..] => {
+                assert!(content.contains("<string>--title=My App</string>"));
+                assert!(content.contains("<string>a &amp; b</string>"));
+                assert!(content.contains("<string>&lt;tag&gt;</string>"));
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+
+        auto.enable().unwrap();
+        let entries = AutoLaunch::list_entries().unwrap();
+        let entry = entries
+            .iter()
+            .find(|e| e.name == "AutoLaunchEscapingTest")
+            .unwrap();
+        assert!(entry.command.ends_with(" --title=My App a & b <tag>"));
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_macos_name_correction() {
+        let app_path = get_test_bin("auto-launch-test");
+        let build = |name: &str, allow: Option<bool>| {
+            let mut builder = AutoLaunchBuilder::new();
+            builder.set_app_name(name).set_app_path(&app_path);
+            if let Some(allow) = allow {
+                builder.set_allow_name_correction(allow);
+            }
+            builder.build()
+        };
+
+        // corrected by default
+        let auto = build("AutoLaunchNameTest", None).unwrap();
+        assert_eq!(auto.get_app_name(), "auto-launch-test");
+        let auto = build("AutoLaunchNameTest", Some(true)).unwrap();
+        assert_eq!(auto.get_app_name(), "auto-launch-test");
+
+        let err = build("AutoLaunchNameTest", Some(false)).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput(_)), "{:?}", err);
+        let auto = build("auto-launch-test", Some(false)).unwrap();
+        assert_eq!(auto.get_app_name(), "auto-launch-test");
+    }
+
+    #[test]
+    fn test_macos_diff() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            "AutoLaunchDiffTest",
+            &app_path,
+            true,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+        auto.disable().unwrap();
+        assert_eq!(auto.diff().unwrap(), StateDiff::Missing);
+
+        auto.enable().unwrap();
+        assert_eq!(auto.diff().unwrap(), StateDiff::Matches);
+
+        // the plist written with the outdated args
+        let outdated = AutoLaunch::new(
+            "AutoLaunchDiffTest",
+            &app_path,
+            true,
+            &["--hidden"],
+            &[] as &[&str],
+            "",
+        );
+        outdated.enable().unwrap();
+        assert_eq!(
+            auto.diff().unwrap(),
+            StateDiff::Differs {
+                actual: format!("{} --hidden", app_path)
+            }
+        );
+
+        auto.disable().unwrap();
+        assert_eq!(auto.diff().unwrap(), StateDiff::Missing);
+    }
+
+    #[test]
+    fn test_macos_active_backend() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchBackendTest")
+            .set_app_path(&app_path);
+        assert_eq!(
+            builder.build().unwrap().active_backend(),
+            Backend::AppleScript
+        );
+        builder.set_use_launch_agent(true);
+        assert_eq!(
+            builder.build().unwrap().active_backend(),
+            Backend::LaunchAgent
+        );
+    }
+
+    /// The `EntryCustomizer` which inserts the entries at the end of the plist top-level dict
+    #[derive(Debug)]
+    struct InsertEntries(&'static str);
+
+    impl EntryCustomizer for InsertEntries {
+        fn customize(&self, content: String) -> String {
+            let end = content.rfind("</dict>").unwrap();
+            format!("{}{}\n  {}", &content[..end], self.0, &content[end..])
+        }
+    }
+
+    #[test]
+    fn test_macos_entry_customizer() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchCustomizerTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_entry_customizer(Arc::new(InsertEntries(
+                "<key>OrgCompliance</key>\n  <string>approved</string>",
+            )));
+        let auto = builder.build().unwrap();
+        auto.disable().unwrap();
+
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        let content = auto.export_entry().unwrap();
+        assert!(content.contains("<key>OrgCompliance</key>\n  <string>approved</string>"));
+        assert!(content.contains("<key>Label</key>"));
+        assert_eq!(auto.enable().unwrap(), Change::Unchanged);
+        auto.disable().unwrap();
+
+        // the result which doesn't parse is rejected before written
+        let auto = builder
+            .set_entry_customizer(Arc::new(InsertEntries("<key>Unclosed</key><string>")))
+            .build()
+            .unwrap();
+        assert!(matches!(auto.enable(), Err(Error::InvalidInput(_))));
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_macos_validate_plist_unescaped() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchValidatePlistTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_entry_customizer(Arc::new(InsertEntries(
+                "<key>Note</key>\n  <string>a & b</string>",
+            )))
+            .build()
+            .unwrap();
+        auto.disable().unwrap();
+
+        // the unescaped `&` is rejected before written
+        match auto.enable() {
+            Err(Error::InvalidInput(message)) => assert!(message.contains("&"), "{}", message),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert!(auto.export_entry().is_err());
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_macos_export_entry() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            "AutoLaunchExportTest",
+            &app_path,
+            true,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+        auto.disable().unwrap();
+        assert!(auto.export_entry().is_err());
+
+        auto.enable().unwrap();
+        let export = auto.export_entry().unwrap();
+        assert!(export.contains("<key>ProgramArguments</key>"));
+        assert!(export.contains(&format!("<string>{}</string>", app_path)));
+        assert!(export.contains("<string>--minimized</string>"));
+
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_macos_enable_once() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            "AutoLaunchOnceTest",
+            &app_path,
+            true,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+
+        match &auto.plan(Action::EnableOnce).unwrap()[..] {
+            [PlannedChange::WriteFile { path, content }, ..] => {
+                let script = format!(
+                    "<string>rm -f {}; exec {} --minimized</string>",
+                    path.display(),
+                    app_path
+                );
+                assert!(content.contains("<string>/bin/sh</string><string>-c</string>"));
+                assert!(content.contains(&script));
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+
+        auto.enable_once().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+
+        // the login item can't remove itself
+        let auto = AutoLaunch::new(
+            "auto-launch-test",
+            &app_path,
+            false,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+        assert!(auto.plan(Action::EnableOnce).unwrap().is_empty());
+        assert!(matches!(auto.enable_once(), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_macos_run_at_load() {
+        let app_path = get_test_bin("auto-launch-test");
+        let build = |run_at_load: Option<bool>| {
+            let mut builder = AutoLaunchBuilder::new();
+            builder
+                .set_app_name("AutoLaunchRunAtLoadTest")
+                .set_app_path(&app_path)
+                .set_use_launch_agent(true);
+            if let Some(run_at_load) = run_at_load {
+                builder.set_run_at_load(run_at_load);
+            }
+            builder.build().unwrap()
+        };
+        let get_plist = |auto: &AutoLaunch| match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }, ..] => content.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+
+        let auto = build(None);
+        assert!(auto.get_run_at_load());
+        assert!(get_plist(&auto).contains("<key>RunAtLoad</key>\n  <true/>"));
+
+        let auto = build(Some(false));
+        assert!(!auto.get_run_at_load());
+        assert!(get_plist(&auto).contains("<key>RunAtLoad</key>\n  <false/>"));
+    }
+
+    #[test]
+    fn test_macos_start_interval() {
+        let app_path = get_test_bin("auto-launch-test");
+        let get_plist = |auto: &AutoLaunch| match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }, ..] => content.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchIntervalTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true);
+
+        let auto = builder.clone().set_start_interval(3600).build().unwrap();
+        assert_eq!(auto.get_start_interval(), Some(3600));
+        assert!(get_plist(&auto).contains("<key>StartInterval</key>\n  <integer>3600</integer>"));
+        auto.enable().unwrap();
+        auto.disable().unwrap();
+
+        let calendar = CalendarInterval {
+            minute: Some(30),
+            hour: Some(9),
+            weekday: Some(1),
+            ..Default::default()
+        };
+        let auto = builder
+            .clone()
+            .set_start_calendar_interval(calendar.clone())
+            .build()
+            .unwrap();
+        assert_eq!(auto.get_start_calendar_interval(), Some(&calendar));
+        assert!(get_plist(&auto).contains(
+            "<key>StartCalendarInterval</key>\n  <dict>\
+            <key>Minute</key><integer>30</integer>\
+            <key>Hour</key><integer>9</integer>\
+            <key>Weekday</key><integer>1</integer>\
+            </dict>"
+        ));
+        auto.enable().unwrap();
+        auto.disable().unwrap();
+
+        // mutually exclusive
+        let auto = builder
+            .clone()
+            .set_start_interval(3600)
+            .set_start_calendar_interval(calendar)
+            .build()
+            .unwrap();
+        assert!(matches!(auto.enable(), Err(Error::InvalidInput(_))));
+
+        // out of range
+        for calendar in [
+            CalendarInterval {
+                minute: Some(60),
+                ..Default::default()
+            },
+            CalendarInterval {
+                hour: Some(24),
+                ..Default::default()
+            },
+            CalendarInterval {
+                day: Some(0),
+                ..Default::default()
+            },
+            CalendarInterval {
+                month: Some(13),
+                ..Default::default()
+            },
+        ] {
+            let auto = builder
+                .clone()
+                .set_start_calendar_interval(calendar)
+                .build()
+                .unwrap();
+            assert!(matches!(auto.enable(), Err(Error::InvalidInput(_))));
+        }
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_macos_watch_paths() {
+        let app_path = get_test_bin("auto-launch-test");
+        let get_plist = |auto: &AutoLaunch| match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }, ..] => content.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchWatchPathsTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true);
+
+        // omitted when empty
+        let auto = builder.clone().set_watch_paths(vec![]).build().unwrap();
+        assert!(!get_plist(&auto).contains("<key>WatchPaths</key>"));
+
+        let paths = vec![
+            PathBuf::from("/tmp/auto-launch/config"),
+            PathBuf::from("/tmp/auto-launch/a & b"),
+        ];
+        let auto = builder.set_watch_paths(paths.clone()).build().unwrap();
+        assert_eq!(auto.get_watch_paths(), paths);
+        assert!(get_plist(&auto).contains(
+            "<key>WatchPaths</key>\n  <array>\
+            <string>/tmp/auto-launch/config</string>\
+            <string>/tmp/auto-launch/a &amp; b</string>\
+            </array>"
+        ));
+        auto.enable().unwrap();
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_macos_purge_all() {
+        let app_path = get_test_bin("auto-launch-test");
+        let agent = AutoLaunch::new(
+            "AutoLaunchPurgeTest",
+            &app_path,
+            true,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+        let login_item = AutoLaunch::new(
+            "auto-launch-test",
+            &app_path,
+            false,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+        agent.enable().unwrap();
+        login_item.enable().unwrap();
+
+        agent.purge_all().unwrap();
+        assert!(!agent.is_enabled().unwrap());
+        assert!(!login_item.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_macos_session_type() {
+        let app_path = get_test_bin("auto-launch-test");
+        let get_plist = |auto: &AutoLaunch| match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }, ..] => content.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchSessionTypeTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true);
+
+        // omitted when unset
+        let auto = builder.build().unwrap();
+        assert_eq!(auto.get_session_type(), None);
+        assert!(!get_plist(&auto).contains("LimitLoadToSessionType"));
+
+        let auto = builder.clone().set_session_type("Aqua").build().unwrap();
+        assert_eq!(auto.get_session_type(), Some("Aqua"));
+        assert!(
+            get_plist(&auto).contains("<key>LimitLoadToSessionType</key>\n  <string>Aqua</string>")
+        );
+        auto.enable().unwrap();
+        auto.disable().unwrap();
+
+        let auto = builder.set_session_type("aqua").build().unwrap();
+        assert!(matches!(auto.enable(), Err(Error::InvalidInput(_))));
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_macos_unload_on_disable() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchUnloadOnDisableTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true);
+
+        // only removes the file by default
+        let auto = builder.build().unwrap();
+        auto.enable().unwrap();
+        assert!(matches!(
+            &auto.plan(Action::Disable).unwrap()[..],
+            [PlannedChange::RemoveFile { .. }]
+        ));
+
+        let auto = builder.set_unload_on_disable(true).build().unwrap();
+        match &auto.plan(Action::Disable).unwrap()[..] {
+            [PlannedChange::RunCommand { program, args }, PlannedChange::RemoveFile { path }] => {
+                assert_eq!(program, "launchctl");
+                assert_eq!(args[0], "unload");
+                assert_eq!(args[1], path.to_string_lossy());
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+        assert_eq!(auto.disable().unwrap(), Change::Changed);
+        assert!(!auto.is_enabled().unwrap());
+
+        // nothing to unload
+        assert!(auto.plan(Action::Disable).unwrap().is_empty());
+        assert_eq!(auto.disable().unwrap(), Change::Unchanged);
+    }
+
+    #[test]
+    fn test_macos_reload_on_change() {
+        let app_path = get_test_bin("auto-launch-test");
+        let runner = Arc::new(FakeRunner::default());
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchReloadOnChangeTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_reload_on_change(true)
+            .set_command_runner(runner.clone());
+        let auto = builder.build().unwrap();
+        auto.disable().unwrap();
+
+        // nothing to reload for the new plist, or the same one
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        assert_eq!(auto.enable().unwrap(), Change::Unchanged);
+        assert!(runner.calls.lock().unwrap().is_empty());
+
+        let path = match &auto.plan(Action::Disable).unwrap()[..] {
+            [PlannedChange::RemoveFile { path }] => path.to_string_lossy().into_owned(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let changed = builder.set_args(&["--hidden"]).build().unwrap();
+        assert_eq!(changed.enable().unwrap(), Change::Changed);
+        assert_eq!(
+            *runner.calls.lock().unwrap(),
+            [
+                vec![
+                    "launchctl".to_string(),
+                    "list".into(),
+                    "AutoLaunchReloadOnChangeTest".into()
+                ],
+                vec!["launchctl".to_string(), "unload".into(), path.clone()],
+                vec!["launchctl".to_string(), "load".into(), path],
+            ]
+        );
+
+        // not loaded in the current session
+        let runner = Arc::new(FakeRunner {
+            exit_code: 113,
+            ..Default::default()
+        });
+        let auto = builder
+            .set_args(&["--minimized"])
+            .set_command_runner(runner.clone())
+            .build()
+            .unwrap();
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        assert_eq!(runner.calls.lock().unwrap().len(), 1);
+        assert_eq!(runner.calls.lock().unwrap()[0][1], "list");
+
+        // not reloaded by default
+        let runner = Arc::new(FakeRunner::default());
+        let auto = builder
+            .set_reload_on_change(false)
+            .set_args(&["--hidden"])
+            .set_command_runner(runner.clone())
+            .build()
+            .unwrap();
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        assert!(runner.calls.lock().unwrap().is_empty());
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_macos_verify_loaded() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchVerifyLoadedTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true);
+
+        // only the plist is checked by default
+        let runner = Arc::new(FakeRunner {
+            exit_code: 113,
+            ..Default::default()
+        });
+        let auto = builder.set_command_runner(runner.clone()).build().unwrap();
+        auto.enable().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        assert!(runner.calls.lock().unwrap().is_empty());
+
+        // the plist exists but the agent is not loaded
+        let auto = builder.set_verify_loaded(true).build().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+        assert_eq!(
+            *runner.calls.lock().unwrap(),
+            [vec![
+                "launchctl".to_string(),
+                "list".into(),
+                "AutoLaunchVerifyLoadedTest".into()
+            ]]
+        );
+
+        // loaded
+        let runner = Arc::new(FakeRunner {
+            stdout: "{\n\t\"Label\" = \"AutoLaunchVerifyLoadedTest\";\n};\n".into(),
+            ..Default::default()
+        });
+        let auto = builder.set_command_runner(runner.clone()).build().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        assert_eq!(runner.calls.lock().unwrap().len(), 1);
+
+        // launchctl is not run without the plist
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+        assert_eq!(runner.calls.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_macos_plist_format() {
+        let app_path = get_test_bin("auto-launch-test");
+        let canonical = fs::canonicalize(&app_path).unwrap();
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchPlistFormatTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_args(&["--minimized"]);
+
+        // the XML form is stable byte for byte
+        let auto = builder.build().unwrap();
+        let expected = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+  <dict>
+  <key>Label</key>
+  <string>AutoLaunchPlistFormatTest</string>
+  <key>ProgramArguments</key>
+  <array><string>{}</string><string>--minimized</string></array>
+  <key>RunAtLoad</key>
+  <true/>
+  </dict>
+</plist>"#,
+            canonical.display()
+        );
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => assert_eq!(*content, expected),
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+
+        // converted via `plutil` after written
+        let runner = Arc::new(FakeRunner::default());
+        let auto = builder
+            .set_binary_plist(true)
+            .set_command_runner(runner.clone())
+            .build()
+            .unwrap();
+        let path = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { path, .. }, PlannedChange::RunCommand { program, args }] => {
+                assert_eq!(program, "plutil");
+                assert_eq!(args[..2], ["-convert", "binary1"]);
+                path.to_string_lossy().into_owned()
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        auto.enable().unwrap();
+        assert_eq!(
+            *runner.calls.lock().unwrap(),
+            [vec![
+                "plutil".to_string(),
+                "-convert".into(),
+                "binary1".into(),
+                path
+            ]]
+        );
+        auto.disable().unwrap();
+    }
+
+    /// The fake `plutil`, which marks the file as binary, and reads it back
+    /// with the tab indents like the real one
+    #[derive(Debug, Default)]
+    struct FakePlutil {
+        exit_code: i32,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl CommandRunner for FakePlutil {
+        fn run(&self, program: &str, args: &[&str]) -> std::io::Result<Output> {
+            let mut call = vec![program.to_string()];
+            call.extend(args.iter().map(|x| x.to_string()));
+            self.calls.lock().unwrap().push(call);
+            let data = fs::read(args.last().unwrap())?;
+            let mut stdout = vec![];
+            match args[1] {
+                "binary1" => fs::write(args.last().unwrap(), [b"bplist00", &data[..]].concat())?,
+                _ => {
+                    let data = data.strip_prefix(b"bplist00").unwrap_or(&data);
+                    stdout = String::from_utf8_lossy(data).replace("\n  ", "\n\t").into();
+                }
+            }
+            Ok(Output {
+                status: ExitStatus::from_raw(self.exit_code << 8),
+                stdout,
+                stderr: vec![],
+            })
+        }
+    }
+
+    /// The binary plist is compared in the same XML layout as it's read back
+    #[test]
+    fn test_macos_binary_plist_unchanged() {
+        let app_path = get_test_bin("auto-launch-test");
+        let runner = Arc::new(FakePlutil::default());
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchBinaryPlistTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_binary_plist(true)
+            .set_reload_on_change(true)
+            .set_command_runner(runner.clone());
+        let auto = builder.build().unwrap();
+        auto.disable().unwrap();
+
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        runner.calls.lock().unwrap().clear();
+        assert_eq!(auto.enable().unwrap(), Change::Unchanged);
+        let calls = runner.calls.lock().unwrap().clone();
+        assert!(
+            calls
+                .iter()
+                .all(|x| x[..3] == ["plutil", "-convert", "xml1"]),
+            "{:?}",
+            calls
+        );
+
+        // the failure of `plutil` is not taken as the empty plist
+        let failing = Arc::new(FakePlutil {
+            exit_code: 1,
+            ..Default::default()
+        });
+        let auto = builder.set_command_runner(failing).build().unwrap();
+        assert!(auto.diff().is_err());
+
+        auto.disable().unwrap();
+    }
+
+    /// The fake `CommandRunner` which records the invocations and returns the canned output
+    /// and exit code, or fails as the program is not installed if `missing`
+    #[derive(Debug, Default)]
+    struct FakeRunner {
+        stdout: String,
+        stderr: String,
+        exit_code: i32,
+        missing: bool,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn run(&self, program: &str, args: &[&str]) -> std::io::Result<Output> {
+            let mut call = vec![program.to_string()];
+            call.extend(args.iter().map(|x| x.to_string()));
+            self.calls.lock().unwrap().push(call);
+            if self.missing {
+                return Err(std::io::ErrorKind::NotFound.into());
+            }
+            Ok(Output {
+                status: ExitStatus::from_raw(self.exit_code << 8),
+                stdout: self.stdout.clone().into_bytes(),
+                stderr: self.stderr.clone().into_bytes(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_macos_command_runner_apple_script() {
+        let app_path = get_test_bin("auto-launch-test");
+        let runner = Arc::new(FakeRunner::default());
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("auto-launch-test")
+            .set_app_path(&app_path)
+            .set_command_runner(runner.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        let calls = runner.calls.lock().unwrap().clone();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            calls[0],
+            [
+                "osascript",
+                "-e",
+                "tell application \"System Events\" to \
+                get the path of every login item whose name is \"auto-launch-test\""
+            ]
+        );
+        assert_eq!(calls[1][..2], ["osascript", "-e"]);
+        assert!(calls[1][2].contains("make login item at end with properties"));
+
+        let runner = Arc::new(FakeRunner {
+            stdout: "Other, auto-launch-test".into(),
+            ..Default::default()
+        });
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("auto-launch-test")
+            .set_app_path(&app_path)
+            .set_command_runner(runner.clone())
+            .build()
+            .unwrap();
+        assert!(auto.is_enabled().unwrap());
+        assert_eq!(runner.calls.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_macos_osascript_unavailable() {
+        let app_path = get_test_bin("auto-launch-test");
+        let runner = Arc::new(FakeRunner {
+            missing: true,
+            ..Default::default()
+        });
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("auto-launch-test")
+            .set_app_path(&app_path)
+            .set_command_runner(runner)
+            .build()
+            .unwrap();
+
+        for result in [auto.enable(), auto.disable()] {
+            match result {
+                Err(Error::Backend(message)) => {
+                    assert!(message.contains("osascript unavailable"));
+                    assert!(message.contains("Launch Agent"));
+                }
+                result => panic!("unexpected result: {:?}", result),
+            }
+        }
+        assert!(matches!(auto.is_enabled(), Err(Error::Backend(_))));
+    }
+
+    #[test]
+    fn test_macos_apple_events_not_authorized() {
+        let app_path = get_test_bin("auto-launch-test");
+        let runner = Arc::new(FakeRunner {
+            stderr: "execution error: Not authorized to send Apple events \
+                to System Events. (-1743)\n"
+                .into(),
+            exit_code: 1,
+            ..Default::default()
+        });
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("auto-launch-test")
+            .set_app_path(&app_path)
+            .set_command_runner(runner)
+            .build()
+            .unwrap();
+
+        for result in [auto.enable(), auto.disable()] {
+            match result {
+                Err(Error::PermissionDenied(message)) => {
+                    assert!(message.contains("System Settings > Privacy & Security"));
+                    assert!(message.contains("(-1743)"));
+                }
+                result => panic!("unexpected result: {:?}", result),
+            }
+        }
+        assert!(matches!(auto.is_enabled(), Err(Error::PermissionDenied(_))));
+
+        // the other failures are kept as is
+        let runner = Arc::new(FakeRunner {
+            stderr: "execution error: Can't get login item. (-1728)\n".into(),
+            exit_code: 1,
+            ..Default::default()
+        });
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("auto-launch-test")
+            .set_app_path(&app_path)
+            .set_command_runner(runner)
+            .build()
+            .unwrap();
+        assert!(matches!(auto.enable(), Err(Error::AppleScriptFailed(1))));
+    }
+
+    #[test]
+    fn test_macos_group() {
+        let app_path = get_test_bin("auto-launch-test");
+        let build = |name: &str, use_launch_agent: bool| {
+            AutoLaunchBuilder::new()
+                .set_app_name(name)
+                .set_app_path(&app_path)
+                .set_use_launch_agent(use_launch_agent)
+                .set_command_runner(Arc::new(FakeRunner {
+                    missing: true,
+                    ..Default::default()
+                }))
+                .build()
+        };
+        // the login item fails, as osascript is not installed
+        let group = AutoLaunchGroup::new(vec![
+            build("AutoLaunchGroupTestA", true).unwrap(),
+            build("auto-launch-test", false).unwrap(),
+            build("AutoLaunchGroupTestC", true).unwrap(),
+        ]);
+
+        let results = group.enable_all();
+        assert!(matches!(results[0], Ok(Change::Changed)));
+        assert!(matches!(results[1], Err(Error::Backend(_))));
+        assert!(matches!(results[2], Ok(Change::Changed)));
+
+        let statuses = group.statuses();
+        assert!(matches!(statuses[0], Ok(EnabledStatus::Enabled)));
+        assert!(statuses[1].is_err());
+        assert!(matches!(statuses[2], Ok(EnabledStatus::Enabled)));
+
+        let results = group.disable_all();
+        assert!(matches!(results[0], Ok(Change::Changed)));
+        assert!(results[1].is_err());
+        assert!(matches!(results[2], Ok(Change::Changed)));
+    }
+
+    /// The fake `CommandRunner` which keeps the login items `(name, path)` in memory,
+    /// for the AppleScript commands of the login items
+    #[derive(Debug, Default)]
+    struct FakeLoginItems {
+        items: Mutex<Vec<(String, String)>>,
+    }
+
+    impl CommandRunner for FakeLoginItems {
+        fn run(&self, _program: &str, args: &[&str]) -> std::io::Result<Output> {
+            let script = args[1].trim_start_matches("tell application \"System Events\" to ");
+            let quoted = |s: &str| s.split('"').nth(1).unwrap_or_default().to_string();
+            let join = |x: Vec<String>| x.join(", ");
+            let mut items = self.items.lock().unwrap();
+            let stdout = if let Some(props) =
+                script.strip_prefix("make login item at end with properties ")
+            {
+                let name = quoted(props.split("name:").nth(1).unwrap());
+                let path = quoted(props.split("path:").nth(1).unwrap());
+                items.push((name, path));
+                String::new()
+            } else if let Some(name) =
+                script.strip_prefix("get the path of every login item whose name is ")
+            {
+                let name = quoted(name);
+                join(
+                    items
+                        .iter()
+                        .filter(|x| x.0 == name)
+                        .map(|x| x.1.clone())
+                        .collect(),
+                )
+            } else if let Some(name) = script.strip_prefix("delete login item ") {
+                let name = quoted(name);
+                if let Some(i) = items.iter().position(|x| x.0 == name) {
+                    items.remove(i);
+                }
+                String::new()
+            } else if script == "get the name of every login item" {
+                join(items.iter().map(|x| x.0.clone()).collect())
+            } else {
+                panic!("unexpected script: {}", script)
+            };
+            Ok(Output {
+                status: ExitStatus::from_raw(0),
+                stdout: stdout.into_bytes(),
+                stderr: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn test_macos_get_use_launch_agent() {
+        let app_path = get_test_bin("auto-launch-test");
+        let no_args = &[] as &[&str];
+        for use_launch_agent in [true, false] {
+            let auto = AutoLaunch::new(
+                "auto-launch-test",
+                &app_path,
+                use_launch_agent,
+                no_args,
+                no_args,
+                "",
+            );
+            assert_eq!(auto.get_use_launch_agent(), use_launch_agent);
+
+            let auto = AutoLaunchBuilder::new()
+                .set_app_name("auto-launch-test")
+                .set_app_path(&app_path)
+                .set_use_launch_agent(use_launch_agent)
+                .build()
+                .unwrap();
+            assert_eq!(auto.get_use_launch_agent(), use_launch_agent);
+        }
+    }
+
+    #[test]
+    fn test_macos_label_conflict() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchConflictTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true);
+        let auto = builder.build().unwrap();
+        let file = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { path, .. }] => path.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+
+        // a third-party plist at the same path is not overwritten
+        let foreign =
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">\n  <dict>\n  \
+            <key>Label</key>\n  <string>com.example.other</string>\n  </dict>\n</plist>";
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, foreign).unwrap();
+        assert!(matches!(auto.enable(), Err(Error::Conflict(path)) if path == file));
+        assert!(matches!(auto.enable_once(), Err(Error::Conflict(_))));
+        assert_eq!(fs::read_to_string(&file).unwrap(), foreign);
+
+        // overwritten with force
+        let forced = builder.set_force_overwrite(true).build().unwrap();
+        assert_eq!(forced.enable().unwrap(), Change::Changed);
+        assert_ne!(fs::read_to_string(&file).unwrap(), foreign);
+
+        // our own plist is updated as usual
+        assert_eq!(auto.enable().unwrap(), Change::Unchanged);
+        auto.enable_once().unwrap();
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_macos_legacy_plist() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunch Legacy Plist Test")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true);
+        let auto = builder.build().unwrap();
+        let file = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { path, .. }] => path.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let legacy = file.with_file_name("AutoLaunch Legacy Plist Test.plist");
+        assert_ne!(file, legacy);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        auto.disable().unwrap();
+
+        // another app's plist of the same name is left alone
+        let foreign =
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">\n  <dict>\n  \
+            <key>Label</key>\n  <string>com.example.other</string>\n  </dict>\n</plist>";
+        fs::write(&legacy, foreign).unwrap();
+        assert!(!auto.is_enabled().unwrap());
+        assert_eq!(auto.entry_count().unwrap(), 0);
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        assert_eq!(fs::read_to_string(&legacy).unwrap(), foreign);
+        assert_eq!(auto.entry_count().unwrap(), 1);
+        assert_eq!(auto.disable().unwrap(), Change::Changed);
+        assert_eq!(fs::read_to_string(&legacy).unwrap(), foreign);
+
+        // ours, written by the former versions, is migrated
+        let ours = foreign.replace("com.example.other", auto.get_label());
+        fs::write(&legacy, ours).unwrap();
+        assert!(auto.is_enabled().unwrap());
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        assert!(!legacy.exists());
+        assert!(file.exists());
+
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_macos_enabled_status() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchEnabledStatusTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_args(&["--one"]);
+        let auto = builder.build().unwrap();
+        assert_eq!(auto.enabled_status().unwrap(), EnabledStatus::NotRegistered);
+
+        auto.enable().unwrap();
+        assert_eq!(auto.enabled_status().unwrap(), EnabledStatus::Enabled);
+        let other = builder.clone().set_args(&["--two"]).build().unwrap();
+        assert_eq!(
+            other.enabled_status().unwrap(),
+            EnabledStatus::RegisteredButPathMismatch
+        );
+
+        let disabled = builder
+            .set_agent_extra_config("<key>Disabled</key><true/>")
+            .build()
+            .unwrap();
+        disabled.enable().unwrap();
+        assert_eq!(
+            disabled.enabled_status().unwrap(),
+            EnabledStatus::RegisteredButDisabledByOs
+        );
+
+        // not loaded by launchd, the same as `is_enabled`
+        let not_loaded = Arc::new(FakeRunner {
+            exit_code: 113,
+            ..Default::default()
+        });
+        let unloaded = builder
+            .set_agent_extra_config("")
+            .set_verify_loaded(true)
+            .set_command_runner(not_loaded)
+            .build()
+            .unwrap();
+        unloaded.enable().unwrap();
+        assert!(!unloaded.is_enabled().unwrap());
+        assert_eq!(
+            unloaded.enabled_status().unwrap(),
+            EnabledStatus::RegisteredButDisabledByOs
+        );
+
+        // skipped by `AUTO_LAUNCH_DISABLE`, checked in a child process
+        if std::env::var_os("AUTO_LAUNCH_TEST_CHILD").is_none() {
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["macos_unit_test::test_macos_enabled_status_env", "--exact"])
+                .env("AUTO_LAUNCH_TEST_CHILD", "1")
+                .env("AUTO_LAUNCH_DISABLE", "1")
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "{}", stdout);
+            assert!(stdout.contains("1 passed"), "{}", stdout);
+        }
+
+        unloaded.disable().unwrap();
+        assert_eq!(auto.enabled_status().unwrap(), EnabledStatus::NotRegistered);
+    }
+
+    /// Run by `test_macos_enabled_status` in a child process with the agent enabled
+    #[test]
+    fn test_macos_enabled_status_env() {
+        if std::env::var_os("AUTO_LAUNCH_TEST_CHILD").is_none() {
+            return;
+        }
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchEnabledStatusTest")
+            .set_app_path(&get_test_bin("auto-launch-test"))
+            .set_use_launch_agent(true)
+            .build()
+            .unwrap();
+        assert!(auto.diff().unwrap() != StateDiff::Missing);
+        assert_eq!(auto.enabled_status().unwrap(), EnabledStatus::NotRegistered);
+    }
+
+    #[test]
+    fn test_macos_corrected_name() {
+        let app_path = get_test_bin("auto-launch-test");
+        let no_args = &[] as &[&str];
+        let auto = AutoLaunch::new("MismatchedName", &app_path, false, no_args, no_args, "");
+        assert_eq!(auto.get_app_name(), "auto-launch-test");
+        let auto = AutoLaunch::new(
+            "Foo",
+            "/Applications/Foo Bar.app/",
+            false,
+            no_args,
+            no_args,
+            "",
+        );
+        assert_eq!(auto.get_app_name(), "Foo Bar");
+
+        let runner = Arc::new(FakeLoginItems::default());
+        let build = |app_name: &str| {
+            AutoLaunchBuilder::new()
+                .set_app_name(app_name)
+                .set_app_path(&app_path)
+                .set_command_runner(runner.clone())
+                .build()
+                .unwrap()
+        };
+        let auto = build("MismatchedName");
+        assert_eq!(auto.get_app_name(), "auto-launch-test");
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        assert_eq!(runner.items.lock().unwrap()[0].0, "auto-launch-test");
+        assert!(auto.is_enabled().unwrap());
+
+        // the instance with another name for the same path manages the same login item
+        let another = build("AnotherName");
+        assert!(another.is_enabled().unwrap());
+        assert_eq!(another.enable().unwrap(), Change::Unchanged);
+
+        assert_eq!(auto.disable().unwrap(), Change::Changed);
+        assert!(runner.items.lock().unwrap().is_empty());
+        assert!(!auto.is_enabled().unwrap());
+        assert!(!another.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_macos_command_runner_launchctl() {
+        let app_path = get_test_bin("auto-launch-test");
+        let runner = Arc::new(FakeRunner::default());
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchCommandRunnerTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_unload_on_disable(true)
+            .set_command_runner(runner.clone())
+            .build()
+            .unwrap();
+
+        auto.enable().unwrap();
+        let path = match &auto.plan(Action::Disable).unwrap()[..] {
+            [.., PlannedChange::RemoveFile { path }] => path.to_string_lossy().into_owned(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        assert_eq!(auto.disable().unwrap(), Change::Changed);
+        assert_eq!(
+            *runner.calls.lock().unwrap(),
+            [vec!["launchctl".to_string(), "unload".into(), path]]
+        );
+    }
+
+    #[test]
+    fn test_macos_relative_to_exe() {
+        let app_path = fs::canonicalize(get_test_bin("auto-launch-test")).unwrap();
+        // the test bin relative to the dir of the test executable
+        let exe_dir = std::env::current_exe()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        let mut relative = PathBuf::new();
+        for _ in exe_dir.components().skip(1) {
+            relative.push("..");
+        }
+        relative.push(app_path.strip_prefix("/").unwrap());
+        let relative = relative.to_str().unwrap();
+
+        let auto = AutoLaunch::new(
+            "AutoLaunchRelativeTest",
+            relative,
+            true,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+        let expected = format!("<string>{}</string>", app_path.display());
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }, ..] => assert!(content.contains(&expected)),
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+
+        auto.enable().unwrap();
+        assert_eq!(auto.diff().unwrap(), StateDiff::Matches);
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_macos_app_bundle() {
+        let no_args = &[] as &[&str];
+        let bundle = std::env::temp_dir().join("AutoLaunchBundleTest/Foo.app");
+        fs::create_dir_all(bundle.join("Contents/MacOS")).unwrap();
+        fs::write(
+            bundle.join("Contents/Info.plist"),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <plist version=\"1.0\">\n<dict>\n\
+            <key>CFBundleExecutable</key>\n<string>foo-bin</string>\n\
+            </dict>\n</plist>\n",
+        )
+        .unwrap();
+        let bundle = fs::canonicalize(bundle).unwrap();
+        let executable = bundle.join("Contents/MacOS/foo-bin");
+        fs::copy(get_test_bin("auto-launch-test"), &executable).unwrap();
+
+        for app_path in [&bundle, &executable] {
+            let app_path = app_path.to_str().unwrap();
+            // the Launch Agent launches the executable
+            let auto = AutoLaunch::new("Foo", app_path, true, no_args, no_args, "");
+            match &auto.plan(Action::Enable).unwrap()[..] {
+                [PlannedChange::WriteFile { content, .. }] => assert!(
+                    content.contains(&format!(
+                        "<array><string>{}</string></array>",
+                        executable.display()
+                    )),
+                    "{}",
+                    content
+                ),
+                plan => panic!("unexpected plan: {:?}", plan),
+            }
+
+            // the login item is made for the bundle
+            let auto = AutoLaunch::new("Foo", app_path, false, no_args, no_args, "");
+            assert_eq!(auto.get_app_name(), "Foo");
+            match &auto.plan(Action::Enable).unwrap()[..] {
+                [PlannedChange::RunAppleScript { script }] => {
+                    assert!(script.contains(&format!("path:\"{}\"", bundle.display())))
+                }
+                plan => panic!("unexpected plan: {:?}", plan),
+            }
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_macos_async_login_item() {
+        let app_path = get_test_bin("auto-launch-test");
+        let runner = Arc::new(FakeLoginItems::default());
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("auto-launch-test")
+            .set_app_path(&app_path)
+            .set_command_runner(runner.clone())
+            .build()
+            .unwrap();
+
+        assert!(!auto.is_enabled_async().await.unwrap());
+        assert_eq!(auto.enable_async().await.unwrap(), Change::Changed);
+        assert_eq!(auto.enable_async().await.unwrap(), Change::Unchanged);
+        assert!(auto.is_enabled_async().await.unwrap());
+        assert_eq!(runner.items.lock().unwrap().len(), 1);
+        assert_eq!(auto.disable_async().await.unwrap(), Change::Changed);
+        assert!(!auto.is_enabled_async().await.unwrap());
+        assert!(runner.items.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_macos_entry_count() {
+        let no_args = &[] as &[&str];
+        // a copy of the test bin, not to count the entries of the other tests
+        let app_path = std::env::temp_dir().join("auto-launch-entry-count-test");
+        fs::copy(get_test_bin("auto-launch-test"), &app_path).unwrap();
+        let app_path = app_path.to_str().unwrap();
+
+        let auto = AutoLaunch::new(
+            "AutoLaunchEntryCountTest",
+            app_path,
+            true,
+            no_args,
+            no_args,
+            "",
+        );
+        auto.disable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 0);
+        auto.enable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 1);
+
+        // a duplicate under another name launching the same app
+        let duplicate = AutoLaunch::new(
+            "AutoLaunchEntryCountDuplicate",
+            app_path,
+            true,
+            &["--two"],
+            no_args,
+            "",
+        );
+        duplicate.enable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 2);
+        // also launched via `/bin/sh -c`
+        let wrapped = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchEntryCountWrapped")
+            .set_app_path(app_path)
+            .set_use_launch_agent(true)
+            .set_raw_args("--raw")
+            .build()
+            .unwrap();
+        wrapped.enable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 3);
+
+        // the app of the path prefixed by the app path, launched via `/bin/sh -c`
+        let helper = format!("{}-helper", app_path);
+        fs::copy(app_path, &helper).unwrap();
+        let other = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchEntryCountHelper")
+            .set_app_path(&helper)
+            .set_use_launch_agent(true)
+            .set_raw_args("--raw")
+            .build()
+            .unwrap();
+        other.enable().unwrap();
+        // the broken plist of another app is skipped
+        let broken = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { path, .. }] => {
+                path.with_file_name("auto-launch-entry-count-broken.plist")
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        fs::write(&broken, b"\xff\xfe").unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 3);
+
+        fs::remove_file(broken).unwrap();
+        other.disable().unwrap();
+        wrapped.disable().unwrap();
+        duplicate.disable().unwrap();
+        auto.disable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 0);
+
+        // the login items with the same name
+        let runner = Arc::new(FakeLoginItems::default());
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("auto-launch-entry-count-test")
+            .set_app_path(app_path)
+            .set_command_runner(runner.clone())
+            .build()
+            .unwrap();
+        auto.enable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 1);
+        let item = runner.items.lock().unwrap()[0].clone();
+        runner.items.lock().unwrap().push(item);
+        assert_eq!(auto.entry_count().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_macos_raw_args() {
+        let app_path = get_test_bin("auto-launch-test");
+        let canonical = fs::canonicalize(&app_path).unwrap();
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchRawArgsTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_raw_args(r#"--a --b="x y""#)
+            .build()
+            .unwrap();
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => assert!(content.contains(&format!(
+                "<array><string>/bin/sh</string><string>-c</string>\
+                <string>exec {} --a --b=\"x y\"</string></array>",
+                canonical.display()
+            ))),
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+    }
+
+    #[test]
+    fn test_macos_startup_sentinel() {
+        let app_path = get_test_bin("auto-launch-test");
+        let canonical = fs::canonicalize(&app_path).unwrap();
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchSentinelTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_args(&["--minimized"])
+            .set_startup_sentinel(true)
+            .build()
+            .unwrap();
+        let expected = format!(
+            "<array><string>{}</string><string>--minimized</string><string>{}</string></array>",
+            canonical.display(),
+            LAUNCHED_AT_STARTUP_ARG
+        );
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => {
+                assert!(content.contains(&expected), "{}", content)
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+    }
+
+    #[test]
+    fn test_macos_placeholders() {
+        let app_path = get_test_bin("auto-launch-test");
+        let args = &["--config=${HOME}/app.toml", "${AUTO_LAUNCH_UNSET_VAR}"];
+        let auto = AutoLaunch::new(
+            "AutoLaunchPlaceholderTest",
+            &app_path,
+            true,
+            args,
+            &[] as &[&str],
+            "",
+        );
+        let content = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }, ..] => content.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let home = std::env::var("HOME").unwrap();
+        assert!(content.contains(&format!("<string>--config={}/app.toml</string>", home)));
+        assert!(content.contains("<string>${AUTO_LAUNCH_UNSET_VAR}</string>"));
+    }
+
+    #[test]
+    fn test_macos_sandbox() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(
+            "AutoLaunchSandboxTest",
+            &app_path,
+            true,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+        if AutoLaunch::is_sandboxed() {
+            // in the child process with the container home
+            assert!(matches!(auto.enable(), Err(Error::SandboxUnsupported)));
+            assert!(matches!(auto.enable_once(), Err(Error::SandboxUnsupported)));
+            return;
+        }
+
+        // simulate the container home in a child process, not to affect the other tests
+        let home = std::env::temp_dir()
+            .join("auto-launch-sandbox/Library/Containers/com.example.test/Data");
+        fs::create_dir_all(&home).unwrap();
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "macos_unit_test::test_macos_sandbox"])
+            .env("HOME", &home)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{}", stdout);
+        assert!(stdout.contains("1 passed"), "{}", stdout);
+        assert!(!home.join("Library").join("LaunchAgents").exists());
+    }
+
+    #[test]
+    fn test_macos_app_path_not_exists() {
+        let app_path = "/Applications/AutoLaunchNotExists.app";
+        for use_launch_agent in [true, false] {
+            let auto = AutoLaunch::new(
+                "AutoLaunchNotExists",
+                app_path,
+                use_launch_agent,
+                &["--minimized"],
+                &[] as &[&str],
+                "",
+            );
+            let err = auto.enable().unwrap_err();
+            assert!(
+                matches!(&err, Error::AppPathDoesntExist(path) if path.to_str() == Some(app_path)),
+                "{:?}",
+                err
+            );
+            assert_eq!(
+                err.to_string(),
+                format!("app path doesn't exist: {}", app_path)
+            );
+            // nothing is written
+            assert!(!auto.is_enabled().unwrap());
+        }
+
+        // the relative path is resolved against the current dir
+        let auto = AutoLaunch::new(
+            "AutoLaunchNotExists",
+            "AutoLaunchNotExists.app",
+            false,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+        let expected = std::env::current_dir()
+            .unwrap()
+            .join("AutoLaunchNotExists.app");
+        assert!(matches!(
+            auto.enable(),
+            Err(Error::AppPathDoesntExist(path)) if path == expected
+        ));
+    }
+
+    #[test]
+    fn test_macos_label() {
+        let app_name = "AutoLaunchLabelTest";
+        let label = "com.example.auto-launch-test";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name(app_name)
+            .set_app_path(app_path)
+            .set_use_launch_agent(true)
+            .set_label(label)
+            .build()
+            .unwrap();
+        assert_eq!(auto.get_app_name(), app_name);
+        assert_eq!(auto.get_label(), label);
+
+        let (path, content) = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { path, content }] => (path.clone(), content.clone()),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        assert_eq!(
+            path.file_name().unwrap(),
+            "com.example.auto-launch-test.plist"
+        );
+        assert!(content.contains(&format!("<key>Label</key>\n  <string>{}</string>", label)));
+
+        auto.enable().unwrap();
+        assert!(path.exists());
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+        assert!(!path.exists());
+        assert!(!auto.is_enabled().unwrap());
+
+        // falls back to the app_name
+        let auto = AutoLaunch::new(
+            app_name,
+            app_path,
+            true,
+            &["--minimized"],
+            &[] as &[&str],
+            "",
+        );
+        assert_eq!(auto.get_label(), app_name);
+    }
+
+    #[test]
+    fn test_macos_plist_filename() {
+        let label = "com.example.auto-launch-label-test";
+        let app_path = get_test_bin("auto-launch-test");
+
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchPlistFilenameTest")
+            .set_app_path(&app_path)
+            .set_use_launch_agent(true)
+            .set_label(label)
+            .set_plist_filename("com.example.auto-launch-file-test.plist")
+            .build()
+            .unwrap();
+        assert_eq!(auto.get_label(), label);
+        assert_eq!(
+            auto.get_plist_filename(),
+            "com.example.auto-launch-file-test"
+        );
+
+        let (path, content) = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { path, content }] => (path.clone(), content.clone()),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        assert_eq!(
+            path.file_name().unwrap(),
+            "com.example.auto-launch-file-test.plist"
+        );
+        assert!(content.contains(&format!("<key>Label</key>\n  <string>{}</string>", label)));
+
+        auto.enable().unwrap();
+        assert!(path.exists());
+        assert!(!path.with_file_name(format!("{}.plist", label)).exists());
+        assert!(auto.is_enabled().unwrap());
+        assert_eq!(auto.enable().unwrap(), Change::Unchanged);
+        auto.disable().unwrap();
+        assert!(!path.exists());
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_macos_main() {
+        let app_name = "auto-launch-test";
+        let app_path = get_test_bin("auto-launch-test");
+        let args = &["--minimized", "--hidden"];
+        let app_path = app_path.as_str();
+
+        // path not exists
+        let app_name_not = "Calculator1";
+        let app_path_not = "/Applications/Calculator1.app";
+
+        // use applescript
+        let auto1 = AutoLaunch::new(app_name, app_path, false, args, &[] as &[&str], "");
+        assert_eq!(auto1.get_app_name(), app_name);
+        auto1.enable().unwrap();
+        assert!(auto1.is_enabled().unwrap());
+        auto1.disable().unwrap();
+        assert!(!auto1.is_enabled().unwrap());
+
+        // enable twice should not create duplicate login items
+        auto1.enable().unwrap();
+        auto1.enable().unwrap();
+        assert!(auto1.is_enabled().unwrap());
+        auto1.disable().unwrap();
+        assert!(!auto1.is_enabled().unwrap());
+
+        let auto2 = AutoLaunch::new(app_name_not, app_path_not, false, args, &[] as &[&str], "");
+        assert_eq!(auto2.get_app_name(), app_name_not);
+        assert!(matches!(
+            auto2.enable(),
+            Err(Error::AppPathDoesntExist(path)) if path.to_str() == Some(app_path_not)
+        ));
+        assert!(!auto2.is_enabled().unwrap());
+
+        // use launch agent
+        let auto1 = AutoLaunch::new(app_name, app_path, true, args, &[] as &[&str], "");
+        assert_eq!(auto1.get_app_name(), app_name);
+        auto1.enable().unwrap();
+        assert!(auto1.is_enabled().unwrap());
+        auto1.disable().unwrap();
+        assert!(!auto1.is_enabled().unwrap());
+
+        let auto2 = AutoLaunch::new(app_name, app_path_not, true, args, &[] as &[&str], "");
+        assert_eq!(auto2.get_app_name(), app_name); // will not change the name
+        assert!(matches!(
+            auto2.enable(),
+            Err(Error::AppPathDoesntExist(path)) if path.to_str() == Some(app_path_not)
+        ));
+        assert!(!auto2.is_enabled().unwrap());
+        auto2.disable().unwrap();
+        assert!(!auto2.is_enabled().unwrap());
+
+        // test builder
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name(app_name)
+            .set_app_path(app_path)
+            .set_args(args)
+            .build()
+            .unwrap();
+
+        assert_eq!(auto.get_app_name(), app_name);
+        auto.enable().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+
+        // use launch agent
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name(app_name)
+            .set_app_path(app_path)
+            .set_use_launch_agent(true)
+            .set_args(args)
+            .build()
+            .unwrap();
+
+        assert_eq!(auto.get_app_name(), app_name);
+        auto.enable().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod linux_unit_test {
+    use crate::unit_test::*;
+    use auto_launch::{
+        render_entry, Action, AutoLaunch, AutoLaunchBuilder, AutoLaunchGroup, Backend, Change,
+        EnabledStatus, EntryCustomizer, Error, PlannedChange, Platform, StateDiff,
+        LAUNCHED_AT_STARTUP_ARG,
+    };
+    use std::fs;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_linux() {
+        let app_name = "AutoLaunchTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let args = &["--minimized"];
+        let app_path = app_path.as_str();
+
+        // default test
+        let auto1 = AutoLaunch::new(app_name, app_path, args);
+
+        assert_eq!(auto1.get_app_name(), app_name);
+        auto1.enable().unwrap();
+        assert!(auto1.is_enabled().unwrap());
+        auto1.disable().unwrap();
+        assert!(!auto1.is_enabled().unwrap());
+
+        // test args
+        let auto2 = AutoLaunch::new(app_name, app_path, args);
+
+        assert_eq!(auto2.get_app_name(), app_name);
+        auto2.enable().unwrap();
+        assert!(auto2.is_enabled().unwrap());
+        auto2.disable().unwrap();
+        assert!(!auto2.is_enabled().unwrap());
+    }
+    #[test]
+    fn test_linux_plan() {
+        let app_name = "AutoLaunchPlanTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let args = &["--minimized"];
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(app_name, app_path, args);
+        assert_eq!(auto.plan(Action::Disable).unwrap(), vec![]);
+
+        let plan = auto.plan(Action::Enable).unwrap();
+        let (path, content) = match &plan[..] {
+            [PlannedChange::WriteFile { path, content }] => (path.clone(), content.clone()),
+            _ => panic!("unexpected plan: {:?}", plan),
+        };
+        // planning should not touch the system
+        assert!(!path.exists());
+        assert!(!auto.is_enabled().unwrap());
+
+        auto.enable().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(
+            auto.plan(Action::Disable).unwrap(),
+            vec![PlannedChange::RemoveFile { path: path.clone() }]
+        );
+
+        auto.disable().unwrap();
+        assert!(!path.exists());
+    }
+    #[test]
+    fn test_linux_list_entries() {
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto1 = AutoLaunch::new("AutoLaunchListTest1", app_path, &["--one"]);
+        let auto2 = AutoLaunch::new("AutoLaunchListTest2", app_path, &["--two"]);
+        auto1.enable().unwrap();
+        auto2.enable().unwrap();
+
+        let entries = AutoLaunch::list_entries().unwrap();
+        let find = |name: &str| entries.iter().find(|e| e.name == name).cloned();
+        assert_eq!(
+            find("AutoLaunchListTest1").unwrap().command,
+            format!("{} --one", app_path)
+        );
+        assert_eq!(
+            find("AutoLaunchListTest2").unwrap().command,
+            format!("{} --two", app_path)
+        );
+
+        // the broken entry of another app is skipped
+        let broken = auto1
+            .get_desktop_entry_path()
+            .unwrap()
+            .with_file_name("AutoLaunchListBroken.desktop");
+        fs::write(&broken, b"[Desktop Entry]\nExec=\xff\n").unwrap();
+        let entries = AutoLaunch::list_entries().unwrap();
+        assert!(entries.iter().any(|e| e.name == "AutoLaunchListTest1"));
+        assert!(entries.iter().all(|e| e.name != "AutoLaunchListBroken"));
+        fs::remove_file(&broken).unwrap();
+
+        auto1.disable().unwrap();
+        auto2.disable().unwrap();
+        let entries = AutoLaunch::list_entries().unwrap();
+        assert!(entries
+            .iter()
+            .all(|e| !e.name.starts_with("AutoLaunchListTest")));
+    }
+    #[test]
+    fn test_linux_normalize_identifier() {
+        let app_name = "My/App 2.0";
+        let app_path = get_test_bin("auto-launch-test");
+        let app_path = app_path.as_str();
+
+        let auto = AutoLaunch::new(app_name, app_path, &["--minimized"]);
+        let path = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { path, .. }] => path.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        assert_eq!(path.file_name().unwrap(), "My_App_2.0.desktop");
+
+        auto.enable().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        // the display name is kept
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("Name=My/App 2.0\n"));
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+
+        // the entry named by the raw app_name is migrated
+        let auto = AutoLaunch::new("AutoLaunch Legacy Test", app_path, &["--minimized"]);
+        let legacy = path.with_file_name("AutoLaunch Legacy Test.desktop");
+        fs::write(&legacy, "[Desktop Entry]").unwrap();
+        assert!(auto.is_enabled().unwrap());
+        auto.enable().unwrap();
+        assert!(!legacy.exists());
+        assert!(path
+            .with_file_name("AutoLaunch_Legacy_Test.desktop")
+            .exists());
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+    }
+    #[test]
+    fn test_linux_canonical_app_path() {
+        let app_name = "AutoLaunchCanonicalTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let canonical = fs::canonicalize(&app_path).unwrap();
+        let canonical = canonical.to_str().unwrap();
+
+        // relative path
+        let relative = "test-exe/target/release/auto-launch-test";
+        let auto = AutoLaunch::new(app_name, relative, &["--minimized"]);
+        assert_eq!(auto.get_app_path(), relative);
+        assert_eq!(auto.get_canonical_app_path(), canonical);
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => {
+                assert!(content.contains(&format!("Exec={} --minimized", canonical)));
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+
+        // home dir
+        let home = std::env::var("HOME").unwrap();
+        let auto = AutoLaunch::new(app_name, "~/auto-launch-test", &["--minimized"]);
+        assert_eq!(
+            auto.get_canonical_app_path(),
+            std::path::Path::new(&home)
+                .join("auto-launch-test")
+                .to_str()
+                .unwrap()
+        );
+
+        // symlink
+        let link = std::env::temp_dir().join("auto-launch-canonical-test");
+        fs::remove_file(&link).ok();
+        std::os::unix::fs::symlink(&app_path, &link).unwrap();
+        let auto = AutoLaunch::new(app_name, link.to_str().unwrap(), &["--minimized"]);
+        assert_eq!(auto.get_canonical_app_path(), canonical);
+        fs::remove_file(&link).unwrap();
+    }
+    #[test]
+    fn test_linux_wrap_in_login_shell() {
+        let app_path = "/path/to/the app";
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchLoginShellTest")
+            .set_app_path(app_path)
+            .set_args(&["--minimized", "--title=My App", "it's", "$HOME"])
+            .set_wrap_in_login_shell(true)
+            .build()
+            .unwrap();
+
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => {
+                let exec = content.lines().find(|x| x.starts_with("Exec=")).unwrap();
+                assert_eq!(
+                    exec,
+                    r#"Exec=sh -lc 'exec "/path/to/the app" --minimized "--title=My App" "it'\\''s" "\\$HOME"'"#
+                );
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+    }
+    #[test]
+    fn test_linux_diff() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new("AutoLaunchDiffTest", &app_path, &["--minimized"]);
+        auto.disable().unwrap();
+        assert_eq!(auto.diff().unwrap(), StateDiff::Missing);
+
+        auto.enable().unwrap();
+        assert_eq!(auto.diff().unwrap(), StateDiff::Matches);
+
+        // the entry written with the outdated args
+        let outdated = AutoLaunch::new("AutoLaunchDiffTest", &app_path, &["--hidden"]);
+        outdated.enable().unwrap();
+        assert_eq!(
+            auto.diff().unwrap(),
+            StateDiff::Differs {
+                actual: format!("{} --hidden", app_path)
+            }
+        );
+
+        auto.disable().unwrap();
+        assert_eq!(auto.diff().unwrap(), StateDiff::Missing);
+    }
+
+    #[test]
+    fn test_linux_active_backend() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchBackendTest")
+            .set_app_path(&app_path);
+        assert_eq!(
+            builder.build().unwrap().active_backend(),
+            Backend::DesktopEntry
+        );
+        builder.set_systemd_unit(true);
+        assert_eq!(
+            builder.build().unwrap().active_backend(),
+            Backend::SystemdUnit
+        );
+    }
+
+    /// The `EntryCustomizer` which appends the lines to the desktop entry
+    #[derive(Debug)]
+    struct AppendLines(&'static str);
+
+    impl EntryCustomizer for AppendLines {
+        fn customize(&self, content: String) -> String {
+            format!("{}\n{}", content, self.0)
+        }
+    }
+
+    #[test]
+    fn test_linux_entry_customizer() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchCustomizerTest")
+            .set_app_path(&app_path)
+            .set_entry_customizer(Arc::new(AppendLines(
+                "# approved by the IT department\nX-Org-Compliance=approved",
+            )));
+        let auto = builder.build().unwrap();
+        auto.disable().unwrap();
+
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        let content = fs::read_to_string(auto.get_desktop_entry_path().unwrap()).unwrap();
+        assert!(content.starts_with("[Desktop Entry]\n"));
+        assert!(content.ends_with(
+            "Terminal=false\n# approved by the IT department\nX-Org-Compliance=approved"
+        ));
+        assert!(auto.is_enabled().unwrap());
+        assert_eq!(auto.enable().unwrap(), Change::Unchanged);
+        auto.disable().unwrap();
+
+        // the result which doesn't parse is rejected before written
+        let auto = builder
+            .set_entry_customizer(Arc::new(AppendLines("not a key")))
+            .build()
+            .unwrap();
+        assert!(matches!(auto.enable(), Err(Error::InvalidInput(_))));
+        assert!(!auto.get_desktop_entry_path().unwrap().exists());
+    }
+
+    #[test]
+    fn test_linux_dbus_activatable() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchDBusTest")
+            .set_app_path(&app_path);
+
+        // omitted by default
+        let auto = builder.build().unwrap();
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => {
+                assert!(!content.contains("DBusActivatable"))
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+
+        builder.set_dbus_activatable(true);
+        match builder.build() {
+            Err(Error::InvalidInput(message)) => assert!(message.contains("dbus_name")),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        for name in ["AutoLaunchDBusTest", "org.example.", "org.3example.App"] {
+            let result = builder.set_dbus_name(name).build();
+            assert!(matches!(result, Err(Error::InvalidInput(_))), "{}", name);
+        }
+
+        // the desktop entry is named by the D-Bus name
+        let auto = builder
+            .set_dbus_name("org.example.AutoLaunchDBusTest")
+            .build()
+            .unwrap();
+        let path = auto.get_desktop_entry_path().unwrap();
+        assert!(path.ends_with(".config/autostart/org.example.AutoLaunchDBusTest.desktop"));
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => {
+                assert!(content.contains("\nDBusActivatable=true\n"));
+                assert!(content.contains(&format!("\nExec={}\n", app_path)));
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+        auto.enable().unwrap();
+        assert!(path.exists());
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_linux_display_name() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("auto-launch-display-name-test")
+            .set_app_path(&app_path)
+            .set_display_name("My Awesome App")
+            .build()
+            .unwrap();
+
+        // the file is named by the app_name, and the Name by the display name
+        let path = auto.get_desktop_entry_path().unwrap();
+        assert!(path.ends_with(".config/autostart/auto-launch-display-name-test.desktop"));
+        auto.enable().unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("\nName=My Awesome App\n"), "{}", content);
+        assert!(content.contains("\nComment=auto-launch-display-name-test startup script\n"));
+        assert!(auto.is_enabled().unwrap());
+
+        auto.disable().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_linux_require_app_path_exists() {
+        let app_path = get_test_bin("auto-launch-test");
+        let missing = format!("{}-missing", app_path);
+        let mut builder = AutoLaunchBuilder::new();
+        builder.set_app_name("AutoLaunchRequirePathTest");
+
+        // written regardless of the path by default
+        let auto = builder.set_app_path(&missing).build().unwrap();
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        auto.disable().unwrap();
+
+        builder.set_require_app_path_exists(true);
+        let auto = builder.build().unwrap();
+        match auto.enable() {
+            Err(Error::AppPathDoesntExist(path)) => assert_eq!(path.to_string_lossy(), missing),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert!(matches!(
+            auto.enable_once(),
+            Err(Error::AppPathDoesntExist(_))
+        ));
+        assert!(!auto.get_desktop_entry_path().unwrap().exists());
+
+        let auto = builder.set_app_path(&app_path).build().unwrap();
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        assert!(auto.is_enabled().unwrap());
+        auto.disable().unwrap();
+
+        // the bare command name is looked up in PATH
+        builder.set_use_path_lookup(true);
+        let auto = builder.set_app_path("sh").build().unwrap();
+        assert!(auto.enable().is_ok());
+        auto.disable().unwrap();
+        let auto = builder
+            .set_app_path("auto-launch-no-such-command")
+            .build()
+            .unwrap();
+        assert!(matches!(auto.enable(), Err(Error::AppPathDoesntExist(_))));
+    }
+
+    #[test]
+    fn test_linux_group() {
+        let app_path = get_test_bin("auto-launch-test");
+        let build = |name: &str, path: &str| {
+            AutoLaunchBuilder::new()
+                .set_app_name(name)
+                .set_app_path(path)
+                .set_require_app_path_exists(true)
+                .build()
+                .unwrap()
+        };
+        let mut group = AutoLaunchGroup::new(vec![build("AutoLaunchGroupTestA", &app_path)]);
+        // the member in between fails, as its path doesn't exist
+        group
+            .add(build("AutoLaunchGroupTestB", "/auto-launch-no-such-path"))
+            .add(build("AutoLaunchGroupTestC", &app_path));
+        assert_eq!(group.members().len(), 3);
+
+        let results = group.enable_all();
+        assert!(matches!(results[0], Ok(Change::Changed)));
+        assert!(matches!(results[1], Err(Error::AppPathDoesntExist(_))));
+        assert!(matches!(results[2], Ok(Change::Changed)));
+
+        let statuses = group.statuses();
+        assert!(matches!(statuses[0], Ok(EnabledStatus::Enabled)));
+        assert!(matches!(statuses[1], Ok(EnabledStatus::NotRegistered)));
+        assert!(matches!(statuses[2], Ok(EnabledStatus::Enabled)));
+
+        let results = group.disable_all();
+        assert!(matches!(results[0], Ok(Change::Changed)));
+        assert!(matches!(results[1], Ok(Change::Unchanged)));
+        assert!(matches!(results[2], Ok(Change::Changed)));
+        assert!(group.members().iter().all(|x| !x.is_enabled().unwrap()));
+    }
+
+    #[test]
+    fn test_linux_export_entry() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new("AutoLaunchExportTest", &app_path, &["--minimized"]);
+        auto.disable().unwrap();
+        assert!(auto.export_entry().is_err());
+
+        auto.enable().unwrap();
+        let export = auto.export_entry().unwrap();
+        let path = auto.get_desktop_entry_path().unwrap();
+        assert!(export.starts_with(&format!("# {}\n", path.display())));
+        assert!(export.contains(&format!("Exec={} --minimized", app_path)));
+
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_linux_desktop_entry_path() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new("AutoLaunch Path/Test", &app_path, &["--minimized"]);
+        let path = auto.get_desktop_entry_path().unwrap();
+        assert!(path.ends_with(".config/autostart/AutoLaunch_Path_Test.desktop"));
+
+        auto.disable().unwrap();
+        assert!(!path.exists());
+        auto.enable().unwrap();
+        assert!(path.exists());
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("Name=AutoLaunch Path/Test"));
+        auto.disable().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_linux_path_lookup() {
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchPathLookupTest")
+            .set_app_path("myapp")
+            .set_args(&["--flag"])
+            .set_use_path_lookup(true);
+
+        let auto = builder.build().unwrap();
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => {
+                assert!(content.contains("\nExec=myapp --flag\n"))
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+        auto.enable().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        assert_eq!(auto.diff().unwrap(), StateDiff::Matches);
+        assert_eq!(auto.enable().unwrap(), Change::Unchanged);
+        auto.disable().unwrap();
+
+        for app_path in ["", "bin/myapp", "/usr/bin/myapp"] {
+            let result = builder.set_app_path(app_path).build();
+            assert!(matches!(result, Err(Error::InvalidInput(_))));
+        }
+    }
+
+    #[test]
+    fn test_linux_systemd_unit() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchSystemdTest")
+            .set_app_path(&app_path)
+            .set_args(&["--minimized"])
+            .set_systemd_unit(true)
+            .build()
+            .unwrap();
+        let (unit, link) = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { .. }, PlannedChange::WriteFile { path, content }, PlannedChange::CreateSymlink { path: link, target }] =>
+            {
+                assert_eq!(target, path);
+                assert!(content.contains("ExecStart="));
+                assert!(content.contains(" --minimized\n"));
+                assert!(content.contains("WantedBy=default.target"));
+                (path.clone(), link.clone())
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let entry = auto.get_desktop_entry_path().unwrap();
+
+        // both artifacts are written
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        assert!(entry.exists() && unit.exists() && link.exists());
+        assert_eq!(fs::read_link(&link).unwrap(), unit);
+        assert_eq!(auto.enable().unwrap(), Change::Unchanged);
+
+        // enabled if only one of them is present
+        fs::remove_file(&entry).unwrap();
+        assert!(auto.is_enabled().unwrap());
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        fs::remove_file(&link).unwrap();
+        assert!(auto.is_enabled().unwrap());
+        assert_eq!(auto.enable().unwrap(), Change::Changed);
+        assert!(link.exists());
+
+        // both artifacts are removed
+        assert_eq!(auto.disable().unwrap(), Change::Changed);
+        assert!(!entry.exists() && !unit.exists() && fs::symlink_metadata(&link).is_err());
+        assert!(!auto.is_enabled().unwrap());
+        assert_eq!(auto.disable().unwrap(), Change::Unchanged);
+    }
+
+    #[test]
+    fn test_linux_systemd_unit_rollback() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchSystemdRollbackTest")
+            .set_app_path(&app_path)
+            .set_args(&["--old"]);
+        let previous = builder.build().unwrap();
+        let auto = builder
+            .set_args(&["--new"])
+            .set_systemd_unit(true)
+            .build()
+            .unwrap();
+        let unit = match &auto.plan(Action::Enable).unwrap()[..] {
+            [_, PlannedChange::WriteFile { path, .. }, ..] => path.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let entry = auto.get_desktop_entry_path().unwrap();
+        // the unit can't be written over a dir
+        fs::create_dir_all(unit.join("blocked")).unwrap();
+
+        // the existing entry is restored
+        previous.enable().unwrap();
+        let content = fs::read_to_string(&entry).unwrap();
+        assert!(auto.enable().is_err());
+        assert_eq!(fs::read_to_string(&entry).unwrap(), content);
+
+        // the entry created by the call is removed
+        previous.disable().unwrap();
+        assert!(auto.enable().is_err());
+        assert!(!entry.exists());
+
+        fs::remove_dir_all(&unit).unwrap();
+    }
+
+    #[test]
+    fn test_linux_set_exec_args() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchSetExecArgsTest")
+            .set_app_path(&app_path)
+            .set_args(&["--old"])
+            .build()
+            .unwrap();
+        let file = auto.get_desktop_entry_path().unwrap();
+        let get_exec = || {
+            let content = fs::read_to_string(&file).unwrap();
+            let exec = content.lines().find_map(|x| x.strip_prefix("Exec="));
+            exec.unwrap().to_string()
+        };
+
+        let result = auto.set_exec_args(&["--new"]);
+        assert!(matches!(result, Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound));
+
+        // the unrelated key edited manually survives
+        auto.enable().unwrap();
+        let program = get_exec().strip_suffix(" --old").unwrap().to_string();
+        let content = fs::read_to_string(&file).unwrap();
+        fs::write(
+            &file,
+            content.replace("Terminal=false", "Terminal=true\nX-Custom=keep"),
+        )
+        .unwrap();
+        auto.set_exec_args(&["--new", "a b"]).unwrap();
+        assert_eq!(get_exec(), format!("{} --new \"a b\"", program));
+        let content = fs::read_to_string(&file).unwrap();
+        assert!(content.contains("\nTerminal=true\nX-Custom=keep"));
+
+        // the `Exec` of the actions are kept
+        fs::write(
+            &file,
+            "[Desktop Entry]\nExec=app --old\n\
+            [Desktop Action new]\nExec=app --action\n",
+        )
+        .unwrap();
+        auto.set_exec_args(&["--new"]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            format!(
+                "[Desktop Entry]\nExec={} --new\n\
+                [Desktop Action new]\nExec=app --action\n",
+                program
+            )
+        );
+
+        auto.disable().unwrap();
+    }
+
+    /// The `Exec` is rebuilt the same way as `enable`, with the shell wrapping and the unit
+    #[test]
+    fn test_linux_set_exec_args_wrapped() {
+        let app_path = get_test_bin("auto-launch-test");
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchSetExecArgsWrappedTest")
+            .set_app_path(&app_path)
+            .set_wrap_in_login_shell(true)
+            .set_systemd_unit(true)
+            .set_startup_sentinel(true)
+            .set_args(&["--old"]);
+        let auto = builder.build().unwrap();
+        let get_contents = |auto: &AutoLaunch| match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content: entry, .. }, PlannedChange::WriteFile {
+                path,
+                content: unit,
+            }, ..] => (entry.clone(), path.clone(), unit.clone()),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        auto.enable().unwrap();
+
+        // the same as enabled with the new args
+        auto.set_exec_args(&["--new", "a b"]).unwrap();
+        let expected = builder.set_args(&["--new", "a b"]).build().unwrap();
+        let (entry, unit_path, unit) = get_contents(&expected);
+        let content = fs::read_to_string(auto.get_desktop_entry_path().unwrap()).unwrap();
+        assert_eq!(content, entry);
+        assert!(content.contains("Exec=sh -lc 'exec "), "{}", content);
+        assert!(content.contains(LAUNCHED_AT_STARTUP_ARG), "{}", content);
+        assert_eq!(fs::read_to_string(&unit_path).unwrap(), unit);
+        assert!(expected.is_enabled().unwrap());
+        assert_eq!(expected.enable().unwrap(), Change::Unchanged);
+
+        auto.disable().unwrap();
+    }
+
+    /// The entry of `enable_once` still removes itself
+    #[test]
+    fn test_linux_set_exec_args_once() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new("AutoLaunchSetExecArgsOnceTest", &app_path, &["--old"]);
+        auto.disable().unwrap();
+        auto.enable_once().unwrap();
+
+        auto.set_exec_args(&["--new"]).unwrap();
+        let content = fs::read_to_string(auto.get_desktop_entry_path().unwrap()).unwrap();
+        let exec = content
+            .lines()
+            .find_map(|x| x.strip_prefix("Exec="))
+            .unwrap();
+        assert!(exec.starts_with("sh -c 'rm -f "), "{}", exec);
+        assert!(exec.ends_with(" --new'"), "{}", exec);
+
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_linux_startup_wm_class() {
+        let app_path = get_test_bin("auto-launch-test");
+        let get_entry = |auto: &AutoLaunch| match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => content.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchWMClassTest")
+            .set_app_path(&app_path);
+
+        // omitted when unset
+        let auto = builder.build().unwrap();
+        assert!(!get_entry(&auto).contains("StartupWMClass"));
+
+        let auto = builder.set_startup_wm_class("the-app").build().unwrap();
+        assert!(get_entry(&auto).contains("\nStartupWMClass=the-app\n"));
+    }
+
+    #[test]
+    fn test_linux_generic_name_and_keywords() {
+        let app_path = get_test_bin("auto-launch-test");
+        let get_entry = |auto: &AutoLaunch| match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => content.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchSearchTest")
+            .set_app_path(&app_path);
+
+        // omitted when unset
+        let entry = get_entry(&builder.build().unwrap());
+        assert!(!entry.contains("GenericName"));
+        assert!(!entry.contains("Keywords"));
+
+        let auto = builder
+            .set_generic_name("Test Runner")
+            .set_keywords(&["test", "auto;launch"])
+            .build()
+            .unwrap();
+        let entry = get_entry(&auto);
+        assert!(entry.contains("\nGenericName=Test Runner\n"));
+        assert!(entry.contains("\nKeywords=test;auto\\;launch;\n"));
+    }
+
+    #[test]
+    fn test_linux_working_dir() {
+        let app_path = get_test_bin("auto-launch-test");
+        let get_entry = |auto: &AutoLaunch| match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => content.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchWorkingDirTest")
+            .set_app_path(&app_path)
+            .set_args(&["--minimized"])
+            .set_working_dir("/opt/the app");
+
+        // the standard `Path` key by default
+        let entry = get_entry(&builder.build().unwrap());
+        assert!(entry.contains("\nPath=/opt/the app\n"));
+        assert!(entry.contains(&format!("\nExec={} --minimized\n", app_path)));
+
+        // wrapped in `sh -c` when requested
+        let entry = get_entry(&builder.set_working_dir_via_shell(true).build().unwrap());
+        assert!(!entry.contains("\nPath="));
+        assert!(entry.contains(&format!(
+            "\nExec=sh -c 'cd \"/opt/the app\" && exec {} --minimized'\n",
+            app_path
+        )));
+    }
+
+    #[test]
+    fn test_linux_enabled_status() {
+        let app_name = "AutoLaunchEnabledStatusTest";
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new(app_name, &app_path, &["--one"]);
+        assert_eq!(auto.enabled_status().unwrap(), EnabledStatus::NotRegistered);
+
+        auto.enable().unwrap();
+        assert_eq!(auto.enabled_status().unwrap(), EnabledStatus::Enabled);
+
+        let other = AutoLaunch::new(app_name, &app_path, &["--two"]);
+        assert_eq!(
+            other.enabled_status().unwrap(),
+            EnabledStatus::RegisteredButPathMismatch
+        );
+
+        // disabled by the desktop environment, e.g. GNOME Tweaks
+        let file = auto.get_desktop_entry_path().unwrap();
+        let content = fs::read_to_string(&file).unwrap();
+        fs::write(&file, content + "\nX-GNOME-Autostart-enabled=false").unwrap();
+        assert_eq!(
+            auto.enabled_status().unwrap(),
+            EnabledStatus::RegisteredButDisabledByOs
+        );
+
+        auto.disable().unwrap();
+        assert_eq!(auto.enabled_status().unwrap(), EnabledStatus::NotRegistered);
+    }
+
+    #[test]
+    fn test_linux_description() {
+        let app_path = get_test_bin("auto-launch-test");
+        let get_comment = |auto: &AutoLaunch| {
+            let content = match &auto.plan(Action::Enable).unwrap()[..] {
+                [PlannedChange::WriteFile { content, .. }] => content.clone(),
+                plan => panic!("unexpected plan: {:?}", plan),
+            };
+            let comment = content.lines().find_map(|x| x.strip_prefix("Comment="));
+            comment.unwrap().to_string()
+        };
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchDescriptionTest")
+            .set_app_path(&app_path);
+
+        let auto = builder.build().unwrap();
+        assert_eq!(
+            auto.get_description(),
+            "AutoLaunchDescriptionTest startup script"
+        );
+        assert_eq!(
+            get_comment(&auto),
+            "AutoLaunchDescriptionTest startup script"
+        );
+
+        let auto = builder
+            .set_description("Sync the files in background")
+            .build()
+            .unwrap();
+        assert_eq!(auto.get_description(), "Sync the files in background");
+        assert_eq!(get_comment(&auto), "Sync the files in background");
+    }
+
+    /// Run in a child process with a temp `HOME`, not to affect the other tests
+    #[test]
+    fn test_linux_disable_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let app_path = get_test_bin("auto-launch-test");
+        if std::env::var_os("AUTO_LAUNCH_TEST_CHILD").is_none() {
+            let home = std::env::temp_dir().join("auto-launch-permission-test");
+            fs::create_dir_all(&home).unwrap();
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "linux_unit_test::test_linux_disable_permission_denied",
+                    "--exact",
+                ])
+                .env("AUTO_LAUNCH_TEST_CHILD", "1")
+                .env("HOME", &home)
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "{}", stdout);
+            assert!(stdout.contains("1 passed"), "{}", stdout);
+            return;
+        }
+
+        let auto = AutoLaunch::new("AutoLaunchPermissionTest", &app_path, &["--minimized"]);
+        auto.enable().unwrap();
+        let dir = auto
+            .get_desktop_entry_path()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        let set_mode = |mode| fs::set_permissions(&dir, fs::Permissions::from_mode(mode)).unwrap();
+
+        // the entries in a read-only dir are not removable, unless run as root
+        set_mode(0o555);
+        let writable = fs::write(dir.join("probe"), "").is_ok();
+        if !writable {
+            let err = auto.disable().unwrap_err();
+            assert!(matches!(err, Error::PermissionDenied(_)), "{:?}", err);
+            assert!(auto.is_enabled().unwrap());
+        }
+
+        set_mode(0o755);
+        fs::remove_file(dir.join("probe")).ok();
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    /// Run in a child process with a temp `HOME`, not to affect the other tests
+    #[test]
+    fn test_linux_file_retry() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let app_path = get_test_bin("auto-launch-test");
+        if std::env::var_os("AUTO_LAUNCH_TEST_CHILD").is_none() {
+            let home = std::env::temp_dir().join("auto-launch-file-retry-test");
+            fs::create_dir_all(&home).unwrap();
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["linux_unit_test::test_linux_file_retry", "--exact"])
+                .env("AUTO_LAUNCH_TEST_CHILD", "1")
+                .env("HOME", &home)
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "{}", stdout);
+            assert!(stdout.contains("1 passed"), "{}", stdout);
+            return;
+        }
+
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchFileRetryTest")
+            .set_app_path(&app_path)
+            .set_file_retry_count(5);
+        let auto = builder.build().unwrap();
+        auto.enable().unwrap();
+        auto.disable().unwrap();
+        let dir = auto
+            .get_desktop_entry_path()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        let set_mode = |mode| fs::set_permissions(&dir, fs::Permissions::from_mode(mode)).unwrap();
+
+        // the dir is locked briefly, unless run as root
+        set_mode(0o555);
+        let writable = fs::write(dir.join("probe"), "").is_ok();
+        if !writable {
+            let unlock = {
+                let dir = dir.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(std::time::Duration::from_millis(60));
+                    fs::set_permissions(dir, fs::Permissions::from_mode(0o755)).unwrap();
+                })
+            };
+            // recovered by the retries
+            auto.enable().unwrap();
+            unlock.join().unwrap();
+            assert!(auto.is_enabled().unwrap());
+
+            // the error is returned at once without retries
+            set_mode(0o555);
+            let auto = builder.set_file_retry_count(0).build().unwrap();
+            let err = auto.disable().unwrap_err();
+            assert!(matches!(err, Error::PermissionDenied(_)), "{:?}", err);
+        }
+
+        set_mode(0o755);
+        fs::remove_file(dir.join("probe")).ok();
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    /// Run in a child process with a missing `HOME`, not to affect the other tests
+    #[test]
+    fn test_linux_disable_missing_dir() {
+        let app_path = get_test_bin("auto-launch-test");
+        let home = std::env::temp_dir().join("auto-launch-missing-home-test");
+        if std::env::var_os("AUTO_LAUNCH_TEST_CHILD").is_none() {
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["linux_unit_test::test_linux_disable_missing_dir", "--exact"])
+                .env("AUTO_LAUNCH_TEST_CHILD", "1")
+                .env("HOME", &home)
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "{}", stdout);
+            assert!(stdout.contains("1 passed"), "{}", stdout);
+            return;
+        }
+
+        assert!(!home.exists());
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchMissingDirTest")
+            .set_app_path(&app_path);
+        assert_eq!(
+            builder.build().unwrap().disable().unwrap(),
+            Change::Unchanged
+        );
+        let auto = builder.set_systemd_unit(true).build().unwrap();
+        assert_eq!(auto.disable().unwrap(), Change::Unchanged);
+        assert!(!home.exists());
+    }
+
+    /// Run in a child process as a user without the passwd entry nor `HOME`,
+    /// so that there is no home dir at all
+    ///
+    /// Only root can switch the user, otherwise the test does nothing.
+    #[test]
+    fn test_linux_no_home_dir() {
+        use std::os::unix::fs::MetadataExt;
+        use std::os::unix::process::CommandExt;
+
+        if std::env::var_os("AUTO_LAUNCH_TEST_CHILD").is_none() {
+            if fs::metadata("/proc/self").unwrap().uid() != 0 {
+                return;
+            }
+            // a copy of the test bin, executable by the other user
+            let dir = std::env::temp_dir().join("auto-launch-no-home-test");
+            fs::create_dir_all(&dir).unwrap();
+            let exe = dir.join("test");
+            fs::copy(std::env::current_exe().unwrap(), &exe).unwrap();
+            let output = std::process::Command::new(&exe)
+                .args(["linux_unit_test::test_linux_no_home_dir", "--exact"])
+                .env("AUTO_LAUNCH_TEST_CHILD", "1")
+                .env_remove("HOME")
+                .uid(54321)
+                .gid(54321)
+                .output()
+                .unwrap();
+            fs::remove_dir_all(&dir).ok();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "{}", stdout);
+            assert!(stdout.contains("1 passed"), "{}", stdout);
+            return;
+        }
+
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchNoHomeDirTest")
+            .set_app_path("/bin/sh")
+            .set_systemd_unit(true)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            auto.get_desktop_entry_path(),
+            Err(Error::HomeDirNotFound)
+        ));
+        assert!(matches!(
+            auto.plan(Action::Enable),
+            Err(Error::HomeDirNotFound)
+        ));
+        assert!(matches!(auto.enable(), Err(Error::HomeDirNotFound)));
+        assert!(matches!(auto.is_enabled(), Err(Error::HomeDirNotFound)));
+        assert!(matches!(
+            AutoLaunch::list_entries(),
+            Err(Error::HomeDirNotFound)
+        ));
+        // nothing was ever written without the home dir
+        assert_eq!(auto.disable().unwrap(), Change::Unchanged);
+    }
+
+    #[test]
+    fn test_linux_symlinked_home() {
+        let app_path = get_test_bin("auto-launch-test");
+        let root = std::env::temp_dir().join("auto-launch-symlinked-home-test");
+        let (real, link) = (root.join("real"), root.join("link"));
+        if std::env::var_os("AUTO_LAUNCH_TEST_CHILD").is_none() {
+            fs::remove_dir_all(&root).ok();
+            fs::create_dir_all(&real).unwrap();
+            std::os::unix::fs::symlink(&real, &link).unwrap();
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["linux_unit_test::test_linux_symlinked_home", "--exact"])
+                .env("AUTO_LAUNCH_TEST_CHILD", "1")
+                .env("HOME", &link)
+                .output()
+                .unwrap();
+            fs::remove_dir_all(&root).ok();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "{}", stdout);
+            assert!(stdout.contains("1 passed"), "{}", stdout);
+            return;
+        }
+
+        let auto = AutoLaunch::new("AutoLaunchSymlinkedHomeTest", &app_path, &["--minimized"]);
+        // the entry is under the resolved home dir
+        let real = fs::canonicalize(&real).unwrap();
+        assert!(auto.get_desktop_entry_path().unwrap().starts_with(&real));
+
+        auto.enable().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        // and visible via the symlink
+        assert_eq!(
+            link.join(".config/autostart").read_dir().unwrap().count(),
+            1
+        );
+
+        auto.disable().unwrap();
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_linux_entry_count() {
+        // a copy of the test bin, not to count the entries of the other tests
+        let app_path = std::env::temp_dir().join("auto-launch-entry-count-test");
+        fs::copy(get_test_bin("auto-launch-test"), &app_path).unwrap();
+        let app_path = app_path.to_str().unwrap();
+
+        let auto = AutoLaunch::new("AutoLaunchEntryCountTest", app_path, &["--one"]);
+        auto.disable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 0);
+        auto.enable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 1);
+
+        // a duplicate under another name launching the same app
+        let duplicate = AutoLaunch::new("AutoLaunchEntryCountDuplicate", app_path, &["--two"]);
+        duplicate.enable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 2);
+        // also launched via the `sh -c` wrapper
+        let wrapped = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchEntryCountWrapped")
+            .set_app_path(app_path)
+            .set_wrap_in_login_shell(true)
+            .build()
+            .unwrap();
+        wrapped.enable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 3);
+
+        // the other apps of the path prefixed by the app path
+        let helper = format!("{}-helper", app_path);
+        let others = [
+            AutoLaunch::new("AutoLaunchEntryCountHelper", &helper, &["--one"]),
+            AutoLaunchBuilder::new()
+                .set_app_name("AutoLaunchEntryCountHelperWrapped")
+                .set_app_path(&helper)
+                .set_wrap_in_login_shell(true)
+                .build()
+                .unwrap(),
+        ];
+        for other in &others {
+            other.enable().unwrap();
+        }
+        // the broken entry of another app is skipped
+        let broken = auto
+            .get_desktop_entry_path()
+            .unwrap()
+            .with_file_name("auto-launch-entry-count-broken.desktop");
+        fs::write(&broken, b"[Desktop Entry]\nExec=\xff\xfe\n").unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 3);
+
+        fs::remove_file(broken).unwrap();
+        for other in &others {
+            other.disable().unwrap();
+        }
+        wrapped.disable().unwrap();
+        duplicate.disable().unwrap();
+        auto.disable().unwrap();
+        assert_eq!(auto.entry_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_linux_raw_args() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchRawArgsTest")
+            .set_app_path(&app_path)
+            .set_raw_args(r#"--a --b="x y""#)
+            .build()
+            .unwrap();
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => {
+                let exec = content.lines().find_map(|x| x.strip_prefix("Exec="));
+                assert!(exec.unwrap().ends_with(r#" --a --b="x y""#));
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+    }
+
+    #[test]
+    fn test_linux_startup_sentinel() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunchBuilder::new()
+            .set_app_name("AutoLaunchSentinelTest")
+            .set_app_path(&app_path)
+            .set_args(&["--minimized"])
+            .set_startup_sentinel(true)
+            .build()
+            .unwrap();
+        match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => {
+                let exec = content.lines().find_map(|x| x.strip_prefix("Exec="));
+                let expected = format!(" --minimized {}", LAUNCHED_AT_STARTUP_ARG);
+                assert!(exec.unwrap().ends_with(&expected), "{}", content);
+            }
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+    }
+
+    #[test]
+    fn test_linux_render_entry_matches_plan() {
+        let app_path = std::fs::canonicalize(get_test_bin("auto-launch-test")).unwrap();
+        let mut builder = AutoLaunchBuilder::new();
+        builder
+            .set_app_name("AutoLaunchRenderTest")
+            .set_app_path(app_path.to_str().unwrap())
+            .set_args(&["--minimized", "--title=the app"]);
+        let rendered = render_entry(Platform::Linux, &builder).unwrap();
+        match &builder.build().unwrap().plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => assert_eq!(content, &rendered),
+            plan => panic!("unexpected plan: {:?}", plan),
+        }
+    }
+
+    #[test]
+    fn test_linux_placeholders() {
+        let app_path = get_test_bin("auto-launch-test");
+        let args = &[
+            "--config=${HOME}/app.toml",
+            "${AUTO_LAUNCH_UNSET_VAR}",
+            "$HOME",
+        ];
+        let auto = AutoLaunch::new("AutoLaunchPlaceholderTest", &app_path, args);
+        let content = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => content.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let home = std::env::var("HOME").unwrap();
+        // resolved into the literal value, the unset and the plain `$` are kept
+        let exec = format!(
+            "Exec={} --config={}/app.toml \"\\\\${{AUTO_LAUNCH_UNSET_VAR}}\" \"\\\\$HOME\"",
+            app_path, home
+        );
+        assert!(content.contains(&exec), "{}", content);
+        assert_eq!(auto.get_args(), args);
+    }
+
+    #[test]
+    fn test_linux_purge_all() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new("AutoLaunch Purge Test", &app_path, &["--minimized"]);
+        auto.enable().unwrap();
+        let path = auto.get_desktop_entry_path().unwrap();
+        // the entry written by the former versions
+        let legacy = path.with_file_name("AutoLaunch Purge Test.desktop");
+        fs::write(&legacy, "[Desktop Entry]").unwrap();
+
+        auto.purge_all().unwrap();
+        assert!(!path.exists());
+        assert!(!legacy.exists());
+        assert!(!auto.is_enabled().unwrap());
+        // nothing to purge
+        auto.purge_all().unwrap();
+    }
+
+    #[test]
+    fn test_linux_atomic_write() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new("AutoLaunchAtomicTest", &app_path, &["--minimized"]);
+        let (path, content) = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { path, content }] => (path.clone(), content.clone()),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+
+        // the stale entry is replaced as a whole
+        auto.disable().unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "[Desktop Entry]\nExec=stale").unwrap();
+        auto.enable().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        // no temp file lingers
+        let lingering = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|x| x.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|x| x.starts_with(".AutoLaunchAtomicTest.desktop."))
+            .collect::<Vec<_>>();
+        assert!(lingering.is_empty(), "temp files left: {:?}", lingering);
+
+        auto.disable().unwrap();
+    }
+
+    #[test]
+    fn test_linux_session_supports_autostart() {
+        // the session is set up in a child process per case, not to race with the other tests
+        if std::env::var_os("AUTO_LAUNCH_TEST_CHILD").is_none() {
+            let keys = ["DISPLAY", "WAYLAND_DISPLAY", "XDG_CURRENT_DESKTOP"];
+            let check = |session: [Option<&str>; 3], expected: bool| {
+                let mut command = std::process::Command::new(std::env::current_exe().unwrap());
+                command
+                    .args([
+                        "linux_unit_test::test_linux_session_supports_autostart",
+                        "--exact",
+                    ])
+                    .env("AUTO_LAUNCH_TEST_CHILD", "1")
+                    .env("AUTO_LAUNCH_TEST_EXPECTED", expected.to_string());
+                for (key, value) in keys.iter().zip(session) {
+                    match value {
+                        Some(value) => command.env(key, value),
+                        None => command.env_remove(key),
+                    };
+                }
+                let output = command.output().unwrap();
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                assert!(output.status.success(), "{:?}: {}", session, stdout);
+                assert!(stdout.contains("1 passed"), "{}", stdout);
+            };
+
+            // headless
+            check([None, None, None], false);
+            check([None, None, Some("GNOME")], false);
+            check([Some(""), None, Some("GNOME")], false);
+            // desktop environments
+            check([Some(":0"), None, Some("GNOME")], true);
+            check([None, Some("wayland-0"), Some("ubuntu:GNOME")], true);
+            check([Some(":0"), None, Some("KDE")], true);
+            // bare window managers
+            check([Some(":0"), None, None], false);
+            check([Some(":0"), None, Some("i3")], false);
+            check([None, Some("wayland-1"), Some("sway")], false);
+            return;
+        }
+
+        let expected = std::env::var("AUTO_LAUNCH_TEST_EXPECTED").unwrap();
+        assert_eq!(
+            AutoLaunch::session_supports_autostart().to_string(),
+            expected
+        );
+    }
+
+    /// Get the `Exec` value of the desktop entry, with the string value unescaped
+    fn get_exec_command(content: &str) -> String {
+        let exec = content
+            .lines()
+            .find_map(|x| x.strip_prefix("Exec="))
+            .unwrap();
+
+        let mut command = String::new();
+        let mut chars = exec.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' | '%' => command.push(chars.next().unwrap()),
+                c => command.push(c),
+            }
+        }
+        command
+    }
+
+    #[test]
+    fn test_linux_enable_once() {
+        let app_path = get_test_bin("auto-launch-test");
+        let auto = AutoLaunch::new("AutoLaunchOnceTest", &app_path, &["--minimized"]);
+
+        let (path, content) = match &auto.plan(Action::EnableOnce).unwrap()[..] {
+            [PlannedChange::WriteFile { path, content }] => (path.clone(), content.clone()),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        let command = get_exec_command(&content);
+        assert_eq!(
+            command,
+            format!(
+                "sh -c 'rm -f {}; exec {} --minimized'",
+                path.display(),
+                app_path
+            )
+        );
+
+        auto.enable_once().unwrap();
+        assert!(auto.is_enabled().unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        // the desktop entry removes itself on launch
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(!auto.is_enabled().unwrap());
+    }
+
+    #[test]
+    fn test_linux_args_quoting() {
+        let app_path = "/path/to/the app";
+        let args = [
+            "--title=My App",
+            r#"say "hi""#,
+            r"C:\dir\",
+            "100%",
+            "$HOME",
+            "",
+        ];
+        let auto = AutoLaunch::new("AutoLaunchQuotingTest", app_path, &args);
+
+        let content = match &auto.plan(Action::Enable).unwrap()[..] {
+            [PlannedChange::WriteFile { content, .. }] => content.clone(),
+            plan => panic!("unexpected plan: {:?}", plan),
+        };
+        // split the command with the shell quoting rules
+        let command = get_exec_command(&content);
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf '%s\\0' {}", command))
+            .output()
+            .unwrap();
+        let argv = String::from_utf8(output.stdout).unwrap();
+        let argv = argv
+            .strip_suffix('\0')
+            .unwrap()
+            .split('\0')
+            .collect::<Vec<_>>();
+
+        assert_eq!(argv[0], app_path);
+        assert_eq!(argv[1..], args);
     }
 }